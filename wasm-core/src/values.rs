@@ -1,64 +1,128 @@
-use std::{any::type_name, fmt::Debug};
+use std::{any::type_name, borrow::Cow, fmt::Debug};
 
 use nom::{
+    bytes::complete::take,
     combinator::opt,
-    error::{dbg_dmp, ContextError, ParseError},
+    error::{dbg_dmp, ContextError, ErrorKind, ParseError},
     multi::count,
     IResult,
 };
-use nom_leb128::{leb128_i32, leb128_i64, leb128_u32};
+use nom_leb128::{leb128_i32, leb128_i64, leb128_u32, leb128_u64};
 
-impl Parse for u32 {
-    fn parse<'a, E>(i: &'a [u8]) -> IResult<&[u8], Self, E>
+impl<'a> Parse<'a> for u32 {
+    fn parse<E>(i: &'a [u8]) -> IResult<&'a [u8], Self, E>
     where
         E: ParseError<&'a [u8]> + ContextError<&'a [u8]> + std::fmt::Debug,
     {
         leb128_u32(i)
     }
 }
-impl Parse for u8 {
-    fn parse<'a, E>(i: &'a [u8]) -> IResult<&[u8], Self, E>
+impl<'a> Parse<'a> for u64 {
+    fn parse<E>(i: &'a [u8]) -> IResult<&'a [u8], Self, E>
+    where
+        E: ParseError<&'a [u8]> + ContextError<&'a [u8]> + std::fmt::Debug,
+    {
+        leb128_u64(i)
+    }
+}
+impl<'a> Parse<'a> for u8 {
+    fn parse<E>(i: &'a [u8]) -> IResult<&'a [u8], Self, E>
     where
         E: ParseError<&'a [u8]> + ContextError<&'a [u8]> + std::fmt::Debug,
     {
         nom::number::complete::u8(i)
     }
 }
-impl Parse for Name {
-    fn parse<'a, E>(i: &'a [u8]) -> IResult<&[u8], Self, E>
+impl<'a> Parse<'a> for Name<'a> {
+    ///borrows the bytes from `i`
+    fn parse<E>(i: &'a [u8]) -> IResult<&'a [u8], Self, E>
     where
         E: ParseError<&'a [u8]> + ContextError<&'a [u8]> + std::fmt::Debug,
     {
-        let (i, name) = <Vec<u8>>::parse(i)?;
+        let (rest, name) = <Cow<[u8]>>::parse(i)?;
+        if std::str::from_utf8(&name).is_err() {
+            let error = E::from_error_kind(i, ErrorKind::Verify);
+            return Err(nom::Err::Error(E::add_context(i, INVALID_UTF8, error)));
+        }
+        Ok((rest, Name(name)))
+    }
+}
+///context of the error for a [Name] that isn't valid utf-8
+pub const INVALID_UTF8: &str = "invalid utf-8 name";
+///adds `node`, parsed from `i`, to the context of the errors in `result`
+pub fn in_context<'a, T, E>(
+    i: &'a [u8],
+    node: &'static str,
+    result: IResult<&'a [u8], T, E>,
+) -> IResult<&'a [u8], T, E>
+where
+    E: ContextError<&'a [u8]>,
+{
+    result.map_err(|error| error.map(|error| E::add_context(i, node, error)))
+}
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Name<'a>(Cow<'a, [u8]>);
+impl<'a> Name<'a> {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+    ///copies the bytes if they are borrowed
+    pub fn into_owned(self) -> Name<'static> {
+        Name(Cow::Owned(self.0.into_owned()))
+    }
+}
+///lossy, invalid utf-8 is replaced
+impl std::fmt::Display for Name<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(&self.0))
+    }
+}
+impl From<Vec<u8>> for Name<'static> {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(Cow::Owned(bytes))
+    }
+}
+impl<'a> From<&'a [u8]> for Name<'a> {
+    fn from(bytes: &'a [u8]) -> Self {
+        Self(Cow::Borrowed(bytes))
+    }
+}
 
-        Ok((i, Name(name)))
+///inverse of [Parse], writes the binary representation of a value
+pub trait Encode {
+    fn encode(&self, out: &mut Vec<u8>);
+    ///encodes into a new buffer
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![];
+        self.encode(&mut out);
+        out
     }
 }
-#[derive(Debug)]
-pub struct Name(Vec<u8>);
 
-pub trait Parse
+///decodes a value from its binary representation, `'a` being the lifetime of the input
+///the values can borrow from
+pub trait Parse<'a>
 where
     Self: Sized,
 {
-    fn parse<'a, E>(i: &'a [u8]) -> IResult<&[u8], Self, E>
+    fn parse<E>(i: &'a [u8]) -> IResult<&'a [u8], Self, E>
     where
         E: ParseError<&'a [u8]> + ContextError<&'a [u8]> + std::fmt::Debug;
-    fn parse_dbg<'a, E>(i: &'a [u8]) -> IResult<&[u8], Self, E>
+    fn parse_dbg<E>(i: &'a [u8]) -> IResult<&'a [u8], Self, E>
     where
         E: ParseError<&'a [u8]> + ContextError<&'a [u8]> + std::fmt::Debug,
     {
         dbg_dmp(Self::parse, type_name::<Self>())(i)
     }
     ///parses with minimal error information
-    fn parse_simple<'a>(i: &'a [u8]) -> IResult<&[u8], Self, nom::error::Error<&'a [u8]>> {
+    fn parse_simple(i: &'a [u8]) -> IResult<&'a [u8], Self, nom::error::Error<&'a [u8]>> {
         Self::parse(i)
     }
 }
 #[derive(Debug)]
-pub struct DebugWrapper<T: Parse + Debug>(T);
-impl<T: Parse + Debug> Parse for DebugWrapper<T> {
-    fn parse<'a, E>(i: &'a [u8]) -> nom::IResult<&[u8], Self, E>
+pub struct DebugWrapper<T: Debug>(T);
+impl<'a, T: Parse<'a> + Debug> Parse<'a> for DebugWrapper<T> {
+    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
     where
         E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
     {
@@ -71,11 +135,11 @@ impl<T: Parse + Debug> Parse for DebugWrapper<T> {
     }
 }
 
-impl<T> Parse for Vec<T>
+impl<'a, T> Parse<'a> for Vec<T>
 where
-    T: Parse,
+    T: Parse<'a>,
 {
-    fn parse<'a, E>(i: &'a [u8]) -> IResult<&[u8], Self, E>
+    fn parse<E>(i: &'a [u8]) -> IResult<&'a [u8], Self, E>
     where
         E: ParseError<&'a [u8]> + ContextError<&'a [u8]> + std::fmt::Debug,
     {
@@ -84,46 +148,151 @@ where
     }
 }
 
-impl Parse for f32 {
-    fn parse<'a, E>(i: &'a [u8]) -> IResult<&[u8], Self, E>
+impl<'a> Parse<'a> for f32 {
+    fn parse<E>(i: &'a [u8]) -> IResult<&'a [u8], Self, E>
     where
         E: ParseError<&'a [u8]> + ContextError<&'a [u8]> + std::fmt::Debug,
     {
         nom::number::complete::le_f32(i)
     }
 }
-impl Parse for f64 {
-    fn parse<'a, E>(i: &'a [u8]) -> IResult<&[u8], Self, E>
+impl<'a> Parse<'a> for f64 {
+    fn parse<E>(i: &'a [u8]) -> IResult<&'a [u8], Self, E>
     where
         E: ParseError<&'a [u8]> + ContextError<&'a [u8]> + std::fmt::Debug,
     {
         nom::number::complete::le_f64(i)
     }
 }
-impl Parse for i32 {
-    fn parse<'a, E>(i: &'a [u8]) -> IResult<&[u8], Self, E>
+impl<'a> Parse<'a> for i32 {
+    fn parse<E>(i: &'a [u8]) -> IResult<&'a [u8], Self, E>
     where
         E: ParseError<&'a [u8]> + ContextError<&'a [u8]> + std::fmt::Debug,
     {
         leb128_i32(i)
     }
 }
-impl Parse for i64 {
-    fn parse<'a, E>(i: &'a [u8]) -> IResult<&[u8], Self, E>
+impl<'a> Parse<'a> for i64 {
+    fn parse<E>(i: &'a [u8]) -> IResult<&'a [u8], Self, E>
     where
         E: ParseError<&'a [u8]> + ContextError<&'a [u8]> + std::fmt::Debug,
     {
         leb128_i64(i)
     }
 }
-impl<T> Parse for Option<T>
+///a vector of bytes, borrowed from the input
+impl<'a> Parse<'a> for Cow<'a, [u8]> {
+    fn parse<E>(i: &'a [u8]) -> IResult<&'a [u8], Self, E>
+    where
+        E: ParseError<&'a [u8]> + ContextError<&'a [u8]> + std::fmt::Debug,
+    {
+        let (i, length) = u32::parse(i)?;
+        let (i, bytes) = take(length as usize)(i)?;
+        Ok((i, Cow::Borrowed(bytes)))
+    }
+}
+impl<'a, T> Parse<'a> for Option<T>
 where
-    T: Parse,
+    T: Parse<'a>,
 {
-    fn parse<'a, E>(i: &'a [u8]) -> IResult<&[u8], Self, E>
+    fn parse<E>(i: &'a [u8]) -> IResult<&'a [u8], Self, E>
     where
         E: ParseError<&'a [u8]> + ContextError<&'a [u8]> + std::fmt::Debug,
     {
         opt(T::parse)(i)
     }
 }
+
+///writes `value` as unsigned LEB128
+pub fn write_leb128_u64(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            break;
+        }
+        out.push(byte | 0x80);
+    }
+}
+///writes `value` as signed LEB128
+pub fn write_leb128_i64(out: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        let done = (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0);
+        if done {
+            out.push(byte);
+            break;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+impl Encode for u32 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_leb128_u64(out, *self as u64)
+    }
+}
+impl Encode for u64 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_leb128_u64(out, *self)
+    }
+}
+impl Encode for u8 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self)
+    }
+}
+impl Encode for i32 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_leb128_i64(out, *self as i64)
+    }
+}
+impl Encode for i64 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_leb128_i64(out, *self)
+    }
+}
+impl Encode for f32 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes())
+    }
+}
+impl Encode for f64 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes())
+    }
+}
+impl Encode for Name<'_> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out)
+    }
+}
+impl<T> Encode for Vec<T>
+where
+    T: Encode,
+{
+    fn encode(&self, out: &mut Vec<u8>) {
+        (self.len() as u32).encode(out);
+        for item in self {
+            item.encode(out);
+        }
+    }
+}
+impl Encode for Cow<'_, [u8]> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (self.len() as u32).encode(out);
+        out.extend_from_slice(self);
+    }
+}
+impl<T> Encode for Option<T>
+where
+    T: Encode,
+{
+    fn encode(&self, out: &mut Vec<u8>) {
+        if let Some(value) = self {
+            value.encode(out)
+        }
+    }
+}
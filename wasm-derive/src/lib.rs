@@ -18,29 +18,66 @@ pub fn wasm_derive(input: TokenStream) -> TokenStream {
 
 fn impl_hello_macro(ast: &syn::DeriveInput) -> TokenStream {
     let name = &ast.ident;
+    let ty = self_type(ast, quote!('a));
     match &ast.data {
-        syn::Data::Struct(stru) => impl_struct(stru, name.clone()),
-        syn::Data::Enum(enu) => impl_enum(enu, name.clone()),
+        syn::Data::Struct(stru) => impl_struct(stru, name.clone(), ty),
+        syn::Data::Enum(enu) => impl_enum(enu, name.clone(), ty),
         syn::Data::Union(_) => {
             panic!("no unions for now")
         }
     }
 }
-fn impl_enum(enu: &DataEnum, name: Ident) -> TokenStream {
-    //let debug_name = name.to_string();
+#[proc_macro_derive(Encode, attributes(starting))]
+pub fn wasm_derive_encode(input: TokenStream) -> TokenStream {
+    let ast: syn::DeriveInput = syn::parse(input).unwrap();
+    let ty = self_type(&ast, quote!('_));
+    match &ast.data {
+        syn::Data::Struct(stru) => impl_encode_struct(stru, ty),
+        syn::Data::Enum(enu) => impl_encode_enum(enu, ty),
+        syn::Data::Union(_) => unsupported(&ast, "unions can't derive Encode"),
+    }
+}
+
+///fails the derive with `message` pointing at `tokens`
+fn unsupported<T: ToTokens>(tokens: T, message: &str) -> TokenStream {
+    syn::Error::new_spanned(tokens, message)
+        .to_compile_error()
+        .into()
+}
+
+///the determinant of each variant, following the `#[starting = 0x..]` attributes
+fn determinants(enu: &DataEnum) -> Vec<u8> {
     let mut det_number = 0;
-    let frags = enu.variants.iter().map(|variant| {
-        let var_attr = &variant.attrs;
-        if !var_attr.is_empty() {
-            //dbg!(&var_attr);
-            //dbg!(parse_from(var_attr.clone()));
-            if let Some(det) = parse_from(var_attr.clone()) {
+    enu.variants
+        .iter()
+        .map(|variant| {
+            if let Some(det) = parse_from(variant.attrs.clone()) {
                 det_number = det as u8;
             }
-        }
+            let det = det_number;
+            det_number = det_number.wrapping_add(1);
+            det
+        })
+        .collect()
+}
+
+///the type deriving, with `lifetime` as its lifetime parameter if it borrows from the input
+fn self_type(
+    ast: &syn::DeriveInput,
+    lifetime: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let name = &ast.ident;
+    if ast.generics.lifetimes().next().is_some() {
+        quote!(#name<#lifetime>)
+    } else {
+        quote!(#name)
+    }
+}
 
-        let det = det_number;
-        det_number += 1;
+fn impl_enum(enu: &DataEnum, name: Ident, ty: proc_macro2::TokenStream) -> TokenStream {
+    //let debug_name = name.to_string();
+    let determinants = determinants(enu);
+    let frags = enu.variants.iter().zip(determinants).map(|(variant, det)| {
         let var = variant.ident.clone();
 
         let variants = match variant.fields.clone() {
@@ -77,44 +114,58 @@ fn impl_enum(enu: &DataEnum, name: Ident) -> TokenStream {
         variants
     });
     let frags: VecStream = frags.collect::<Vec<_>>().into();
+    let context = context(&name);
 
     let implementation = quote! {
-    impl Parse for #name {
-        fn parse<'a, E>(i: &'a [u8]) -> nom::IResult<&[u8], Self, E>
+    impl<'a> Parse<'a> for #ty {
+        fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
         where
             E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
         {
-            let (i,determinant) = u8::parse(i)?;
+            let result: nom::IResult<&'a [u8], Self, E> = (|| {
+                let (i,determinant) = u8::parse(i)?;
 
-            let enu = match determinant {
-                #frags
-                _ => {
-                    return Err(nom::Err::Error(nom::error::make_error(i,nom::error::ErrorKind::Fail)));
-                }
-            };
-            Ok(enu)
+                let enu = match determinant {
+                    #frags
+                    _ => {
+                        return Err(nom::Err::Error(nom::error::make_error(i,nom::error::ErrorKind::Fail)));
+                    }
+                };
+                Ok(enu)
+            })();
+            #context
         }
     }};
     implementation.into()
 }
 
-fn impl_struct(stru: &DataStruct, name: Ident) -> TokenStream {
+///names the type in the context of the errors in `result`, parsed from `i`
+fn context(name: &Ident) -> proc_macro2::TokenStream {
+    quote! {
+        result.map_err(|error| error.map(|error| E::add_context(i, stringify!(#name), error)))
+    }
+}
+
+fn impl_struct(stru: &DataStruct, name: Ident, ty: proc_macro2::TokenStream) -> TokenStream {
     //let debug_name = name.to_string();
     match &stru.fields {
         syn::Fields::Named(named) => {
             let (extracts_stream, content) = impl_named(named);
-            let id = name;
+            let context = context(&name);
             let implementation = quote! {
-                impl Parse for #id {
-                    fn parse<'a, E>(i: &'a [u8]) -> nom::IResult<&[u8], Self, E>
+                impl<'a> Parse<'a> for #ty {
+                    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
                     where
                         E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
                     {
-                        #extracts_stream
-                        let structure = Self{
-                            #content
-                        };
-                        Ok((i,structure))
+                        let result: nom::IResult<&'a [u8], Self, E> = (|| {
+                            #extracts_stream
+                            let structure = Self{
+                                #content
+                            };
+                            Ok((i,structure))
+                        })();
+                        #context
                     }
                 }
             };
@@ -132,18 +183,21 @@ fn impl_struct(stru: &DataStruct, name: Ident) -> TokenStream {
                 frag
             });
             let fields: VecStream = fields.collect::<Vec<_>>().into();
-            let id = name;
+            let context = context(&name);
             let implementation = quote! {
-                impl Parse for #id {
-                    fn parse<'a, E>(i: &'a [u8]) -> nom::IResult<&[u8], Self, E>
+                impl<'a> Parse<'a> for #ty {
+                    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
                     where
                         E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
                     {
-                        #fields
-                        let structure = Self(
-                            #idents
-                        );
-                        Ok((i,structure))
+                        let result: nom::IResult<&'a [u8], Self, E> = (|| {
+                            #fields
+                            let structure = Self(
+                                #idents
+                            );
+                            Ok((i,structure))
+                        })();
+                        #context
                     }
                 }
             };
@@ -186,6 +240,87 @@ fn impl_named(named: &FieldsNamed) -> (proc_macro2::TokenStream, proc_macro2::To
     (extracts_stream, content)
 }
 
+fn impl_encode_enum(enu: &DataEnum, ty: proc_macro2::TokenStream) -> TokenStream {
+    let determinants = determinants(enu);
+    let frags = enu.variants.iter().zip(determinants).map(|(variant, det)| {
+        let var = variant.ident.clone();
+        match &variant.fields {
+            syn::Fields::Named(_named) => Err(variant),
+            syn::Fields::Unnamed(fields) => {
+                let mut idents: Punctuated<Ident, Comma> = Punctuated::new();
+                let fields = fields.unnamed.iter().enumerate().map(|field| {
+                    let ident = format_ident!("field{}", field.0);
+                    let frag = quote! {
+                        Encode::encode(#ident, out);
+                    };
+                    idents.push(ident);
+                    frag
+                });
+                let fields: VecStream = fields.collect::<Vec<_>>().into();
+                Ok(quote! {
+                    Self::#var(#idents) => {
+                        Encode::encode(&#det, out);
+                        #fields
+                    }
+                })
+            }
+            syn::Fields::Unit => Ok(quote! {
+                Self::#var => Encode::encode(&#det, out),
+            }),
+        }
+    });
+    let frags: VecStream = match frags.collect::<Result<Vec<_>, _>>() {
+        Ok(frags) => frags.into(),
+        Err(variant) => {
+            return unsupported(variant, "variants with named fields can't derive Encode")
+        }
+    };
+
+    let implementation = quote! {
+    impl Encode for #ty {
+        fn encode(&self, out: &mut Vec<u8>) {
+            match self {
+                #frags
+            }
+        }
+    }};
+    implementation.into()
+}
+
+fn impl_encode_struct(stru: &DataStruct, ty: proc_macro2::TokenStream) -> TokenStream {
+    let fields: Vec<proc_macro2::TokenStream> = match &stru.fields {
+        syn::Fields::Named(named) => named
+            .named
+            .iter()
+            .map(|field| {
+                let id = field.ident.clone().expect("no ident");
+                quote! {
+                    Encode::encode(&self.#id, out);
+                }
+            })
+            .collect(),
+        syn::Fields::Unnamed(fields) => (0..fields.unnamed.len())
+            .map(|index| {
+                let index = syn::Index::from(index);
+                quote! {
+                    Encode::encode(&self.#index, out);
+                }
+            })
+            .collect(),
+        //encoded as nothing
+        syn::Fields::Unit => vec![],
+    };
+    let fields: VecStream = fields.into();
+    let implementation = quote! {
+        impl Encode for #ty {
+            fn encode(&self, out: &mut Vec<u8>) {
+                #fields
+            }
+        }
+    };
+    implementation.into()
+}
+
 struct VecStream(Vec<proc_macro2::TokenStream>);
 
 impl ToTokens for VecStream {
@@ -201,16 +336,18 @@ impl From<Vec<proc_macro2::TokenStream>> for VecStream {
 }
 
 fn parse_from(attrs: Vec<Attribute>) -> Option<u32> {
-    let first = attrs.get(0)?;
-    let path = first.path.segments.iter().next()?.clone().ident.to_string();
-    if path != String::from("starting") {
-        return None;
-    }
-    let tokens = first.tokens.clone().into_iter().take(2).collect::<Vec<_>>();
-    let token1 = tokens.get(0)?;
+    //doc comments are attributes too
+    let starting = attrs.iter().find(|attr| attr.path.is_ident("starting"))?;
+    let tokens = starting
+        .tokens
+        .clone()
+        .into_iter()
+        .take(2)
+        .collect::<Vec<_>>();
+    let token1 = tokens.first()?;
     let token2 = tokens.get(1)?;
     if let TokenTree::Punct(punct) = token1 {
-        if punct.to_string() != String::from("=") {
+        if punct.to_string() != "=" {
             return None;
         }
     } else {
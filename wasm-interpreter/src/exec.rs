@@ -0,0 +1,521 @@
+use crate::{
+    name_str, numeric,
+    values::{Stack, Value},
+    Function, Host, Instance, Trap, MAX_CALL_DEPTH, MAX_PAGES,
+};
+use std::ops::Range;
+use wasm::{
+    instructions::{
+        control::{BlockType, ControlInstruction},
+        memory::{MemArg, MemoryInstruction},
+        parametric::ParametricInstruction,
+        reference::ReferenceInstruction,
+        table::TableInstruction,
+        variable::VariableInstruction,
+        Instruction,
+    },
+    modules::indices::FuncIdx,
+};
+
+///how control leaves an instruction sequence
+pub(crate) enum Flow {
+    Continue,
+    ///branch to the label `n` levels out
+    Branch(u32),
+    Return,
+}
+
+///locals and operands of a single function activation
+struct Frame {
+    locals: Vec<Value>,
+    stack: Stack,
+}
+
+///checks that `[start, start + len)` fits in `size`
+fn in_bounds(start: u32, len: u32, size: usize) -> bool {
+    start as u64 + len as u64 <= size as u64
+}
+
+///`[start, start + len)`, widened before adding so it can't overflow `u32`
+fn range(start: u32, len: u32) -> Range<usize> {
+    start as usize..start as usize + len as usize
+}
+
+///checks the effective address of `arg` and returns its byte range
+fn effective_range(
+    data: &[u8],
+    address: u32,
+    arg: &MemArg,
+    len: usize,
+) -> Result<Range<usize>, Trap> {
+    let start = address as u64 + arg.offset;
+    let end = start.checked_add(len as u64);
+    let end = match end {
+        Some(end) if end <= data.len() as u64 => end,
+        _ => return Err(Trap::MemoryOutOfBounds),
+    };
+    Ok(start as usize..end as usize)
+}
+
+///pops an address and pushes the `$ty` stored there, extended to `$to`
+macro_rules! load {
+    ($stack:ident, $data:expr, $arg:ident, $ty:ty => $to:ty) => {{
+        let address: u32 = $stack.pop();
+        let range = effective_range(&$data, address, $arg, std::mem::size_of::<$ty>())?;
+        let mut bytes = [0; std::mem::size_of::<$ty>()];
+        bytes.copy_from_slice(&$data[range]);
+        $stack.push(<$ty>::from_le_bytes(bytes) as $to)
+    }};
+}
+///pops a `$ty` value and an address, storing the value wrapped to `$to`
+macro_rules! store {
+    ($stack:ident, $data:expr, $arg:ident, $ty:ty => $to:ty) => {{
+        let value: $ty = $stack.pop();
+        let address: u32 = $stack.pop();
+        let range = effective_range(&$data, address, $arg, std::mem::size_of::<$to>())?;
+        $data[range].copy_from_slice(&(value as $to).to_le_bytes());
+    }};
+}
+
+impl<'m, H: Host> Instance<'m, H> {
+    pub(crate) fn call(&mut self, func: FuncIdx, args: Vec<Value>) -> Result<Vec<Value>, Trap> {
+        match self.funcs[func as usize] {
+            Function::Host {
+                module,
+                name,
+                func_type,
+            } => {
+                let (module, name) = (name_str(module), name_str(name));
+                let results = self.host.call(&module, &name, &args)?;
+                let matching = results.len() == func_type.rt2.len()
+                    && func_type
+                        .rt2
+                        .iter()
+                        .zip(&results)
+                        .all(|(t, result)| *t == result.value_type());
+                if !matching {
+                    let message = format!(
+                        "host function {}.{} returned mismatched results",
+                        module, name
+                    );
+                    return Err(Trap::Host(message));
+                }
+                Ok(results)
+            }
+            Function::Local { func_type, code } => {
+                if self.depth == MAX_CALL_DEPTH {
+                    return Err(Trap::CallStackExhausted);
+                }
+                let mut locals = args;
+                for local in &code.locals {
+                    let value = Value::default_of(local.value_type);
+                    locals.resize(locals.len() + local.count as usize, value);
+                }
+                let mut frame = Frame {
+                    locals,
+                    stack: Stack::default(),
+                };
+                self.depth += 1;
+                let flow = self.execute(&mut frame, &code.body.0);
+                self.depth -= 1;
+                flow?;
+                Ok(frame.stack.split_off(func_type.rt2.len()))
+            }
+        }
+    }
+
+    ///parameter and result counts of a block
+    fn arity(&self, block: &BlockType) -> (usize, usize) {
+        match block {
+            BlockType::Empty => (0, 0),
+            BlockType::ValType(_) => (0, 1),
+            BlockType::TypeIdx(type_idx) => {
+                let func_type = self.types[*type_idx as usize];
+                (func_type.rt1.len(), func_type.rt2.len())
+            }
+        }
+    }
+
+    fn execute(
+        &mut self,
+        frame: &mut Frame,
+        instructions: &'m [Instruction],
+    ) -> Result<Flow, Trap> {
+        for instruction in instructions {
+            let stack = &mut frame.stack;
+            match instruction {
+                Instruction::Control(instruction) => match self.control(frame, instruction)? {
+                    Flow::Continue => {}
+                    flow => return Ok(flow),
+                },
+                Instruction::Reference(instruction) => match instruction {
+                    ReferenceInstruction::Null(ref_type) => stack.push(Value::null(*ref_type)),
+                    ReferenceInstruction::IsNull => {
+                        let value: Value = stack.pop();
+                        stack.push(value.is_null());
+                    }
+                    ReferenceInstruction::Func(func) => stack.push(Value::FuncRef(Some(*func))),
+                },
+                Instruction::Parametric(instruction) => match instruction {
+                    ParametricInstruction::Drop => {
+                        stack.pop::<Value>();
+                    }
+                    ParametricInstruction::Select | ParametricInstruction::SelectTyped(_) => {
+                        let condition: i32 = stack.pop();
+                        let second: Value = stack.pop();
+                        let first: Value = stack.pop();
+                        stack.push(if condition != 0 { first } else { second });
+                    }
+                },
+                Instruction::Variable(instruction) => match instruction {
+                    VariableInstruction::LocalGet(local) => {
+                        stack.push(frame.locals[*local as usize])
+                    }
+                    VariableInstruction::LocalSet(local) => {
+                        frame.locals[*local as usize] = stack.pop();
+                    }
+                    VariableInstruction::LocalTee(local) => {
+                        let value: Value = stack.pop();
+                        frame.locals[*local as usize] = value;
+                        stack.push(value);
+                    }
+                    VariableInstruction::GlobalGet(global) => {
+                        stack.push(self.globals[*global as usize])
+                    }
+                    VariableInstruction::GlobalSet(global) => {
+                        self.globals[*global as usize] = stack.pop();
+                    }
+                },
+                Instruction::Table(instruction) => self.table_instruction(stack, instruction)?,
+                Instruction::Memory(instruction) => self.memory_instruction(stack, instruction)?,
+                Instruction::Numeric(instruction) => numeric::execute(stack, instruction)?,
+                Instruction::Saturating(instruction) => {
+                    numeric::execute_saturating(stack, instruction)
+                }
+                Instruction::Simd(_) | Instruction::RelaxedSimd(_) => {
+                    return Err(Trap::Unsupported("simd"))
+                }
+                Instruction::Atomic(_) => return Err(Trap::Unsupported("atomic")),
+            }
+        }
+        Ok(Flow::Continue)
+    }
+
+    ///runs a block body, resolving branches that target it
+    fn block(
+        &mut self,
+        frame: &mut Frame,
+        block: &BlockType,
+        body: &'m [Instruction],
+    ) -> Result<Flow, Trap> {
+        let (params, results) = self.arity(block);
+        let height = frame.stack.len() - params;
+        match self.execute(frame, body)? {
+            Flow::Continue => Ok(Flow::Continue),
+            Flow::Branch(0) => {
+                frame.stack.unwind(height, results);
+                Ok(Flow::Continue)
+            }
+            Flow::Branch(n) => Ok(Flow::Branch(n - 1)),
+            Flow::Return => Ok(Flow::Return),
+        }
+    }
+
+    fn control(
+        &mut self,
+        frame: &mut Frame,
+        instruction: &'m ControlInstruction,
+    ) -> Result<Flow, Trap> {
+        match instruction {
+            ControlInstruction::Unreachable => return Err(Trap::Unreachable),
+            ControlInstruction::Nop => {}
+            ControlInstruction::Block(block, body) => return self.block(frame, block, &body.0),
+            ControlInstruction::Loop(block, body) => {
+                let (params, _) = self.arity(block);
+                let height = frame.stack.len() - params;
+                loop {
+                    match self.execute(frame, &body.0)? {
+                        Flow::Continue => break,
+                        Flow::Branch(0) => frame.stack.unwind(height, params),
+                        Flow::Branch(n) => return Ok(Flow::Branch(n - 1)),
+                        Flow::Return => return Ok(Flow::Return),
+                    }
+                }
+            }
+            ControlInstruction::IfElse(block, if_branch, else_branch) => {
+                let condition: i32 = frame.stack.pop();
+                let body = match (condition != 0, else_branch) {
+                    (true, _) => &if_branch.0[..],
+                    (false, Some(else_branch)) => &else_branch.0,
+                    (false, None) => &[],
+                };
+                return self.block(frame, block, body);
+            }
+            //without exceptions being thrown, the handlers never run
+            ControlInstruction::TryTable(block, _, body)
+            | ControlInstruction::Try { block, body, .. }
+            | ControlInstruction::Delegate(block, body, _) => {
+                return self.block(frame, block, &body.0)
+            }
+            ControlInstruction::Throw(_)
+            | ControlInstruction::ThrowRef
+            | ControlInstruction::Rethrow(_) => {
+                return Err(Trap::Unsupported("exception handling"))
+            }
+            ControlInstruction::Br(label) => return Ok(Flow::Branch(*label)),
+            ControlInstruction::BrIf(label) => {
+                let condition: i32 = frame.stack.pop();
+                if condition != 0 {
+                    return Ok(Flow::Branch(*label));
+                }
+            }
+            ControlInstruction::BrTable { table, default } => {
+                let index: u32 = frame.stack.pop();
+                let label = table.get(index as usize).unwrap_or(default);
+                return Ok(Flow::Branch(*label));
+            }
+            ControlInstruction::Return => return Ok(Flow::Return),
+            ControlInstruction::Call(func) => {
+                let params = self.funcs[*func as usize].func_type().rt1.len();
+                let args = frame.stack.split_off(params);
+                let results = self.call(*func, args)?;
+                frame.stack.0.extend(results);
+            }
+            ControlInstruction::CallIndirect(type_idx, table) => {
+                let index: u32 = frame.stack.pop();
+                let element = self.tables[*table as usize]
+                    .elements
+                    .get(index as usize)
+                    .ok_or(Trap::UndefinedElement)?;
+                let func = match element {
+                    Value::FuncRef(Some(func)) => *func,
+                    _ => return Err(Trap::UninitializedElement),
+                };
+                let func_type = self.funcs[func as usize].func_type();
+                if func_type != self.types[*type_idx as usize] {
+                    return Err(Trap::IndirectCallTypeMismatch);
+                }
+                let args = frame.stack.split_off(func_type.rt1.len());
+                let results = self.call(func, args)?;
+                frame.stack.0.extend(results);
+            }
+        }
+        Ok(Flow::Continue)
+    }
+
+    fn table_instruction(
+        &mut self,
+        stack: &mut Stack,
+        instruction: &TableInstruction,
+    ) -> Result<(), Trap> {
+        match instruction {
+            TableInstruction::TableGet(table) => {
+                let index: u32 = stack.pop();
+                let elements = &self.tables[*table as usize].elements;
+                let value = elements.get(index as usize).ok_or(Trap::TableOutOfBounds)?;
+                stack.push(*value);
+            }
+            TableInstruction::TableSet(table) => {
+                let value: Value = stack.pop();
+                let index: u32 = stack.pop();
+                let elements = &mut self.tables[*table as usize].elements;
+                let slot = elements
+                    .get_mut(index as usize)
+                    .ok_or(Trap::TableOutOfBounds)?;
+                *slot = value;
+            }
+            TableInstruction::TableInit(elem, table) => {
+                let (n, src, dst) = (stack.pop(), stack.pop(), stack.pop());
+                self.table_init(*table, *elem, dst, src, n)?;
+            }
+            TableInstruction::TableDrop(elem) => self.elems[*elem as usize].clear(),
+            TableInstruction::TableCopy(dst_table, src_table) => {
+                let (n, src, dst): (u32, u32, u32) = (stack.pop(), stack.pop(), stack.pop());
+                let src_len = self.tables[*src_table as usize].elements.len();
+                let dst_len = self.tables[*dst_table as usize].elements.len();
+                if !in_bounds(src, n, src_len) || !in_bounds(dst, n, dst_len) {
+                    return Err(Trap::TableOutOfBounds);
+                }
+                let src = range(src, n);
+                let values = self.tables[*src_table as usize].elements[src].to_vec();
+                let dst = range(dst, n);
+                self.tables[*dst_table as usize].elements[dst].copy_from_slice(&values);
+            }
+            TableInstruction::TableGrow(table) => {
+                let n: u32 = stack.pop();
+                let value: Value = stack.pop();
+                let table = &mut self.tables[*table as usize];
+                let old = table.elements.len() as u32;
+                let max = table
+                    .max
+                    .unwrap_or(u32::MAX)
+                    .min(self.limits.table_elements);
+                match old.checked_add(n) {
+                    Some(new) if new <= max => {
+                        table.elements.resize(new as usize, value);
+                        stack.push(old as i32);
+                    }
+                    _ => stack.push(-1),
+                }
+            }
+            TableInstruction::TableSize(table) => {
+                stack.push(self.tables[*table as usize].elements.len() as i32)
+            }
+            TableInstruction::TableFill(table) => {
+                let n: u32 = stack.pop();
+                let value: Value = stack.pop();
+                let index: u32 = stack.pop();
+                let elements = &mut self.tables[*table as usize].elements;
+                if !in_bounds(index, n, elements.len()) {
+                    return Err(Trap::TableOutOfBounds);
+                }
+                elements[range(index, n)].fill(value);
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn table_init(
+        &mut self,
+        table: u32,
+        elem: u32,
+        dst: u32,
+        src: u32,
+        n: u32,
+    ) -> Result<(), Trap> {
+        let elem = &self.elems[elem as usize];
+        let elements = &mut self.tables[table as usize].elements;
+        if !in_bounds(src, n, elem.len()) || !in_bounds(dst, n, elements.len()) {
+            return Err(Trap::TableOutOfBounds);
+        }
+        elements[range(dst, n)].copy_from_slice(&elem[range(src, n)]);
+        Ok(())
+    }
+
+    fn memory_instruction(
+        &mut self,
+        stack: &mut Stack,
+        instruction: &MemoryInstruction,
+    ) -> Result<(), Trap> {
+        use MemoryInstruction::*;
+        match instruction {
+            I32Load(arg) => load!(stack, self.memories[arg.memory as usize].data, arg, i32 => i32),
+            I64Load(arg) => load!(stack, self.memories[arg.memory as usize].data, arg, i64 => i64),
+            F32Load(arg) => load!(stack, self.memories[arg.memory as usize].data, arg, f32 => f32),
+            F64Load(arg) => load!(stack, self.memories[arg.memory as usize].data, arg, f64 => f64),
+            I32Load8S(arg) => load!(stack, self.memories[arg.memory as usize].data, arg, i8 => i32),
+            I32Load8U(arg) => load!(stack, self.memories[arg.memory as usize].data, arg, u8 => i32),
+            I32Load16S(arg) => {
+                load!(stack, self.memories[arg.memory as usize].data, arg, i16 => i32)
+            }
+            I32Load16U(arg) => {
+                load!(stack, self.memories[arg.memory as usize].data, arg, u16 => i32)
+            }
+            I64Load8S(arg) => load!(stack, self.memories[arg.memory as usize].data, arg, i8 => i64),
+            I64Load8U(arg) => load!(stack, self.memories[arg.memory as usize].data, arg, u8 => i64),
+            I64Load16S(arg) => {
+                load!(stack, self.memories[arg.memory as usize].data, arg, i16 => i64)
+            }
+            I64Load16U(arg) => {
+                load!(stack, self.memories[arg.memory as usize].data, arg, u16 => i64)
+            }
+            I64Load32S(arg) => {
+                load!(stack, self.memories[arg.memory as usize].data, arg, i32 => i64)
+            }
+            I64Load32U(arg) => {
+                load!(stack, self.memories[arg.memory as usize].data, arg, u32 => i64)
+            }
+            I32Store(arg) => {
+                store!(stack, self.memories[arg.memory as usize].data, arg, i32 => i32)
+            }
+            I64Store(arg) => {
+                store!(stack, self.memories[arg.memory as usize].data, arg, i64 => i64)
+            }
+            F32Store(arg) => {
+                store!(stack, self.memories[arg.memory as usize].data, arg, f32 => f32)
+            }
+            F64Store(arg) => {
+                store!(stack, self.memories[arg.memory as usize].data, arg, f64 => f64)
+            }
+            I32Store8(arg) => {
+                store!(stack, self.memories[arg.memory as usize].data, arg, i32 => u8)
+            }
+            I32Store16(arg) => {
+                store!(stack, self.memories[arg.memory as usize].data, arg, i32 => u16)
+            }
+            I64Store8(arg) => {
+                store!(stack, self.memories[arg.memory as usize].data, arg, i64 => u8)
+            }
+            I64Store16(arg) => {
+                store!(stack, self.memories[arg.memory as usize].data, arg, i64 => u16)
+            }
+            I64Store32(arg) => {
+                store!(stack, self.memories[arg.memory as usize].data, arg, i64 => u32)
+            }
+            Size(memory) => stack.push(self.memories[*memory as usize].pages() as i32),
+            Grow(memory) => {
+                let n: u32 = stack.pop();
+                let memory = &mut self.memories[*memory as usize];
+                let old = memory.pages();
+                let max = memory.max.unwrap_or(MAX_PAGES).min(MAX_PAGES);
+                let max = max.min(self.limits.memory_pages);
+                match old.checked_add(n) {
+                    Some(new) if new <= max => {
+                        memory.data.resize(new as usize * crate::PAGE_SIZE, 0);
+                        stack.push(old as i32);
+                    }
+                    _ => stack.push(-1),
+                }
+            }
+            Init(_, data, memory) => {
+                let (n, src, dst) = (stack.pop(), stack.pop(), stack.pop());
+                self.memory_init(*memory, *data, dst, src, n)?;
+            }
+            Drop(_, data) => self.datas[*data as usize] = &[],
+            Copy(_, destination, source) => {
+                let (n, src, dst): (u32, u32, u32) = (stack.pop(), stack.pop(), stack.pop());
+                let (destination, source) = (*destination as usize, *source as usize);
+                let source_len = self.memories[source].data.len();
+                let destination_len = self.memories[destination].data.len();
+                if !in_bounds(src, n, source_len) || !in_bounds(dst, n, destination_len) {
+                    return Err(Trap::MemoryOutOfBounds);
+                }
+                let (src, dst) = (range(src, n), dst as usize);
+                if source == destination {
+                    self.memories[source].data.copy_within(src, dst);
+                } else {
+                    let bytes = self.memories[source].data[src].to_vec();
+                    self.memories[destination].data[dst..dst + bytes.len()].copy_from_slice(&bytes);
+                }
+            }
+            Fill(_, memory) => {
+                let n: u32 = stack.pop();
+                let value: i32 = stack.pop();
+                let dst: u32 = stack.pop();
+                let data = &mut self.memories[*memory as usize].data;
+                if !in_bounds(dst, n, data.len()) {
+                    return Err(Trap::MemoryOutOfBounds);
+                }
+                data[range(dst, n)].fill(value as u8);
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn memory_init(
+        &mut self,
+        memory: u32,
+        data: u32,
+        dst: u32,
+        src: u32,
+        n: u32,
+    ) -> Result<(), Trap> {
+        let data = self.datas[data as usize];
+        let memory = &mut self.memories[memory as usize].data;
+        if !in_bounds(src, n, data.len()) || !in_bounds(dst, n, memory.len()) {
+            return Err(Trap::MemoryOutOfBounds);
+        }
+        memory[range(dst, n)].copy_from_slice(&data[range(src, n)]);
+        Ok(())
+    }
+}
@@ -0,0 +1,621 @@
+use std::fmt;
+use wasm::{
+    instructions::{
+        numeric::NumericInstruction, reference::ReferenceInstruction, simd::SimdInstruction,
+        variable::VariableInstruction, Expression, Instruction,
+    },
+    modules::{
+        code::Func,
+        data::Data,
+        element::Elem,
+        export::{Export, ExportDescriptor},
+        import::ImportDescriptor,
+        Module, Section,
+    },
+    types::{FuncType, GlobalType, IndexType, Limit, RefType},
+    validation::{validate, ValidationError},
+};
+use wasm_core::values::Name;
+
+mod exec;
+mod numeric;
+pub mod values;
+
+use values::FromValue;
+pub use values::{FuncAddr, Value};
+
+pub const PAGE_SIZE: usize = 0x10000;
+const MAX_PAGES: u32 = 0x10000;
+const MAX_CALL_DEPTH: usize = 512;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Trap {
+    Unreachable,
+    IntegerDivideByZero,
+    IntegerOverflow,
+    InvalidConversionToInteger,
+    MemoryOutOfBounds,
+    TableOutOfBounds,
+    UndefinedElement,
+    UninitializedElement,
+    IndirectCallTypeMismatch,
+    CallStackExhausted,
+    ///an instruction of a proposal the interpreter doesn't execute
+    Unsupported(&'static str),
+    ///raised by a host function
+    Host(String),
+}
+impl fmt::Display for Trap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            Trap::Unreachable => "unreachable",
+            Trap::IntegerDivideByZero => "integer divide by zero",
+            Trap::IntegerOverflow => "integer overflow",
+            Trap::InvalidConversionToInteger => "invalid conversion to integer",
+            Trap::MemoryOutOfBounds => "out of bounds memory access",
+            Trap::TableOutOfBounds => "out of bounds table access",
+            Trap::UndefinedElement => "undefined element",
+            Trap::UninitializedElement => "uninitialized element",
+            Trap::IndirectCallTypeMismatch => "indirect call type mismatch",
+            Trap::CallStackExhausted => "call stack exhausted",
+            Trap::Unsupported(proposal) => {
+                return write!(f, "unsupported {} instruction", proposal)
+            }
+            Trap::Host(message) => return write!(f, "host trap: {}", message),
+        };
+        f.write_str(message)
+    }
+}
+impl std::error::Error for Trap {}
+
+#[derive(Debug)]
+pub enum Error {
+    Validation(ValidationError),
+    ///an import the host could not satisfy
+    Link(String),
+    Trap(Trap),
+    ExportNotFound(String),
+    ///arguments passed to [Instance::invoke] do not match the function type
+    ArgumentMismatch,
+    ///the module uses a proposal the interpreter doesn't implement
+    Unsupported(&'static str),
+    ///a memory or table larger than the [InstanceLimits] allow
+    LimitExceeded(String),
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Validation(error) => write!(f, "invalid module: {}", error),
+            Error::Link(message) => write!(f, "link error: {}", message),
+            Error::Trap(trap) => write!(f, "trap: {}", trap),
+            Error::ExportNotFound(name) => write!(f, "no exported function named {:?}", name),
+            Error::ArgumentMismatch => f.write_str("arguments do not match the function type"),
+            Error::Unsupported(feature) => write!(f, "unsupported {}", feature),
+            Error::LimitExceeded(message) => write!(f, "limit exceeded: {}", message),
+        }
+    }
+}
+impl std::error::Error for Error {}
+impl From<Trap> for Error {
+    fn from(trap: Trap) -> Self {
+        Error::Trap(trap)
+    }
+}
+
+///provides the imports of a module
+pub trait Host {
+    ///called for every invocation of an imported function
+    fn call(&mut self, module: &str, name: &str, args: &[Value]) -> Result<Vec<Value>, Trap>;
+    ///value of an imported global, `None` fails instantiation
+    fn global(&mut self, _module: &str, _name: &str, _global_type: &GlobalType) -> Option<Value> {
+        None
+    }
+}
+///host for modules without function or global imports
+impl Host for () {
+    fn call(&mut self, module: &str, name: &str, _args: &[Value]) -> Result<Vec<Value>, Trap> {
+        Err(Trap::Host(format!("no host function {}.{}", module, name)))
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Function<'m> {
+    Host {
+        module: &'m Name<'m>,
+        name: &'m Name<'m>,
+        func_type: &'m FuncType,
+    },
+    Local {
+        func_type: &'m FuncType,
+        code: &'m Func,
+    },
+}
+impl<'m> Function<'m> {
+    fn func_type(&self) -> &'m FuncType {
+        match self {
+            Function::Host { func_type, .. } | Function::Local { func_type, .. } => func_type,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Table {
+    pub elements: Vec<Value>,
+    pub max: Option<u32>,
+}
+impl Table {
+    fn new(ref_type: RefType, limit: &Limit, limits: &InstanceLimits) -> Result<Self, Error> {
+        if limit.min > limits.table_elements as u64 {
+            let message = format!(
+                "table of {} elements, at most {} allowed",
+                limit.min, limits.table_elements
+            );
+            return Err(Error::LimitExceeded(message));
+        }
+        Ok(Self {
+            elements: vec![Value::null(ref_type); limit.min as usize],
+            //validation keeps the limits of tables within u32
+            max: limit.max.map(|max| max as u32),
+        })
+    }
+}
+#[derive(Debug)]
+pub struct Memory {
+    pub data: Vec<u8>,
+    pub max: Option<u32>,
+}
+impl Memory {
+    fn new(limit: &Limit, limits: &InstanceLimits) -> Result<Self, Error> {
+        if limit.min > limits.memory_pages as u64 {
+            let message = format!(
+                "memory of {} pages, at most {} allowed",
+                limit.min, limits.memory_pages
+            );
+            return Err(Error::LimitExceeded(message));
+        }
+        Ok(Self {
+            data: vec![0; limit.min as usize * PAGE_SIZE],
+            //and those of 32 bit memories within 2^16 pages
+            max: limit.max.map(|max| max as u32),
+        })
+    }
+    pub fn pages(&self) -> u32 {
+        (self.data.len() / PAGE_SIZE) as u32
+    }
+}
+
+///bounds on what an [Instance] allocates, checked at instantiation and when growing
+#[derive(Debug, Clone, Copy)]
+pub struct InstanceLimits {
+    ///pages of each memory
+    pub memory_pages: u32,
+    ///elements of each table
+    pub table_elements: u32,
+}
+impl Default for InstanceLimits {
+    ///1 GiB memories and tables of 10 million elements
+    fn default() -> Self {
+        Self {
+            memory_pages: 0x4000,
+            table_elements: 10_000_000,
+        }
+    }
+}
+
+///an instantiated module, borrowing its code from the parsed [Module]
+pub struct Instance<'m, H: Host> {
+    pub(crate) types: Vec<&'m FuncType>,
+    pub(crate) funcs: Vec<Function<'m>>,
+    pub tables: Vec<Table>,
+    pub memories: Vec<Memory>,
+    pub globals: Vec<Value>,
+    pub(crate) elems: Vec<Vec<Value>>,
+    pub(crate) datas: Vec<&'m [u8]>,
+    exports: Vec<&'m Export<'m>>,
+    pub host: H,
+    pub(crate) depth: usize,
+    pub(crate) limits: InstanceLimits,
+}
+
+fn name_str(name: &Name) -> String {
+    String::from_utf8_lossy(name.as_bytes()).into_owned()
+}
+
+impl<'m, H: Host> Instance<'m, H> {
+    ///validates and instantiates `module`, running its start function.
+    ///imported tables and memories are allocated from their declared types.
+    pub fn new(module: &'m Module, host: H) -> Result<Self, Error> {
+        Self::new_with(module, host, InstanceLimits::default())
+    }
+    ///[Instance::new] failing with [Error::LimitExceeded] instead of allocating memories or
+    ///tables beyond `limits`
+    pub fn new_with(module: &'m Module, host: H, limits: InstanceLimits) -> Result<Self, Error> {
+        let valid = validate(module).map_err(Error::Validation)?;
+        let context = valid.context;
+        if context
+            .mems
+            .iter()
+            .any(|mem| mem.lim.index_type == IndexType::I64)
+        {
+            return Err(Error::Unsupported("64 bit memory"));
+        }
+        let mut instance = Self {
+            types: context.types.clone(),
+            funcs: vec![],
+            tables: vec![],
+            memories: vec![],
+            globals: vec![],
+            elems: vec![],
+            datas: vec![],
+            exports: vec![],
+            host,
+            depth: 0,
+            limits,
+        };
+        let mut codes = vec![];
+        let mut globals = vec![];
+        let mut elems = vec![];
+        let mut datas = vec![];
+        let mut start = None;
+        for section in &module.sections {
+            match section {
+                Section::ImportSection(sec) => {
+                    for import in &sec.0 {
+                        match &import.descriptor {
+                            ImportDescriptor::Func(type_idx) => {
+                                instance.funcs.push(Function::Host {
+                                    module: &import.module,
+                                    name: &import.name,
+                                    func_type: context.types[*type_idx as usize],
+                                })
+                            }
+                            ImportDescriptor::Table(table_type) => instance
+                                .tables
+                                .push(Table::new(table_type.et, &table_type.lim, &limits)?),
+                            ImportDescriptor::Mem(mem_type) => {
+                                instance.memories.push(Memory::new(&mem_type.lim, &limits)?)
+                            }
+                            ImportDescriptor::Global(global_type) => {
+                                let (module, name) =
+                                    (name_str(&import.module), name_str(&import.name));
+                                let value = instance.host.global(&module, &name, global_type);
+                                match value {
+                                    Some(value) if value.value_type() == global_type.t => {
+                                        instance.globals.push(value)
+                                    }
+                                    _ => {
+                                        let message =
+                                            format!("unresolved global {}.{}", module, name);
+                                        return Err(Error::Link(message));
+                                    }
+                                }
+                            }
+                            //tags only matter to throw, which traps
+                            ImportDescriptor::Tag(_) => {}
+                        }
+                    }
+                }
+                Section::TableSection(sec) => {
+                    for table in &sec.0 {
+                        let table_type = &table.table_type;
+                        instance
+                            .tables
+                            .push(Table::new(table_type.et, &table_type.lim, &limits)?);
+                    }
+                }
+                Section::MemorySection(sec) => {
+                    for memory in &sec.0 {
+                        let memory = Memory::new(&memory.memory_type.lim, &limits)?;
+                        instance.memories.push(memory);
+                    }
+                }
+                Section::GlobalSection(sec) => globals.extend(sec.0.iter()),
+                Section::ExportSection(sec) => instance.exports.extend(sec.0.iter()),
+                Section::StartSection(sec) => start = start.or(sec.0),
+                Section::ElementSection(sec) => elems.extend(sec.0.iter()),
+                Section::CodeSection(sec) => codes.extend(sec.0.iter()),
+                Section::DataSection(sec) => datas.extend(sec.0.iter()),
+                _ => {}
+            }
+        }
+        let local_funcs = context.funcs[context.imported_funcs as usize..].iter();
+        for (type_idx, code) in local_funcs.zip(codes) {
+            instance.funcs.push(Function::Local {
+                func_type: context.types[*type_idx as usize],
+                code: &code.code,
+            });
+        }
+        for global in globals {
+            let value = instance.eval_const(&global.expression);
+            instance.globals.push(value);
+        }
+        for elem in &elems {
+            let values = match elem {
+                Elem::ActiveIndex(_, funcs)
+                | Elem::PassiveIndex(_, funcs)
+                | Elem::ActiveExplicitIndex(_, _, _, funcs)
+                | Elem::DeclarativeIndex(_, funcs) => funcs
+                    .iter()
+                    .map(|func| Value::FuncRef(Some(*func)))
+                    .collect(),
+                Elem::ActiveExpression(_, exprs)
+                | Elem::PassiveExpression(_, exprs)
+                | Elem::ActiveExplicitExpression(_, _, _, exprs)
+                | Elem::DeclarativeExpression(_, exprs) => {
+                    exprs.iter().map(|expr| instance.eval_const(expr)).collect()
+                }
+            };
+            instance.elems.push(values);
+        }
+        instance.datas = datas
+            .iter()
+            .map(|data| match data {
+                Data::Active(_, init) | Data::Passive(init) | Data::ActiveExplicit(_, _, init) => {
+                    &init[..]
+                }
+            })
+            .collect();
+
+        for (index, elem) in elems.iter().enumerate() {
+            let (table, offset) = match elem {
+                Elem::ActiveIndex(offset, _) | Elem::ActiveExpression(offset, _) => (0, offset),
+                Elem::ActiveExplicitIndex(table, offset, ..)
+                | Elem::ActiveExplicitExpression(table, offset, ..) => (*table, offset),
+                Elem::DeclarativeIndex(..) | Elem::DeclarativeExpression(..) => {
+                    instance.elems[index].clear();
+                    continue;
+                }
+                Elem::PassiveIndex(..) | Elem::PassiveExpression(..) => continue,
+            };
+            let offset = u32::from_value(instance.eval_const(offset));
+            let len = instance.elems[index].len() as u32;
+            instance.table_init(table, index as u32, offset, 0, len)?;
+            instance.elems[index].clear();
+        }
+        for (index, data) in datas.iter().enumerate() {
+            let (memory, offset, init) = match data {
+                Data::Active(offset, init) => (0, offset, init),
+                Data::ActiveExplicit(memory, offset, init) => (*memory, offset, init),
+                Data::Passive(_) => continue,
+            };
+            let offset = u32::from_value(instance.eval_const(offset));
+            instance.memory_init(memory, index as u32, offset, 0, init.len() as u32)?;
+            instance.datas[index] = &[];
+        }
+        if let Some(start) = start {
+            instance.call(start, vec![])?;
+        }
+        Ok(instance)
+    }
+
+    ///calls the exported function `name`
+    pub fn invoke(&mut self, name: &str, args: &[Value]) -> Result<Vec<Value>, Error> {
+        let func = self
+            .exports
+            .iter()
+            .find_map(|export| match export.descriptor {
+                ExportDescriptor::Func(func) if export.name.as_bytes() == name.as_bytes() => {
+                    Some(func)
+                }
+                _ => None,
+            })
+            .ok_or_else(|| Error::ExportNotFound(name.to_string()))?;
+        let params = &self.funcs[func as usize].func_type().rt1;
+        let matching = params.len() == args.len()
+            && params
+                .iter()
+                .zip(args)
+                .all(|(t, arg)| *t == arg.value_type());
+        if !matching {
+            return Err(Error::ArgumentMismatch);
+        }
+        Ok(self.call(func, args.to_vec())?)
+    }
+
+    ///the exported memory `name`
+    pub fn memory(&mut self, name: &str) -> Option<&mut Memory> {
+        let memory = self
+            .exports
+            .iter()
+            .find_map(|export| match export.descriptor {
+                ExportDescriptor::Mem(memory) if export.name.as_bytes() == name.as_bytes() => {
+                    Some(memory)
+                }
+                _ => None,
+            })?;
+        self.memories.get_mut(memory as usize)
+    }
+
+    ///the current value of the exported global `name`
+    pub fn global(&self, name: &str) -> Option<Value> {
+        self.exports
+            .iter()
+            .find_map(|export| match export.descriptor {
+                ExportDescriptor::Global(global) if export.name.as_bytes() == name.as_bytes() => {
+                    Some(self.globals[global as usize])
+                }
+                _ => None,
+            })
+    }
+
+    pub(crate) fn eval_const(&self, expr: &Expression) -> Value {
+        eval_const(&self.globals, expr)
+    }
+}
+
+///evaluates a validated constant expression
+fn eval_const(globals: &[Value], expr: &Expression) -> Value {
+    match &expr.0[..] {
+        [Instruction::Numeric(NumericInstruction::I32Const(value))] => Value::I32(*value),
+        [Instruction::Numeric(NumericInstruction::I64Const(value))] => Value::I64(*value),
+        [Instruction::Numeric(NumericInstruction::F32Const(value))] => Value::F32(*value),
+        [Instruction::Numeric(NumericInstruction::F64Const(value))] => Value::F64(*value),
+        [Instruction::Simd(SimdInstruction::V128Const(v128))] => {
+            Value::V128(u128::from_le_bytes(v128.0))
+        }
+        [Instruction::Reference(ReferenceInstruction::Null(ref_type))] => Value::null(*ref_type),
+        [Instruction::Reference(ReferenceInstruction::Func(func))] => Value::FuncRef(Some(*func)),
+        [Instruction::Variable(VariableInstruction::GlobalGet(global))] => {
+            globals[*global as usize]
+        }
+        _ => unreachable!("constant expressions are checked by validation"),
+    }
+}
+
+#[cfg(test)]
+fn parse(file: &[u8]) -> Module<'_> {
+    use nom::error::VerboseError;
+    use wasm_core::values::Parse;
+    Module::parse::<VerboseError<_>>(file).unwrap().1
+}
+#[cfg(test)]
+struct Dummy;
+#[cfg(test)]
+impl Host for Dummy {
+    fn call(&mut self, _module: &str, _name: &str, _args: &[Value]) -> Result<Vec<Value>, Trap> {
+        Ok(vec![])
+    }
+}
+#[test]
+fn upper() {
+    let file = include_bytes!("../../wasm/src/loop.wasm");
+    let module = parse(file);
+    let mut instance = Instance::new(&module, ()).unwrap();
+    let memory = instance.memory("memory").unwrap();
+    memory.data[..7].copy_from_slice(b"hello{}");
+    instance.invoke("upper", &[Value::I32(7)]).unwrap();
+    assert_eq!(&instance.memory("memory").unwrap().data[..7], b"HELLO{}");
+}
+#[test]
+fn spec_if() {
+    let module = parse(include_bytes!("../../wasm/src/if.wasm"));
+    let mut instance = Instance::new(&module, Dummy).unwrap();
+    let mut invoke = |name: &str, args: &[i32]| {
+        let args: Vec<Value> = args.iter().map(|arg| Value::I32(*arg)).collect();
+        instance.invoke(name, &args).unwrap()
+    };
+    assert_eq!(invoke("singular", &[0]), [Value::I32(8)]);
+    assert_eq!(invoke("singular", &[10]), [Value::I32(7)]);
+    assert_eq!(invoke("multi", &[0]), [Value::I32(9), Value::I32(-1)]);
+    assert_eq!(invoke("multi", &[1]), [Value::I32(8), Value::I32(1)]);
+    assert_eq!(invoke("nested", &[0, 0]), [Value::I32(11)]);
+    assert_eq!(invoke("nested", &[3, 2]), [Value::I32(9)]);
+    assert_eq!(invoke("break-value", &[1]), [Value::I32(18)]);
+    assert_eq!(invoke("break-value", &[0]), [Value::I32(21)]);
+}
+#[test]
+fn traps() {
+    //"div" divides its two i32 params, "loop" calls itself forever
+    let file = [
+        0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, //header
+        0x01, 0x0A, 0x02, 0x60, 0x02, 0x7F, 0x7F, 0x01, 0x7F, 0x60, 0x00, 0x00, //types
+        0x03, 0x03, 0x02, 0x00, 0x01, //funcs
+        0x07, 0x0E, 0x02, 0x03, b'd', b'i', b'v', 0x00, 0x00, 0x04, b'l', b'o', b'o', b'p', 0x00,
+        0x01, //exports
+        0x0A, 0x0E, 0x02, 0x07, 0x00, 0x20, 0x00, 0x20, 0x01, 0x6D, 0x0B, 0x04, 0x00, 0x10, 0x01,
+        0x0B, //code
+    ];
+    let module = parse(&file);
+    let mut instance = Instance::new(&module, ()).unwrap();
+    let result = instance
+        .invoke("div", &[Value::I32(7), Value::I32(2)])
+        .unwrap();
+    assert_eq!(result, [Value::I32(3)]);
+    let trap = |result: Result<Vec<Value>, Error>| match result {
+        Err(Error::Trap(trap)) => trap,
+        other => panic!("expected a trap, found {:?}", other),
+    };
+    let result = instance.invoke("div", &[Value::I32(1), Value::I32(0)]);
+    assert_eq!(trap(result), Trap::IntegerDivideByZero);
+    let result = instance.invoke("div", &[Value::I32(i32::MIN), Value::I32(-1)]);
+    assert_eq!(trap(result), Trap::IntegerOverflow);
+    assert_eq!(trap(instance.invoke("loop", &[])), Trap::CallStackExhausted);
+    assert!(matches!(
+        instance.invoke("div", &[Value::I32(1)]),
+        Err(Error::ArgumentMismatch)
+    ));
+}
+#[test]
+fn multi_memory() {
+    let module = Module::from_wat(
+        r#"
+        (memory $a (export "a") 1)
+        (memory $b 1)
+        (data (memory $b) (i32.const 4) "wasm")
+        (func (export "copy") (result i32)
+          i32.const 0
+          i32.const 4
+          i32.const 4
+          memory.copy $a $b
+          i32.const 0
+          i32.load $b offset=4
+          i32.const 0
+          i32.load
+          i32.sub)"#,
+    )
+    .unwrap();
+    let mut instance = Instance::new(&module, ()).unwrap();
+    assert_eq!(instance.invoke("copy", &[]).unwrap(), [Value::I32(0)]);
+    assert_eq!(&instance.memory("a").unwrap().data[..4], b"wasm");
+}
+#[test]
+fn host_results() {
+    let module = Module::from_wat(
+        r#"
+        (import "env" "answer" (func $answer (result i32)))
+        (func (export "ask") (result i32)
+          call $answer)"#,
+    )
+    .unwrap();
+    let mut instance = Instance::new(&module, Dummy).unwrap();
+    let message = "host function env.answer returned mismatched results";
+    assert!(matches!(
+        instance.invoke("ask", &[]),
+        Err(Error::Trap(Trap::Host(host))) if host == message
+    ));
+}
+#[test]
+fn limits() {
+    use wasm::text;
+    let limits = InstanceLimits {
+        memory_pages: 2,
+        table_elements: 8,
+    };
+    let instantiate = |source: &str| {
+        let module = text::parse(source).unwrap();
+        Instance::new_with(&module, (), limits).map(|_| ())
+    };
+    let memory = instantiate("(module (memory 3))").unwrap_err();
+    assert_eq!(
+        memory.to_string(),
+        "limit exceeded: memory of 3 pages, at most 2 allowed"
+    );
+    let table = instantiate(r#"(module (import "env" "t" (table 9 funcref)))"#).unwrap_err();
+    assert!(matches!(table, Error::LimitExceeded(_)));
+    //the default limits are checked before allocating 4 GiB
+    let module = text::parse("(module (memory 65536))").unwrap();
+    let error = Instance::new(&module, ()).err().unwrap();
+    assert!(matches!(error, Error::LimitExceeded(_)));
+
+    let source = r#"
+        (module
+          (memory 1)
+          (table 1 funcref)
+          (func (export "grow") (param i32) (result i32)
+            local.get 0
+            memory.grow
+            ref.null func
+            local.get 0
+            table.grow 0
+            i32.add))"#;
+    let module = text::parse(source).unwrap();
+    let mut instance = Instance::new_with(&module, (), limits).unwrap();
+    let grow = |instance: &mut Instance<()>, n| instance.invoke("grow", &[Value::I32(n)]).unwrap();
+    //both grow by one, from 1 to 2
+    assert_eq!(grow(&mut instance, 1), [Value::I32(2)]);
+    assert_eq!(instance.memories[0].pages(), 2);
+    //the memory can't grow past 2 pages, the table reaches 8 elements
+    assert_eq!(grow(&mut instance, 6), [Value::I32(-1 + 2)]);
+    assert_eq!(instance.memories[0].pages(), 2);
+    assert_eq!(instance.tables[0].elements.len(), 8);
+}
@@ -0,0 +1,293 @@
+use crate::{
+    values::{FromValue, Stack, Value},
+    Trap,
+};
+use wasm::instructions::numeric::{NumericInstruction, SaturatingTruncationInstruction};
+
+fn unary<A, R>(stack: &mut Stack, f: impl FnOnce(A) -> R)
+where
+    A: FromValue,
+    R: Into<Value>,
+{
+    let a = stack.pop();
+    stack.push(f(a));
+}
+fn binary<A, R>(stack: &mut Stack, f: impl FnOnce(A, A) -> R)
+where
+    A: FromValue,
+    R: Into<Value>,
+{
+    let b = stack.pop();
+    let a = stack.pop();
+    stack.push(f(a, b));
+}
+fn trapping_unary<A, R>(stack: &mut Stack, f: impl FnOnce(A) -> Result<R, Trap>) -> Result<(), Trap>
+where
+    A: FromValue,
+    R: Into<Value>,
+{
+    let a = stack.pop();
+    stack.push(f(a)?);
+    Ok(())
+}
+fn trapping_binary<A, R>(
+    stack: &mut Stack,
+    f: impl FnOnce(A, A) -> Result<R, Trap>,
+) -> Result<(), Trap>
+where
+    A: FromValue,
+    R: Into<Value>,
+{
+    let b = stack.pop();
+    let a = stack.pop();
+    stack.push(f(a, b)?);
+    Ok(())
+}
+
+macro_rules! int_division {
+    ($($name:ident: $ty:ty),*) => {
+        $(
+            mod $name {
+                use crate::Trap;
+                ///only signed division can overflow
+                pub fn div(a: $ty, b: $ty) -> Result<$ty, Trap> {
+                    if b == 0 {
+                        return Err(Trap::IntegerDivideByZero);
+                    }
+                    a.checked_div(b).ok_or(Trap::IntegerOverflow)
+                }
+                pub fn rem(a: $ty, b: $ty) -> Result<$ty, Trap> {
+                    if b == 0 {
+                        return Err(Trap::IntegerDivideByZero);
+                    }
+                    Ok(a.wrapping_rem(b))
+                }
+            }
+        )*
+    };
+}
+int_division!(i32_ops: i32, u32_ops: u32, i64_ops: i64, u64_ops: u64);
+
+macro_rules! float_ops {
+    ($($name:ident: $ty:ty),*) => {
+        $(
+            mod $name {
+                pub fn min(a: $ty, b: $ty) -> $ty {
+                    if a.is_nan() || b.is_nan() {
+                        <$ty>::NAN
+                    } else if a == b {
+                        //picks -0 over +0
+                        if a.is_sign_negative() { a } else { b }
+                    } else {
+                        a.min(b)
+                    }
+                }
+                pub fn max(a: $ty, b: $ty) -> $ty {
+                    if a.is_nan() || b.is_nan() {
+                        <$ty>::NAN
+                    } else if a == b {
+                        if a.is_sign_positive() { a } else { b }
+                    } else {
+                        a.max(b)
+                    }
+                }
+            }
+        )*
+    };
+}
+float_ops!(f32_ops: f32, f64_ops: f64);
+
+///truncates `value` towards zero, trapping when the result does not fit in `min..max`
+fn truncate(value: f64, min: f64, max: f64) -> Result<f64, Trap> {
+    if value.is_nan() {
+        return Err(Trap::InvalidConversionToInteger);
+    }
+    let truncated = value.trunc();
+    if truncated < min || truncated >= max {
+        return Err(Trap::IntegerOverflow);
+    }
+    Ok(truncated)
+}
+const I32_RANGE: (f64, f64) = (-2147483648.0, 2147483648.0);
+const U32_RANGE: (f64, f64) = (0.0, 4294967296.0);
+const I64_RANGE: (f64, f64) = (-9223372036854775808.0, 9223372036854775808.0);
+const U64_RANGE: (f64, f64) = (0.0, 18446744073709551616.0);
+
+pub(crate) fn execute(stack: &mut Stack, instruction: &NumericInstruction) -> Result<(), Trap> {
+    use NumericInstruction::*;
+    match instruction {
+        I32Const(value) => stack.push(*value),
+        I64Const(value) => stack.push(*value),
+        F32Const(value) => stack.push(*value),
+        F64Const(value) => stack.push(*value),
+
+        I32Eqz => unary(stack, |a: i32| a == 0),
+        I32Eq => binary(stack, |a: i32, b| a == b),
+        I32Ne => binary(stack, |a: i32, b| a != b),
+        I32LtS => binary(stack, |a: i32, b| a < b),
+        I32LtU => binary(stack, |a: u32, b| a < b),
+        I32GtS => binary(stack, |a: i32, b| a > b),
+        I32GtU => binary(stack, |a: u32, b| a > b),
+        I32LeS => binary(stack, |a: i32, b| a <= b),
+        I32LeU => binary(stack, |a: u32, b| a <= b),
+        I32GeS => binary(stack, |a: i32, b| a >= b),
+        I32GeU => binary(stack, |a: u32, b| a >= b),
+
+        I64Eqz => unary(stack, |a: i64| a == 0),
+        I64Eq => binary(stack, |a: i64, b| a == b),
+        I64Ne => binary(stack, |a: i64, b| a != b),
+        I64LtS => binary(stack, |a: i64, b| a < b),
+        I64LtU => binary(stack, |a: u64, b| a < b),
+        I64GtS => binary(stack, |a: i64, b| a > b),
+        I64GtU => binary(stack, |a: u64, b| a > b),
+        I64LeS => binary(stack, |a: i64, b| a <= b),
+        I64LeU => binary(stack, |a: u64, b| a <= b),
+        I64GeS => binary(stack, |a: i64, b| a >= b),
+        I64GeU => binary(stack, |a: u64, b| a >= b),
+
+        F32Eq => binary(stack, |a: f32, b| a == b),
+        F32Ne => binary(stack, |a: f32, b| a != b),
+        F32Lt => binary(stack, |a: f32, b| a < b),
+        F32Gt => binary(stack, |a: f32, b| a > b),
+        F32Le => binary(stack, |a: f32, b| a <= b),
+        F32Ge => binary(stack, |a: f32, b| a >= b),
+
+        F64Eq => binary(stack, |a: f64, b| a == b),
+        F64Ne => binary(stack, |a: f64, b| a != b),
+        F64Lt => binary(stack, |a: f64, b| a < b),
+        F64Gt => binary(stack, |a: f64, b| a > b),
+        F64Le => binary(stack, |a: f64, b| a <= b),
+        F64Ge => binary(stack, |a: f64, b| a >= b),
+
+        I32Clz => unary(stack, |a: i32| a.leading_zeros() as i32),
+        I32Ctz => unary(stack, |a: i32| a.trailing_zeros() as i32),
+        I32Popcnt => unary(stack, |a: i32| a.count_ones() as i32),
+        I32Add => binary(stack, |a: i32, b| a.wrapping_add(b)),
+        I32Sub => binary(stack, |a: i32, b| a.wrapping_sub(b)),
+        I32Mul => binary(stack, |a: i32, b| a.wrapping_mul(b)),
+        I32DivS => trapping_binary(stack, i32_ops::div)?,
+        I32DivU => trapping_binary(stack, |a, b| u32_ops::div(a, b).map(|r| r as i32))?,
+        I32RemS => trapping_binary(stack, i32_ops::rem)?,
+        I32RemU => trapping_binary(stack, |a, b| u32_ops::rem(a, b).map(|r| r as i32))?,
+        I32And => binary(stack, |a: i32, b| a & b),
+        I32Or => binary(stack, |a: i32, b| a | b),
+        I32Xor => binary(stack, |a: i32, b| a ^ b),
+        I32Shl => binary(stack, |a: i32, b| a.wrapping_shl(b as u32)),
+        I32ShrS => binary(stack, |a: i32, b| a.wrapping_shr(b as u32)),
+        I32ShrU => binary(stack, |a: u32, b| a.wrapping_shr(b) as i32),
+        I32Rotl => binary(stack, |a: u32, b| a.rotate_left(b % 32) as i32),
+        I32Rotr => binary(stack, |a: u32, b| a.rotate_right(b % 32) as i32),
+
+        I64Clz => unary(stack, |a: i64| a.leading_zeros() as i64),
+        I64Ctz => unary(stack, |a: i64| a.trailing_zeros() as i64),
+        I64Popcnt => unary(stack, |a: i64| a.count_ones() as i64),
+        I64Add => binary(stack, |a: i64, b| a.wrapping_add(b)),
+        I64Sub => binary(stack, |a: i64, b| a.wrapping_sub(b)),
+        I64Mul => binary(stack, |a: i64, b| a.wrapping_mul(b)),
+        I64DivS => trapping_binary(stack, i64_ops::div)?,
+        I64DivU => trapping_binary(stack, |a, b| u64_ops::div(a, b).map(|r| r as i64))?,
+        I64RemS => trapping_binary(stack, i64_ops::rem)?,
+        I64RemU => trapping_binary(stack, |a, b| u64_ops::rem(a, b).map(|r| r as i64))?,
+        I64And => binary(stack, |a: i64, b| a & b),
+        I64Or => binary(stack, |a: i64, b| a | b),
+        I64Xor => binary(stack, |a: i64, b| a ^ b),
+        I64Shl => binary(stack, |a: i64, b| a.wrapping_shl(b as u32)),
+        I64ShrS => binary(stack, |a: i64, b| a.wrapping_shr(b as u32)),
+        I64ShrU => binary(stack, |a: u64, b| a.wrapping_shr(b as u32) as i64),
+        I64Rotl => binary(stack, |a: u64, b| a.rotate_left((b % 64) as u32) as i64),
+        I64Rotr => binary(stack, |a: u64, b| a.rotate_right((b % 64) as u32) as i64),
+
+        F32Abs => unary(stack, |a: f32| a.abs()),
+        F32Neg => unary(stack, |a: f32| -a),
+        F32Ceil => unary(stack, |a: f32| a.ceil()),
+        F32Floor => unary(stack, |a: f32| a.floor()),
+        F32Trunc => unary(stack, |a: f32| a.trunc()),
+        F32Nearest => unary(stack, |a: f32| a.round_ties_even()),
+        F32Sqrt => unary(stack, |a: f32| a.sqrt()),
+        F32Add => binary(stack, |a: f32, b| a + b),
+        F32Sub => binary(stack, |a: f32, b| a - b),
+        F32Mul => binary(stack, |a: f32, b| a * b),
+        F32Div => binary(stack, |a: f32, b| a / b),
+        F32Min => binary(stack, f32_ops::min),
+        F32Max => binary(stack, f32_ops::max),
+        F32CopySign => binary(stack, |a: f32, b| a.copysign(b)),
+
+        F64Abs => unary(stack, |a: f64| a.abs()),
+        F64Neg => unary(stack, |a: f64| -a),
+        F64Ceil => unary(stack, |a: f64| a.ceil()),
+        F64Floor => unary(stack, |a: f64| a.floor()),
+        F64Trunc => unary(stack, |a: f64| a.trunc()),
+        F64Nearest => unary(stack, |a: f64| a.round_ties_even()),
+        F64Sqrt => unary(stack, |a: f64| a.sqrt()),
+        F64Add => binary(stack, |a: f64, b| a + b),
+        F64Sub => binary(stack, |a: f64, b| a - b),
+        F64Mul => binary(stack, |a: f64, b| a * b),
+        F64Div => binary(stack, |a: f64, b| a / b),
+        F64Min => binary(stack, f64_ops::min),
+        F64Max => binary(stack, f64_ops::max),
+        F64CopySign => binary(stack, |a: f64, b| a.copysign(b)),
+
+        I32WrapI64 => unary(stack, |a: i64| a as i32),
+        I32TruncF32S => trapping_unary(stack, |a: f32| {
+            truncate(a as f64, I32_RANGE.0, I32_RANGE.1).map(|r| r as i32)
+        })?,
+        I32TruncF32U => trapping_unary(stack, |a: f32| {
+            truncate(a as f64, U32_RANGE.0, U32_RANGE.1).map(|r| r as u32 as i32)
+        })?,
+        I32TruncF64S => trapping_unary(stack, |a: f64| {
+            truncate(a, I32_RANGE.0, I32_RANGE.1).map(|r| r as i32)
+        })?,
+        I32TruncF64U => trapping_unary(stack, |a: f64| {
+            truncate(a, U32_RANGE.0, U32_RANGE.1).map(|r| r as u32 as i32)
+        })?,
+        I64ExtendI32S => unary(stack, |a: i32| a as i64),
+        I64ExtendI32U => unary(stack, |a: u32| a as i64),
+        I64TruncF32S => trapping_unary(stack, |a: f32| {
+            truncate(a as f64, I64_RANGE.0, I64_RANGE.1).map(|r| r as i64)
+        })?,
+        I64TruncF32U => trapping_unary(stack, |a: f32| {
+            truncate(a as f64, U64_RANGE.0, U64_RANGE.1).map(|r| r as u64 as i64)
+        })?,
+        I64TruncF64S => trapping_unary(stack, |a: f64| {
+            truncate(a, I64_RANGE.0, I64_RANGE.1).map(|r| r as i64)
+        })?,
+        I64TruncF64U => trapping_unary(stack, |a: f64| {
+            truncate(a, U64_RANGE.0, U64_RANGE.1).map(|r| r as u64 as i64)
+        })?,
+        F32ConvertI32S => unary(stack, |a: i32| a as f32),
+        F32ConvertI32U => unary(stack, |a: u32| a as f32),
+        F32ConvertI64S => unary(stack, |a: i64| a as f32),
+        F32ConvertI64u => unary(stack, |a: u64| a as f32),
+        F32DemoteF64 => unary(stack, |a: f64| a as f32),
+        F64ConvertI32S => unary(stack, |a: i32| a as f64),
+        F64ConvertI32U => unary(stack, |a: u32| a as f64),
+        F64ConvertI64S => unary(stack, |a: i64| a as f64),
+        F64ConvertI64u => unary(stack, |a: u64| a as f64),
+        F64PromoteF32 => unary(stack, |a: f32| a as f64),
+        I32ReinterpretF32 => unary(stack, |a: f32| a.to_bits() as i32),
+        I64ReinterpretF64 => unary(stack, |a: f64| a.to_bits() as i64),
+        F32ReinterpretI32 => unary(stack, |a: u32| f32::from_bits(a)),
+        F64ReinterpretI64 => unary(stack, |a: u64| f64::from_bits(a)),
+        I32Extend8S => unary(stack, |a: i32| a as i8 as i32),
+        I32Extend16S => unary(stack, |a: i32| a as i16 as i32),
+        I64Extend8S => unary(stack, |a: i64| a as i8 as i64),
+        I64Extend16S => unary(stack, |a: i64| a as i16 as i64),
+        I64Extend32S => unary(stack, |a: i64| a as i32 as i64),
+    }
+    Ok(())
+}
+
+///`as` casts from floats already saturate and map NaN to zero
+pub(crate) fn execute_saturating(stack: &mut Stack, instruction: &SaturatingTruncationInstruction) {
+    use SaturatingTruncationInstruction::*;
+    match instruction {
+        I32TruncSatF32S => unary(stack, |a: f32| a as i32),
+        I32TruncSatF32U => unary(stack, |a: f32| a as u32 as i32),
+        I32TruncSatF64S => unary(stack, |a: f64| a as i32),
+        I32TruncSatF64U => unary(stack, |a: f64| a as u32 as i32),
+        I64TruncSatF32S => unary(stack, |a: f32| a as i64),
+        I64TruncSatF32U => unary(stack, |a: f32| a as u64 as i64),
+        I64TruncSatF64S => unary(stack, |a: f64| a as i64),
+        I64TruncSatF64U => unary(stack, |a: f64| a as u64 as i64),
+    }
+}
@@ -0,0 +1,147 @@
+use wasm::types::{NumType, RefType, ValueType};
+
+///index of a function in the instance's function space
+pub type FuncAddr = u32;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    ///the bits of a SIMD vector, in little endian order
+    V128(u128),
+    FuncRef(Option<FuncAddr>),
+    ExternRef(Option<u32>),
+    ///always null, the interpreter doesn't throw exceptions
+    ExnRef(Option<u32>),
+}
+
+impl Value {
+    ///the zero value locals and tables start with
+    pub fn default_of(value_type: ValueType) -> Self {
+        match value_type {
+            ValueType::NumType(NumType::I32) => Value::I32(0),
+            ValueType::NumType(NumType::I64) => Value::I64(0),
+            ValueType::NumType(NumType::F32) => Value::F32(0.0),
+            ValueType::NumType(NumType::F64) => Value::F64(0.0),
+            ValueType::V128 => Value::V128(0),
+            ValueType::RefType(ref_type) => Value::null(ref_type),
+        }
+    }
+    pub fn null(ref_type: RefType) -> Self {
+        match ref_type {
+            RefType::FuncRef => Value::FuncRef(None),
+            RefType::ExternRef => Value::ExternRef(None),
+            RefType::ExnRef => Value::ExnRef(None),
+        }
+    }
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            Value::I32(_) => ValueType::NumType(NumType::I32),
+            Value::I64(_) => ValueType::NumType(NumType::I64),
+            Value::F32(_) => ValueType::NumType(NumType::F32),
+            Value::F64(_) => ValueType::NumType(NumType::F64),
+            Value::V128(_) => ValueType::V128,
+            Value::FuncRef(_) => ValueType::RefType(RefType::FuncRef),
+            Value::ExternRef(_) => ValueType::RefType(RefType::ExternRef),
+            Value::ExnRef(_) => ValueType::RefType(RefType::ExnRef),
+        }
+    }
+    pub fn is_null(&self) -> bool {
+        matches!(
+            self,
+            Value::FuncRef(None) | Value::ExternRef(None) | Value::ExnRef(None)
+        )
+    }
+}
+
+impl From<i32> for Value {
+    fn from(value: i32) -> Self {
+        Value::I32(value)
+    }
+}
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::I64(value)
+    }
+}
+impl From<f32> for Value {
+    fn from(value: f32) -> Self {
+        Value::F32(value)
+    }
+}
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::F64(value)
+    }
+}
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::I32(value as i32)
+    }
+}
+
+///extraction of a typed operand, validation guarantees the type matches
+pub(crate) trait FromValue: Sized {
+    fn from_value(value: Value) -> Self;
+}
+macro_rules! from_value {
+    ($($ty:ty => $variant:ident),*) => {
+        $(
+            impl FromValue for $ty {
+                fn from_value(value: Value) -> Self {
+                    match value {
+                        Value::$variant(value) => value,
+                        other => panic!(
+                            "expected {} operand, found {:?}",
+                            stringify!($variant),
+                            other
+                        ),
+                    }
+                }
+            }
+        )*
+    };
+}
+from_value!(i32 => I32, i64 => I64, f32 => F32, f64 => F64);
+impl FromValue for u32 {
+    fn from_value(value: Value) -> Self {
+        i32::from_value(value) as u32
+    }
+}
+impl FromValue for u64 {
+    fn from_value(value: Value) -> Self {
+        i64::from_value(value) as u64
+    }
+}
+impl FromValue for Value {
+    fn from_value(value: Value) -> Self {
+        value
+    }
+}
+
+///operand stack of a single function activation
+#[derive(Debug, Default)]
+pub(crate) struct Stack(pub Vec<Value>);
+impl Stack {
+    pub fn push(&mut self, value: impl Into<Value>) {
+        self.0.push(value.into())
+    }
+    pub fn pop<T: FromValue>(&mut self) -> T {
+        T::from_value(self.0.pop().expect("operand stack underflow"))
+    }
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    ///removes the top `n` values, keeping their order
+    pub fn split_off(&mut self, n: usize) -> Vec<Value> {
+        self.0.split_off(self.0.len() - n)
+    }
+    ///drops every value above `height` except the top `keep`
+    pub fn unwind(&mut self, height: usize, keep: usize) {
+        let kept = self.split_off(keep);
+        self.0.truncate(height);
+        self.0.extend(kept);
+    }
+}
@@ -0,0 +1,323 @@
+use nom::error::{ErrorKind as NomErrorKind, VerboseError, VerboseErrorKind};
+use std::convert::TryInto;
+use std::fmt::{self, Display};
+use wasm_core::values::{Parse, INVALID_UTF8};
+
+//contexts marking failures with a specific kind, the other contexts name AST nodes
+pub(crate) const BAD_MAGIC: &str = "bad magic";
+pub(crate) const UNSUPPORTED_VERSION: &str = "unsupported version";
+pub(crate) const UNKNOWN_SECTION_ID: &str = "unknown section id";
+pub(crate) const SECTION_SIZE_MISMATCH: &str = "section size mismatch";
+pub(crate) const CODE_SIZE_MISMATCH: &str = "code size mismatch";
+pub(crate) const SECTION_OUT_OF_ORDER: &str = "section out of order";
+pub(crate) const DUPLICATE_SECTION: &str = "duplicate section";
+pub(crate) const UNKNOWN_OPCODE: &str = "unknown opcode";
+///marks the end of a region parsed on its own, see [crate::modules::sized]
+pub(crate) const BOUNDED: &str = "bounded";
+
+///what went wrong while decoding a binary module
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    BadMagic,
+    UnsupportedVersion([u8; 4]),
+    UnknownSectionId(u8),
+    ///the contents of a section don't match its declared size
+    SectionSizeMismatch {
+        id: u8,
+        declared: u32,
+    },
+    ///a function body doesn't match its declared size
+    CodeSizeMismatch {
+        declared: u32,
+    },
+    ///a section after one that must follow it
+    SectionOutOfOrder(u8),
+    DuplicateSection(u8),
+    ///an opcode without an instruction, with its prefix byte if it has one
+    UnknownOpcode {
+        prefix: Option<u8>,
+        opcode: u32,
+    },
+    Leb128Overflow,
+    InvalidUtf8Name,
+    UnexpectedEof,
+    ///any other malformed input, the context tells where
+    Malformed,
+}
+///a decoding error, with the byte offset it happened at and the AST nodes enclosing it,
+///outermost first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub offset: usize,
+    pub context: Vec<&'static str>,
+}
+
+impl Error {
+    ///converts the error from parsing `input` with the [VerboseError] collecting contexts
+    pub(crate) fn from_nom(input: &[u8], error: nom::Err<VerboseError<&[u8]>>) -> Self {
+        let errors = match error {
+            nom::Err::Error(error) | nom::Err::Failure(error) => error.errors,
+            nom::Err::Incomplete(_) => {
+                return Self {
+                    kind: ErrorKind::UnexpectedEof,
+                    offset: input.len(),
+                    context: vec![],
+                }
+            }
+        };
+        //positions are the length of the input left after them. in a region parsed on its own
+        //that stops at the end of the region, the `BOUNDED` marker adds the bytes after it
+        let fallback = |rest: &[u8]| match rest {
+            [] => (ErrorKind::UnexpectedEof, rest.len()),
+            _ => (ErrorKind::Malformed, rest.len()),
+        };
+        let mut kind = None;
+        let mut context = vec![];
+        //innermost first, the first specific failure wins
+        for (rest, error) in &errors {
+            if let VerboseErrorKind::Context(BOUNDED) = error {
+                let (inner, left) = kind.take().unwrap_or_else(|| fallback(errors[0].0));
+                //running out of the region is reported by the size mismatch context after it,
+                //at the entry, so the nodes inside the region are dropped
+                match inner {
+                    ErrorKind::UnexpectedEof => context.clear(),
+                    inner => kind = Some((inner, left + rest.len())),
+                }
+                continue;
+            }
+            //the failing input may be cut off before the bytes a kind reports
+            let first = |kind: fn(u8) -> ErrorKind| {
+                rest.first()
+                    .map_or(ErrorKind::UnexpectedEof, |&byte| kind(byte))
+            };
+            let found = match error {
+                VerboseErrorKind::Context(BAD_MAGIC) => Some(ErrorKind::BadMagic),
+                VerboseErrorKind::Context(UNSUPPORTED_VERSION) => Some(
+                    match rest.get(..4).and_then(|version| version.try_into().ok()) {
+                        Some(version) => ErrorKind::UnsupportedVersion(version),
+                        None => ErrorKind::UnexpectedEof,
+                    },
+                ),
+                VerboseErrorKind::Context(UNKNOWN_SECTION_ID) => {
+                    Some(first(ErrorKind::UnknownSectionId))
+                }
+                VerboseErrorKind::Context(SECTION_SIZE_MISMATCH) => Some(match rest {
+                    [id, size @ ..] => {
+                        let (_, declared) = u32::parse_simple(size).unwrap_or((size, 0));
+                        ErrorKind::SectionSizeMismatch { id: *id, declared }
+                    }
+                    [] => ErrorKind::UnexpectedEof,
+                }),
+                VerboseErrorKind::Context(CODE_SIZE_MISMATCH) => {
+                    let (_, declared) = u32::parse_simple(rest).unwrap_or((rest, 0));
+                    Some(ErrorKind::CodeSizeMismatch { declared })
+                }
+                VerboseErrorKind::Context(SECTION_OUT_OF_ORDER) => {
+                    Some(first(ErrorKind::SectionOutOfOrder))
+                }
+                VerboseErrorKind::Context(DUPLICATE_SECTION) => {
+                    Some(first(ErrorKind::DuplicateSection))
+                }
+                VerboseErrorKind::Context(UNKNOWN_OPCODE) => Some(unknown_opcode(rest)),
+                VerboseErrorKind::Context(INVALID_UTF8) => Some(ErrorKind::InvalidUtf8Name),
+                //nom_leb128 adds its own message
+                VerboseErrorKind::Context(message) if message.starts_with("LEB128") => None,
+                VerboseErrorKind::Context(node) => {
+                    context.push(*node);
+                    None
+                }
+                VerboseErrorKind::Nom(NomErrorKind::TooLarge) => Some(ErrorKind::Leb128Overflow),
+                VerboseErrorKind::Nom(NomErrorKind::Eof) => Some(ErrorKind::UnexpectedEof),
+                _ => None,
+            };
+            if let (None, Some(found)) = (&kind, found) {
+                kind = Some((found, rest.len()));
+            }
+        }
+        context.reverse();
+        let (kind, left) = kind.unwrap_or_else(|| fallback(errors[0].0));
+        Self {
+            kind,
+            offset: input.len() - left,
+            context,
+        }
+    }
+}
+
+///the opcode at the start of `rest`, prefix bytes are followed by a LEB128 opcode
+fn unknown_opcode(rest: &[u8]) -> ErrorKind {
+    match rest {
+        [prefix @ 0xFC..=0xFE, opcode @ ..] => match u32::parse_simple(opcode) {
+            Ok((_, opcode)) => ErrorKind::UnknownOpcode {
+                prefix: Some(*prefix),
+                opcode,
+            },
+            Err(_) => ErrorKind::UnexpectedEof,
+        },
+        [opcode, ..] => ErrorKind::UnknownOpcode {
+            prefix: None,
+            opcode: *opcode as u32,
+        },
+        [] => ErrorKind::UnexpectedEof,
+    }
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::BadMagic => write!(f, "bad magic number"),
+            ErrorKind::UnsupportedVersion(version) => {
+                write!(f, "unsupported version {}", u32::from_le_bytes(*version))
+            }
+            ErrorKind::UnknownSectionId(id) => write!(f, "unknown section id {}", id),
+            ErrorKind::SectionSizeMismatch { id, declared } => write!(
+                f,
+                "section {} doesn't match its declared size of {} bytes",
+                id, declared
+            ),
+            ErrorKind::CodeSizeMismatch { declared } => write!(
+                f,
+                "function body doesn't match its declared size of {} bytes",
+                declared
+            ),
+            ErrorKind::SectionOutOfOrder(id) => write!(f, "section {} out of order", id),
+            ErrorKind::DuplicateSection(id) => write!(f, "duplicate section {}", id),
+            ErrorKind::UnknownOpcode {
+                prefix: None,
+                opcode,
+            } => write!(f, "unknown opcode {:#04x}", opcode),
+            ErrorKind::UnknownOpcode {
+                prefix: Some(prefix),
+                opcode,
+            } => write!(f, "unknown opcode {:#04x} {}", prefix, opcode),
+            ErrorKind::Leb128Overflow => write!(f, "LEB128 integer too large"),
+            ErrorKind::InvalidUtf8Name => write!(f, "name is not valid utf-8"),
+            ErrorKind::UnexpectedEof => write!(f, "unexpected end of input"),
+            ErrorKind::Malformed => write!(f, "malformed input"),
+        }
+    }
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#x}: {}", self.offset, self.kind)?;
+        if !self.context.is_empty() {
+            write!(f, " (in {})", self.context.join(" > "))?;
+        }
+        Ok(())
+    }
+}
+impl std::error::Error for Error {}
+
+///the error of a function body decoded on its own
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeError {
+    ///the position of the body in the code section. imported functions aren't counted,
+    ///so this is the function index minus the number of imported functions
+    pub entry: u32,
+    pub error: Error,
+}
+impl Display for CodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "code section, entry {}: {}", self.entry, self.error)
+    }
+}
+impl std::error::Error for CodeError {}
+
+#[test]
+fn from_bytes() {
+    use crate::{
+        fixtures::{module_bytes, HEADER},
+        modules::Module,
+    };
+    let module = |sections: &[u8]| module_bytes(&[sections]);
+    let error = |bytes: &[u8]| Module::from_bytes(bytes).unwrap_err();
+    let file = include_bytes!("loop.wasm");
+    assert_eq!(Module::from_bytes(file).unwrap().sections.len(), 6);
+
+    assert_eq!(error(b"\0asn\x01\0\0\0").kind, ErrorKind::BadMagic);
+    let version = error(b"\0asm\x02\0\0\0");
+    assert_eq!(version.kind, ErrorKind::UnsupportedVersion([2, 0, 0, 0]));
+    assert_eq!(version.offset, 4);
+    //cut off before the end of the version
+    for truncated in [&b"\0asm"[..], b"\0asm\x01\0"] {
+        assert_eq!(error(truncated).kind, ErrorKind::UnexpectedEof);
+    }
+    let section = error(&module(&[0x0E, 0x00]));
+    assert_eq!(section.kind, ErrorKind::UnknownSectionId(14));
+    assert_eq!(
+        section.to_string(),
+        "0x8: unknown section id 14 (in Module)"
+    );
+    let custom = error(&module(&[0x00, 0x01, 0x04, b'n', b'a', b'm', b'e']));
+    let size = ErrorKind::SectionSizeMismatch { id: 0, declared: 1 };
+    assert_eq!((custom.kind, custom.offset), (size, 8));
+    let overflow = error(&module(&[0x01, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01]));
+    assert_eq!(overflow.kind, ErrorKind::Leb128Overflow);
+    let export = error(&module(&[0x07, 0x05, 0x01, 0x01, 0xFF, 0x00, 0x00]));
+    assert_eq!(export.kind, ErrorKind::InvalidUtf8Name);
+    assert_eq!(export.context, ["Module", "ExportSection", "Export"]);
+    assert_eq!(
+        error(&module(&[0x01, 0x05, 0x01])).kind,
+        ErrorKind::UnexpectedEof
+    );
+
+    //a func with `block (unknown) end end` as its body
+    let code = [0x0A, 0x08, 0x01, 0x06, 0x00, 0x02, 0x40, 0xFF, 0x0B, 0x0B];
+    let types = [0x01, 0x04, 0x01, 0x60, 0x00, 0x00, 0x03, 0x02, 0x01, 0x00];
+    let opcode = error(&module(&[&types[..], &code].concat()));
+    let unknown = ErrorKind::UnknownOpcode {
+        prefix: None,
+        opcode: 0xFF,
+    };
+    assert_eq!(opcode.kind, unknown);
+    assert_eq!(opcode.offset, HEADER.len() + types.len() + 7);
+    let nodes = ["Module", "CodeSection", "Code", "Func", "Expression"];
+    assert_eq!(opcode.context, [&nodes[..], &["Instruction"; 2]].concat());
+}
+#[test]
+fn declared_sizes() {
+    use crate::{
+        fixtures::{module_bytes, HEADER},
+        modules::Module,
+    };
+    let error = |sections: &[&[u8]]| Module::from_bytes(&module_bytes(sections)).unwrap_err();
+    let functions = [0x03, 0x02, 0x01, 0x00];
+    let longer = error(&[&[0x01, 0x05, 0x01, 0x60, 0x00, 0x00], &functions]);
+    let size = ErrorKind::SectionSizeMismatch { id: 1, declared: 5 };
+    assert_eq!((longer.kind, longer.offset), (size, 8));
+    let shorter = error(&[&[0x01, 0x03, 0x01, 0x60, 0x00, 0x00], &functions]);
+    let size = ErrorKind::SectionSizeMismatch { id: 1, declared: 3 };
+    assert_eq!(shorter.kind, size);
+
+    //`nop end` in a body declared as 2 bytes long
+    let types = [0x01, 0x04, 0x01, 0x60, 0x00, 0x00];
+    let code = [0x0A, 0x05, 0x01, 0x02, 0x00, 0x01, 0x0B];
+    let body = error(&[&types, &functions, &code]);
+    assert_eq!(body.kind, ErrorKind::CodeSizeMismatch { declared: 2 });
+    assert_eq!(
+        body.offset,
+        HEADER.len() + types.len() + functions.len() + 3
+    );
+    assert_eq!(body.context, ["Module", "CodeSection", "Code"]);
+
+    //`(unknown) end` in the first of two bodies, followed by a custom section
+    let functions = [0x03, 0x03, 0x02, 0x00, 0x00];
+    let code = [0x0A, 0x09, 0x02, 0x03, 0x00, 0xFF, 0x0B, 0x02, 0x00, 0x0B];
+    let custom = [0x00, 0x02, 0x01, b'a'];
+    let opcode = error(&[&types, &functions, &code, &custom]);
+    let unknown = ErrorKind::UnknownOpcode {
+        prefix: None,
+        opcode: 0xFF,
+    };
+    assert_eq!(opcode.kind, unknown);
+    assert_eq!(
+        opcode.offset,
+        HEADER.len() + types.len() + functions.len() + 5
+    );
+    //`i32.const` cut off by the end of the first body
+    let code = [0x0A, 0x07, 0x02, 0x02, 0x00, 0x41, 0x02, 0x00, 0x0B];
+    let cut = error(&[&types, &functions, &code]);
+    assert_eq!(cut.kind, ErrorKind::CodeSizeMismatch { declared: 2 });
+    assert_eq!(cut.offset, HEADER.len() + types.len() + functions.len() + 3);
+}
@@ -0,0 +1,8 @@
+//!binary modules put together by hand in the tests
+
+///the magic and version binary modules start with
+pub(crate) const HEADER: [u8; 8] = [0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00];
+///a binary module made of the encoded `sections`
+pub(crate) fn module_bytes(sections: &[&[u8]]) -> Vec<u8> {
+    [&HEADER[..], &sections.concat()].concat()
+}
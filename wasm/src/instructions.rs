@@ -1,45 +1,112 @@
 use self::{
+    atomic::AtomicInstruction,
     control::ControlInstruction,
     memory::MemoryInstruction,
     numeric::{NumericInstruction, SaturatingTruncationInstruction},
     parametric::ParametricInstruction,
     reference::ReferenceInstruction,
+    simd::{RelaxedSimdInstruction, SimdInstruction},
     table::TableInstruction,
     variable::VariableInstruction,
 };
-use crate::Suffix;
-use nom::{branch::alt, combinator::map, multi};
-use wasm_core::values::Parse;
+use crate::{error::UNKNOWN_OPCODE, Suffix};
+use nom::{combinator::map, multi};
+use wasm_core::values::{in_context, Encode, Parse};
 
-mod control;
-mod memory;
-mod numeric;
-mod parametric;
-mod reference;
-mod table;
-mod variable;
+///an instruction family under a prefix byte, followed by its `u32` opcodes
+macro_rules! prefixed {
+    ($prefix:literal, $(#[$attr:meta])* $name:ident { $($opcode:literal => $variant:ident $(($immediate:ty))?,)* }) => {
+        $(#[$attr])*
+        #[derive(Debug)]
+        pub enum $name {
+            $($variant $(($immediate))?,)*
+        }
+        impl<'a> Parse<'a> for $name {
+            fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
+            where
+                E: nom::error::ParseError<&'a [u8]>
+                    + nom::error::ContextError<&'a [u8]>
+                    + std::fmt::Debug,
+            {
+                let start = i;
+                let (i, _) = <$crate::Suffix<$prefix>>::parse(i)?;
+                let (i, opcode) = u32::parse(i)?;
+                match opcode {
+                    $($opcode => prefixed!(@parse i, $variant $(, $immediate)?),)*
+                    _ => $crate::instructions::unknown_opcode(start),
+                }
+            }
+        }
+        impl Encode for $name {
+            fn encode(&self, out: &mut Vec<u8>) {
+                ($prefix as u8).encode(out);
+                match self {
+                    $(prefixed!(@pattern $variant, immediate $(, $immediate)?) => {
+                        ($opcode as u32).encode(out);
+                        prefixed!(@encode immediate, out $(, $immediate)?);
+                    })*
+                }
+            }
+        }
+    };
+    (@parse $i:ident, $variant:ident) => {
+        Ok(($i, Self::$variant))
+    };
+    (@parse $i:ident, $variant:ident, $immediate:ty) => {
+        nom::combinator::map(<$immediate>::parse, Self::$variant)($i)
+    };
+    (@pattern $variant:ident, $binding:ident) => {
+        Self::$variant
+    };
+    (@pattern $variant:ident, $binding:ident, $immediate:ty) => {
+        Self::$variant($binding)
+    };
+    (@encode $binding:ident, $out:ident) => {};
+    (@encode $binding:ident, $out:ident, $immediate:ty) => {
+        $binding.encode($out)
+    };
+}
+
+pub mod atomic;
+pub mod control;
+pub mod memory;
+pub mod numeric;
+pub mod operator;
+pub mod parametric;
+pub mod reference;
+pub mod simd;
+pub mod table;
+pub mod variable;
 
 #[derive(Debug)]
-pub struct Instructions(Vec<Instruction>);
+pub struct Instructions(pub Vec<Instruction>);
 impl<const END: u8> From<TerminatedInstructionSequence<END>> for Instructions {
     fn from(seq: TerminatedInstructionSequence<END>) -> Self {
         Self(seq.0)
     }
 }
 impl Instructions {
-    fn parse_with_finalizer<'a, E, const END: u8>(i: &'a [u8]) -> nom::IResult<&[u8], Self, E>
+    fn parse_with_finalizer<'a, E, const END: u8>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
     where
         E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
     {
         let (i, seq) = <TerminatedInstructionSequence<END>>::parse(i)?;
         Ok((i, seq.into()))
     }
-    fn empty() -> Self {
-        Self(vec![])
+    fn encode_with_finalizer(&self, out: &mut Vec<u8>, end: u8) {
+        for instruction in &self.0 {
+            instruction.encode(out);
+        }
+        end.encode(out);
+    }
+}
+impl Encode for Instructions {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.encode_with_finalizer(out, 0x0B)
     }
 }
-impl Parse for Instructions {
-    fn parse<'a, E>(i: &'a [u8]) -> nom::IResult<&[u8], Self, E>
+impl<'a> Parse<'a> for Instructions {
+    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
     where
         E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
     {
@@ -62,59 +129,97 @@ pub enum Instruction {
     Memory(MemoryInstruction),
     Numeric(NumericInstruction),
     Saturating(SaturatingTruncationInstruction),
+    Simd(SimdInstruction),
+    RelaxedSimd(RelaxedSimdInstruction),
+    Atomic(AtomicInstruction),
 }
-impl Parse for Instruction {
-    fn parse<'a, E>(i: &'a [u8]) -> nom::IResult<&[u8], Self, E>
+impl<'a> Parse<'a> for Instruction {
+    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
     where
         E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
     {
-        let parsers = (
-            map(ControlInstruction::parse, |instruction| {
-                Self::Control(instruction)
-            }),
-            map(ReferenceInstruction::parse, |instruction| {
-                Self::Reference(instruction)
-            }),
-            map(ParametricInstruction::parse, |instruction| {
-                Self::Parametric(instruction)
-            }),
-            map(VariableInstruction::parse, |instruction| {
-                Self::Variable(instruction)
-            }),
-            map(TableInstruction::parse, |instruction| {
-                Self::Table(instruction)
-            }),
-            map(MemoryInstruction::parse, |instruction| {
-                Self::Memory(instruction)
-            }),
-            map(NumericInstruction::parse, |instruction| {
-                Self::Numeric(instruction)
-            }),
-            map(SaturatingTruncationInstruction::parse, |instruction| {
-                Self::Saturating(instruction)
-            }),
-        );
-        alt(parsers)(i)
+        //dispatching on the opcode keeps the errors of nested instructions
+        let (_, opcode) = u8::parse(i)?;
+        let parsed = match opcode {
+            0x00..=0x04 | 0x06 | 0x08..=0x0A | 0x0C..=0x11 | 0x1F => {
+                map(ControlInstruction::parse, Self::Control)(i)
+            }
+            0xD0..=0xD2 => map(ReferenceInstruction::parse, Self::Reference)(i),
+            0x1A..=0x1C => map(ParametricInstruction::parse, Self::Parametric)(i),
+            0x20..=0x24 => map(VariableInstruction::parse, Self::Variable)(i),
+            0x25 | 0x26 => map(TableInstruction::parse, Self::Table)(i),
+            0x28..=0x40 => map(MemoryInstruction::parse, Self::Memory)(i),
+            0x41..=0xC4 => map(NumericInstruction::parse, Self::Numeric)(i),
+            0xFC => match u32::parse(&i[1..])?.1 {
+                0..=7 => map(SaturatingTruncationInstruction::parse, Self::Saturating)(i),
+                8..=11 => map(MemoryInstruction::parse, Self::Memory)(i),
+                12..=17 => map(TableInstruction::parse, Self::Table)(i),
+                _ => unknown_opcode(i),
+            },
+            0xFD => match u32::parse(&i[1..])?.1 {
+                0x100..=0x113 => map(RelaxedSimdInstruction::parse, Self::RelaxedSimd)(i),
+                _ => map(SimdInstruction::parse, Self::Simd)(i),
+            },
+            0xFE => map(AtomicInstruction::parse, Self::Atomic)(i),
+            _ => unknown_opcode(i),
+        };
+        in_context(i, "Instruction", parsed)
+    }
+}
+
+fn unknown_opcode<'a, T, E>(i: &'a [u8]) -> nom::IResult<&'a [u8], T, E>
+where
+    E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]>,
+{
+    let error = E::from_error_kind(i, nom::error::ErrorKind::Switch);
+    Err(nom::Err::Error(E::add_context(i, UNKNOWN_OPCODE, error)))
+}
+
+impl Encode for Instruction {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::Control(instruction) => instruction.encode(out),
+            Self::Reference(instruction) => instruction.encode(out),
+            Self::Parametric(instruction) => instruction.encode(out),
+            Self::Variable(instruction) => instruction.encode(out),
+            Self::Table(instruction) => instruction.encode(out),
+            Self::Memory(instruction) => instruction.encode(out),
+            Self::Numeric(instruction) => instruction.encode(out),
+            Self::Saturating(instruction) => instruction.encode(out),
+            Self::Simd(instruction) => instruction.encode(out),
+            Self::RelaxedSimd(instruction) => instruction.encode(out),
+            Self::Atomic(instruction) => instruction.encode(out),
+        }
     }
 }
 
 #[derive(Debug)]
-pub struct Expression(Vec<Instruction>);
-impl Parse for Expression {
-    fn parse<'a, E>(i: &'a [u8]) -> nom::IResult<&[u8], Self, E>
+pub struct Expression(pub Vec<Instruction>);
+impl<'a> Parse<'a> for Expression {
+    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
     where
         E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
     {
-        map(<TerminatedInstructionSequence<0x0B>>::parse, |seq| {
+        let parsed = map(<TerminatedInstructionSequence<0x0B>>::parse, |seq| {
             Self(seq.0)
-        })(i)
+        })(i);
+        in_context(i, "Expression", parsed)
+    }
+}
+
+impl Encode for Expression {
+    fn encode(&self, out: &mut Vec<u8>) {
+        for instruction in &self.0 {
+            instruction.encode(out);
+        }
+        0x0Bu8.encode(out);
     }
 }
 
 struct TerminatedInstructionSequence<const END: u8>(Vec<Instruction>);
 
-impl<const END: u8> Parse for TerminatedInstructionSequence<END> {
-    fn parse<'a, E>(i: &'a [u8]) -> nom::IResult<&[u8], Self, E>
+impl<'a, const END: u8> Parse<'a> for TerminatedInstructionSequence<END> {
+    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
     where
         E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
     {
@@ -0,0 +1,200 @@
+//!the threads proposal, whose atomic memory accesses are prefixed by `0xFE`
+use super::memory::MemArg;
+use crate::Suffix;
+use wasm_core::values::{Encode, Parse};
+
+prefixed!(
+    0xFE,
+    ///the atomic instructions of the threads proposal
+    AtomicInstruction {
+    0x00 => MemoryAtomicNotify(MemArg),
+    0x01 => MemoryAtomicWait32(MemArg),
+    0x02 => MemoryAtomicWait64(MemArg),
+    0x03 => AtomicFence(Suffix<0x00>),
+    0x10 => I32AtomicLoad(MemArg),
+    0x11 => I64AtomicLoad(MemArg),
+    0x12 => I32AtomicLoad8U(MemArg),
+    0x13 => I32AtomicLoad16U(MemArg),
+    0x14 => I64AtomicLoad8U(MemArg),
+    0x15 => I64AtomicLoad16U(MemArg),
+    0x16 => I64AtomicLoad32U(MemArg),
+    0x17 => I32AtomicStore(MemArg),
+    0x18 => I64AtomicStore(MemArg),
+    0x19 => I32AtomicStore8(MemArg),
+    0x1A => I32AtomicStore16(MemArg),
+    0x1B => I64AtomicStore8(MemArg),
+    0x1C => I64AtomicStore16(MemArg),
+    0x1D => I64AtomicStore32(MemArg),
+    0x1E => I32AtomicRmwAdd(MemArg),
+    0x1F => I64AtomicRmwAdd(MemArg),
+    0x20 => I32AtomicRmw8AddU(MemArg),
+    0x21 => I32AtomicRmw16AddU(MemArg),
+    0x22 => I64AtomicRmw8AddU(MemArg),
+    0x23 => I64AtomicRmw16AddU(MemArg),
+    0x24 => I64AtomicRmw32AddU(MemArg),
+    0x25 => I32AtomicRmwSub(MemArg),
+    0x26 => I64AtomicRmwSub(MemArg),
+    0x27 => I32AtomicRmw8SubU(MemArg),
+    0x28 => I32AtomicRmw16SubU(MemArg),
+    0x29 => I64AtomicRmw8SubU(MemArg),
+    0x2A => I64AtomicRmw16SubU(MemArg),
+    0x2B => I64AtomicRmw32SubU(MemArg),
+    0x2C => I32AtomicRmwAnd(MemArg),
+    0x2D => I64AtomicRmwAnd(MemArg),
+    0x2E => I32AtomicRmw8AndU(MemArg),
+    0x2F => I32AtomicRmw16AndU(MemArg),
+    0x30 => I64AtomicRmw8AndU(MemArg),
+    0x31 => I64AtomicRmw16AndU(MemArg),
+    0x32 => I64AtomicRmw32AndU(MemArg),
+    0x33 => I32AtomicRmwOr(MemArg),
+    0x34 => I64AtomicRmwOr(MemArg),
+    0x35 => I32AtomicRmw8OrU(MemArg),
+    0x36 => I32AtomicRmw16OrU(MemArg),
+    0x37 => I64AtomicRmw8OrU(MemArg),
+    0x38 => I64AtomicRmw16OrU(MemArg),
+    0x39 => I64AtomicRmw32OrU(MemArg),
+    0x3A => I32AtomicRmwXor(MemArg),
+    0x3B => I64AtomicRmwXor(MemArg),
+    0x3C => I32AtomicRmw8XorU(MemArg),
+    0x3D => I32AtomicRmw16XorU(MemArg),
+    0x3E => I64AtomicRmw8XorU(MemArg),
+    0x3F => I64AtomicRmw16XorU(MemArg),
+    0x40 => I64AtomicRmw32XorU(MemArg),
+    0x41 => I32AtomicRmwXchg(MemArg),
+    0x42 => I64AtomicRmwXchg(MemArg),
+    0x43 => I32AtomicRmw8XchgU(MemArg),
+    0x44 => I32AtomicRmw16XchgU(MemArg),
+    0x45 => I64AtomicRmw8XchgU(MemArg),
+    0x46 => I64AtomicRmw16XchgU(MemArg),
+    0x47 => I64AtomicRmw32XchgU(MemArg),
+    0x48 => I32AtomicRmwCmpxchg(MemArg),
+    0x49 => I64AtomicRmwCmpxchg(MemArg),
+    0x4A => I32AtomicRmw8CmpxchgU(MemArg),
+    0x4B => I32AtomicRmw16CmpxchgU(MemArg),
+    0x4C => I64AtomicRmw8CmpxchgU(MemArg),
+    0x4D => I64AtomicRmw16CmpxchgU(MemArg),
+    0x4E => I64AtomicRmw32CmpxchgU(MemArg),
+});
+
+impl AtomicInstruction {
+    ///the memory immediate of every instruction but `atomic.fence`, with the width in bits of
+    ///the access
+    pub fn memory_access(&self) -> Option<(&MemArg, u32)> {
+        use AtomicInstruction::*;
+        let access = match self {
+            I32AtomicLoad8U(mem_arg)
+            | I64AtomicLoad8U(mem_arg)
+            | I32AtomicStore8(mem_arg)
+            | I64AtomicStore8(mem_arg)
+            | I32AtomicRmw8AddU(mem_arg)
+            | I64AtomicRmw8AddU(mem_arg)
+            | I32AtomicRmw8SubU(mem_arg)
+            | I64AtomicRmw8SubU(mem_arg)
+            | I32AtomicRmw8AndU(mem_arg)
+            | I64AtomicRmw8AndU(mem_arg)
+            | I32AtomicRmw8OrU(mem_arg)
+            | I64AtomicRmw8OrU(mem_arg)
+            | I32AtomicRmw8XorU(mem_arg)
+            | I64AtomicRmw8XorU(mem_arg)
+            | I32AtomicRmw8XchgU(mem_arg)
+            | I64AtomicRmw8XchgU(mem_arg)
+            | I32AtomicRmw8CmpxchgU(mem_arg)
+            | I64AtomicRmw8CmpxchgU(mem_arg) => (mem_arg, 8),
+            I32AtomicLoad16U(mem_arg)
+            | I64AtomicLoad16U(mem_arg)
+            | I32AtomicStore16(mem_arg)
+            | I64AtomicStore16(mem_arg)
+            | I32AtomicRmw16AddU(mem_arg)
+            | I64AtomicRmw16AddU(mem_arg)
+            | I32AtomicRmw16SubU(mem_arg)
+            | I64AtomicRmw16SubU(mem_arg)
+            | I32AtomicRmw16AndU(mem_arg)
+            | I64AtomicRmw16AndU(mem_arg)
+            | I32AtomicRmw16OrU(mem_arg)
+            | I64AtomicRmw16OrU(mem_arg)
+            | I32AtomicRmw16XorU(mem_arg)
+            | I64AtomicRmw16XorU(mem_arg)
+            | I32AtomicRmw16XchgU(mem_arg)
+            | I64AtomicRmw16XchgU(mem_arg)
+            | I32AtomicRmw16CmpxchgU(mem_arg)
+            | I64AtomicRmw16CmpxchgU(mem_arg) => (mem_arg, 16),
+            MemoryAtomicNotify(mem_arg)
+            | MemoryAtomicWait32(mem_arg)
+            | I32AtomicLoad(mem_arg)
+            | I64AtomicLoad32U(mem_arg)
+            | I32AtomicStore(mem_arg)
+            | I64AtomicStore32(mem_arg)
+            | I32AtomicRmwAdd(mem_arg)
+            | I64AtomicRmw32AddU(mem_arg)
+            | I32AtomicRmwSub(mem_arg)
+            | I64AtomicRmw32SubU(mem_arg)
+            | I32AtomicRmwAnd(mem_arg)
+            | I64AtomicRmw32AndU(mem_arg)
+            | I32AtomicRmwOr(mem_arg)
+            | I64AtomicRmw32OrU(mem_arg)
+            | I32AtomicRmwXor(mem_arg)
+            | I64AtomicRmw32XorU(mem_arg)
+            | I32AtomicRmwXchg(mem_arg)
+            | I64AtomicRmw32XchgU(mem_arg)
+            | I32AtomicRmwCmpxchg(mem_arg)
+            | I64AtomicRmw32CmpxchgU(mem_arg) => (mem_arg, 32),
+            MemoryAtomicWait64(mem_arg)
+            | I64AtomicLoad(mem_arg)
+            | I64AtomicStore(mem_arg)
+            | I64AtomicRmwAdd(mem_arg)
+            | I64AtomicRmwSub(mem_arg)
+            | I64AtomicRmwAnd(mem_arg)
+            | I64AtomicRmwOr(mem_arg)
+            | I64AtomicRmwXor(mem_arg)
+            | I64AtomicRmwXchg(mem_arg)
+            | I64AtomicRmwCmpxchg(mem_arg) => (mem_arg, 64),
+            AtomicFence(_) => return None,
+        };
+        Some(access)
+    }
+}
+
+#[test]
+fn atomics() {
+    use crate::{modules::Module, text, validation::validate};
+    use nom::error::VerboseError;
+    let source = r#"
+        (module
+          (memory 1 2 shared)
+          (func (param i32 i64) (result i64)
+            atomic.fence
+            local.get 0
+            local.get 1
+            i64.atomic.rmw32.add_u offset=8
+            local.get 0
+            i32.const 1
+            memory.atomic.notify
+            drop))"#;
+    let module = text::parse(source).unwrap();
+    assert!(validate(&module).is_ok());
+    let printed = text::print(&module);
+    assert!(printed.contains("(memory (;0;) 1 2 shared)"));
+    assert!(printed.contains("i64.atomic.rmw32.add_u offset=8"));
+    assert!(printed.contains("atomic.fence"));
+    let bytes = module.to_bytes();
+    //shared memory with a maximum, then the rmw with its natural alignment
+    assert!(bytes.windows(3).any(|window| window == [0x03, 0x01, 0x02]));
+    assert!(bytes
+        .windows(5)
+        .any(|window| window == [0xFE, 0x24, 0x02, 0x08, 0x20]));
+    let (_, parsed) = Module::parse::<VerboseError<_>>(&bytes).unwrap();
+    assert_eq!(parsed.to_bytes(), bytes);
+
+    let invalid = |source: &str| validate(&text::parse(source).unwrap()).unwrap_err();
+    let unbounded = invalid("(module (memory 1 shared))");
+    assert!(unbounded
+        .to_string()
+        .contains("shared memory must have a maximum"));
+    let misaligned = r#"
+        (module
+          (memory 1 1 shared)
+          (func (result i32) i32.const 0 i32.atomic.load align=2))"#;
+    assert!(invalid(misaligned)
+        .to_string()
+        .contains("natural alignment"));
+}
@@ -1,14 +1,15 @@
 use super::Instructions;
 use crate::{
     instructions::Instruction,
-    modules::indices::{FuncIdx, LabelIdx, TableIdx, TypeIdx},
+    modules::indices::{FuncIdx, LabelIdx, TableIdx, TagIdx, TypeIdx},
     types::ValueType,
 };
 use nom::{
-    branch::alt, bytes::complete::tag, character::complete::one_of, multi::many_till,
-    sequence::tuple, Parser,
+    branch::alt, bytes::complete::tag, character::complete::one_of, combinator::map,
+    multi::many_till, sequence::tuple, Parser,
 };
-use wasm_core::values::Parse;
+use wasm_core::values::{write_leb128_i64, Encode, Parse};
+use wasm_derive::{Encode, Parse};
 
 #[derive(Debug)]
 pub enum BlockType {
@@ -17,25 +18,49 @@ pub enum BlockType {
     TypeIdx(TypeIdx),
 }
 
-impl Parse for BlockType {
-    fn parse<'a, E>(i: &'a [u8]) -> nom::IResult<&[u8], Self, E>
+impl<'a> Parse<'a> for BlockType {
+    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
     where
         E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
     {
         let empty = tag([0x40]).map(|_| Self::Empty);
-        let val_type = ValueType::parse.map(|val_type| Self::ValType(val_type));
-        let type_index = TypeIdx::parse.map(|type_index| Self::TypeIdx(type_index));
+        let val_type = ValueType::parse.map(Self::ValType);
+        let type_index = TypeIdx::parse.map(Self::TypeIdx);
         alt((empty, val_type, type_index))(i)
     }
 }
 
+impl Encode for BlockType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            BlockType::Empty => 0x40u8.encode(out),
+            BlockType::ValType(val_type) => val_type.encode(out),
+            //a signed 33 bit integer, so indices can't be mistaken for the bytes above
+            BlockType::TypeIdx(type_index) => write_leb128_i64(out, *type_index as i64),
+        }
+    }
+}
+
+///a handler of `try_table`, branching to its label with what it catches
+#[derive(Parse, Encode, Debug)]
+pub enum Catch {
+    ///exceptions of the tag, with their values
+    Catch(TagIdx, LabelIdx),
+    ///exceptions of the tag, with their values and an `exnref`
+    CatchRef(TagIdx, LabelIdx),
+    CatchAll(LabelIdx),
+    ///any exception, as an `exnref`
+    CatchAllRef(LabelIdx),
+}
+
 #[derive(Debug)]
 pub enum ControlInstruction {
     Unreachable,
     Nop,
     Block(BlockType, Instructions),
     Loop(BlockType, Instructions),
-    IfElse(BlockType, Instructions, Instructions),
+    ///the else branch is `None` without an `else`, an empty one is kept for re-encoding
+    IfElse(BlockType, Instructions, Option<Instructions>),
     Br(LabelIdx),
     BrIf(LabelIdx),
     BrTable {
@@ -45,15 +70,33 @@ pub enum ControlInstruction {
     Return,
     Call(FuncIdx),
     CallIndirect(FuncIdx, TableIdx),
+    Throw(TagIdx),
+    ThrowRef,
+    TryTable(BlockType, Vec<Catch>, Instructions),
+    ///the `try` of the legacy exception handling, with its `catch` and `catch_all` handlers
+    Try {
+        block: BlockType,
+        body: Instructions,
+        catches: Vec<(TagIdx, Instructions)>,
+        catch_all: Option<Instructions>,
+    },
+    ///a legacy `try` handing its exceptions over to the handlers around the label
+    Delegate(BlockType, Instructions, LabelIdx),
+    ///throws again the exception caught by the legacy handler of the label
+    Rethrow(LabelIdx),
 }
 const END: u8 = 0x0B;
-impl Parse for ControlInstruction {
-    fn parse<'a, E>(i: &'a [u8]) -> nom::IResult<&[u8], Self, E>
+const CATCH: u8 = 0x07;
+const DELEGATE: u8 = 0x18;
+const CATCH_ALL: u8 = 0x19;
+impl<'a> Parse<'a> for ControlInstruction {
+    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
     where
         E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
     {
         let (i, opcode) = one_of([
-            0x00, 0x01, 0x02, 0x03, 0x04, 0x0C, 0x0D, 0x0E, 0x0F, 0x10, 0x11,
+            0x00, 0x01, 0x02, 0x03, 0x04, 0x06, 0x08, 0x09, 0x0A, 0x0C, 0x0D, 0x0E, 0x0F, 0x10,
+            0x11, 0x1F,
         ])(i)?;
         let instruction = match opcode as u8 {
             0x00 => (i, Self::Unreachable),
@@ -73,18 +116,53 @@ impl Parse for ControlInstruction {
                 let (i, (if_branch, finalizer)) =
                     many_till(Instruction::parse, one_of([END, 0x05]))(i)?;
                 let ins = match finalizer as u8 {
-                    END => (
-                        i,
-                        Self::IfElse(block, if_branch.into(), Instructions::empty()),
-                    ),
+                    END => (i, Self::IfElse(block, if_branch.into(), None)),
                     0x05 => {
                         let (i, else_branch) = Instructions::parse(i)?;
-                        (i, Self::IfElse(block, if_branch.into(), else_branch))
+                        (i, Self::IfElse(block, if_branch.into(), Some(else_branch)))
                     }
                     _ => unreachable!(),
                 };
                 ins
             }
+            0x06 => {
+                let (i, block) = BlockType::parse(i)?;
+                let handler_end = one_of([END, CATCH, DELEGATE, CATCH_ALL]);
+                let (mut i, (body, mut finalizer)) = many_till(Instruction::parse, handler_end)(i)?;
+                if finalizer as u8 == DELEGATE {
+                    let (i, label) = LabelIdx::parse(i)?;
+                    return Ok((i, Self::Delegate(block, body.into(), label)));
+                }
+                let mut catches = vec![];
+                while finalizer as u8 == CATCH {
+                    let (rest, tag) = TagIdx::parse(i)?;
+                    let handler_end = one_of([END, CATCH, CATCH_ALL]);
+                    let (rest, (handler, next)) = many_till(Instruction::parse, handler_end)(rest)?;
+                    catches.push((tag, handler.into()));
+                    (i, finalizer) = (rest, next);
+                }
+                let (i, catch_all) = match finalizer as u8 {
+                    CATCH_ALL => map(Instructions::parse, Some)(i)?,
+                    _ => (i, None),
+                };
+                let body = body.into();
+                let instruction = Self::Try {
+                    block,
+                    body,
+                    catches,
+                    catch_all,
+                };
+                (i, instruction)
+            }
+            0x08 => {
+                let (i, tag) = TagIdx::parse(i)?;
+                (i, Self::Throw(tag))
+            }
+            0x09 => {
+                let (i, label) = LabelIdx::parse(i)?;
+                (i, Self::Rethrow(label))
+            }
+            0x0A => (i, Self::ThrowRef),
             0x0C => {
                 let (i, label) = LabelIdx::parse(i)?;
                 (i, Self::Br(label))
@@ -108,8 +186,108 @@ impl Parse for ControlInstruction {
                 let (i, call) = tuple((TypeIdx::parse, TableIdx::parse))(i)?;
                 (i, Self::CallIndirect(call.0, call.1))
             }
+            0x1F => {
+                let catches = <Vec<Catch>>::parse;
+                let (i, (block, catches, body)) =
+                    tuple((BlockType::parse, catches, Instructions::parse))(i)?;
+                (i, Self::TryTable(block, catches, body))
+            }
             _ => unreachable!(),
         };
         Ok(instruction)
     }
 }
+impl Encode for ControlInstruction {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::Unreachable => 0x00u8.encode(out),
+            Self::Nop => 0x01u8.encode(out),
+            Self::Block(block, ins) => {
+                0x02u8.encode(out);
+                block.encode(out);
+                ins.encode(out);
+            }
+            Self::Loop(block, ins) => {
+                0x03u8.encode(out);
+                block.encode(out);
+                ins.encode(out);
+            }
+            Self::IfElse(block, if_branch, else_branch) => {
+                0x04u8.encode(out);
+                block.encode(out);
+                match else_branch {
+                    None => if_branch.encode(out),
+                    Some(else_branch) => {
+                        if_branch.encode_with_finalizer(out, 0x05);
+                        else_branch.encode(out);
+                    }
+                }
+            }
+            Self::Br(label) => {
+                0x0Cu8.encode(out);
+                label.encode(out);
+            }
+            Self::BrIf(label) => {
+                0x0Du8.encode(out);
+                label.encode(out);
+            }
+            Self::BrTable { table, default } => {
+                0x0Eu8.encode(out);
+                table.encode(out);
+                default.encode(out);
+            }
+            Self::Return => 0x0Fu8.encode(out),
+            Self::Call(function) => {
+                0x10u8.encode(out);
+                function.encode(out);
+            }
+            Self::CallIndirect(type_index, table) => {
+                0x11u8.encode(out);
+                type_index.encode(out);
+                table.encode(out);
+            }
+            Self::Throw(tag) => {
+                0x08u8.encode(out);
+                tag.encode(out);
+            }
+            Self::ThrowRef => 0x0Au8.encode(out),
+            Self::TryTable(block, catches, body) => {
+                0x1Fu8.encode(out);
+                block.encode(out);
+                catches.encode(out);
+                body.encode(out);
+            }
+            Self::Try {
+                block,
+                body,
+                catches,
+                catch_all,
+            } => {
+                0x06u8.encode(out);
+                block.encode(out);
+                //each sequence ends with the opcode of the handler after it
+                let mut sequence = body;
+                for (tag, handler) in catches {
+                    sequence.encode_with_finalizer(out, CATCH);
+                    tag.encode(out);
+                    sequence = handler;
+                }
+                if let Some(handler) = catch_all {
+                    sequence.encode_with_finalizer(out, CATCH_ALL);
+                    sequence = handler;
+                }
+                sequence.encode(out);
+            }
+            Self::Delegate(block, body, label) => {
+                0x06u8.encode(out);
+                block.encode(out);
+                body.encode_with_finalizer(out, DELEGATE);
+                label.encode(out);
+            }
+            Self::Rethrow(label) => {
+                0x09u8.encode(out);
+                label.encode(out);
+            }
+        }
+    }
+}
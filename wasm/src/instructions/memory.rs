@@ -3,18 +3,59 @@ use nom::{
     combinator::{map, verify},
     sequence::tuple,
 };
-use wasm_core::values::Parse;
-use wasm_derive::Parse;
+use wasm_core::values::{Encode, Parse};
 
-use crate::{modules::indices::DataIdx, Prefix, Suffix};
+use crate::{
+    modules::indices::{DataIdx, MemIdx},
+    Prefix,
+};
 
-#[derive(Parse, Debug)]
+#[derive(Debug, Default)]
 pub struct MemArg {
     pub align: u32,
-    pub offset: u32,
+    ///a `u64` for 64 bit memories, from the memory64 proposal
+    pub offset: u64,
+    ///encoded only when it isn't 0 or `explicit_memory` is set, flagged by bit 6 of the
+    ///alignment
+    pub memory: MemIdx,
+    ///the index was encoded even though it is 0, kept so encoding reproduces the input
+    pub explicit_memory: bool,
+}
+impl<'a> Parse<'a> for MemArg {
+    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
+    where
+        E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
+    {
+        let (i, flags) = u32::parse(i)?;
+        let explicit_memory = flags & MEMORY_FLAG != 0;
+        let (i, memory) = if explicit_memory {
+            MemIdx::parse(i)?
+        } else {
+            (i, 0)
+        };
+        let (i, offset) = u64::parse(i)?;
+        let mem_arg = MemArg {
+            align: flags & !MEMORY_FLAG,
+            offset,
+            memory,
+            explicit_memory,
+        };
+        Ok((i, mem_arg))
+    }
+}
+impl Encode for MemArg {
+    fn encode(&self, out: &mut Vec<u8>) {
+        if self.memory == 0 && !self.explicit_memory {
+            self.align.encode(out);
+        } else {
+            (self.align | MEMORY_FLAG).encode(out);
+            self.memory.encode(out);
+        }
+        self.offset.encode(out);
+    }
 }
+const MEMORY_FLAG: u32 = 1 << 6;
 
-type Zero = Suffix<0x00>;
 #[derive(Debug)]
 pub enum MemoryInstruction {
     I32Load(MemArg),
@@ -40,16 +81,17 @@ pub enum MemoryInstruction {
     I64Store8(MemArg),
     I64Store16(MemArg),
     I64Store32(MemArg),
-    Size(Zero),
-    Grow(Zero),
-    Init(Prefix<8>, DataIdx, Zero),
+    Size(MemIdx),
+    Grow(MemIdx),
+    Init(Prefix<8>, DataIdx, MemIdx),
     Drop(Prefix<9>, DataIdx),
-    Copy(Prefix<10>, Zero, Zero),
-    Fill(Prefix<11>, Zero),
+    ///the destination memory, then the source one
+    Copy(Prefix<10>, MemIdx, MemIdx),
+    Fill(Prefix<11>, MemIdx),
 }
 
-impl Parse for MemoryInstruction {
-    fn parse<'a, E>(i: &'a [u8]) -> nom::IResult<&[u8], Self, E>
+impl<'a> Parse<'a> for MemoryInstruction {
+    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
     where
         E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
     {
@@ -60,12 +102,12 @@ impl Parse for MemoryInstruction {
                 (i, simple(op, mem_arg))
             }
             0x3F => {
-                let (i, prefix) = Zero::parse(i)?;
-                (i, Self::Size(prefix))
+                let (i, memory) = MemIdx::parse(i)?;
+                (i, Self::Size(memory))
             }
             0x40 => {
-                let (i, prefix) = Zero::parse(i)?;
-                (i, Self::Grow(prefix))
+                let (i, memory) = MemIdx::parse(i)?;
+                (i, Self::Grow(memory))
             }
             0xFC => prefixed(i)?,
             _ => unreachable!(),
@@ -73,17 +115,17 @@ impl Parse for MemoryInstruction {
         Ok(op)
     }
 }
-fn prefixed<'a, E>(i: &'a [u8]) -> nom::IResult<&[u8], MemoryInstruction, E>
+fn prefixed<'a, E>(i: &'a [u8]) -> nom::IResult<&'a [u8], MemoryInstruction, E>
 where
     E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
 {
-    let init = tuple((<Prefix<8>>::parse, DataIdx::parse, Zero::parse));
+    let init = tuple((<Prefix<8>>::parse, DataIdx::parse, MemIdx::parse));
     let init = map(init, |data| MemoryInstruction::Init(data.0, data.1, data.2));
     let drop = tuple((<Prefix<9>>::parse, DataIdx::parse));
     let drop = map(drop, |data| MemoryInstruction::Drop(data.0, data.1));
-    let copy = tuple((<Prefix<10>>::parse, Zero::parse, Zero::parse));
+    let copy = tuple((<Prefix<10>>::parse, MemIdx::parse, MemIdx::parse));
     let copy = map(copy, |data| MemoryInstruction::Copy(data.0, data.1, data.2));
-    let fill = tuple((<Prefix<11>>::parse, Zero::parse));
+    let fill = tuple((<Prefix<11>>::parse, MemIdx::parse));
     let fill = map(fill, |data| MemoryInstruction::Fill(data.0, data.1));
     alt((init, drop, copy, fill))(i)
 }
@@ -115,3 +157,113 @@ fn simple(op: u8, mem_arg: MemArg) -> MemoryInstruction {
         _ => unreachable!(),
     }
 }
+
+impl Encode for MemoryInstruction {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            MemoryInstruction::Size(memory) => {
+                0x3Fu8.encode(out);
+                memory.encode(out);
+            }
+            MemoryInstruction::Grow(memory) => {
+                0x40u8.encode(out);
+                memory.encode(out);
+            }
+            MemoryInstruction::Init(prefix, data, memory) => {
+                0xFCu8.encode(out);
+                prefix.encode(out);
+                data.encode(out);
+                memory.encode(out);
+            }
+            MemoryInstruction::Drop(prefix, data) => {
+                0xFCu8.encode(out);
+                prefix.encode(out);
+                data.encode(out);
+            }
+            MemoryInstruction::Copy(prefix, destination, source) => {
+                0xFCu8.encode(out);
+                prefix.encode(out);
+                destination.encode(out);
+                source.encode(out);
+            }
+            MemoryInstruction::Fill(prefix, memory) => {
+                0xFCu8.encode(out);
+                prefix.encode(out);
+                memory.encode(out);
+            }
+            simple => {
+                let (op, mem_arg) = simple_opcode(simple);
+                op.encode(out);
+                mem_arg.encode(out);
+            }
+        }
+    }
+}
+fn simple_opcode(instruction: &MemoryInstruction) -> (u8, &MemArg) {
+    match instruction {
+        MemoryInstruction::I32Load(mem_arg) => (0x28, mem_arg),
+        MemoryInstruction::I64Load(mem_arg) => (0x29, mem_arg),
+        MemoryInstruction::F32Load(mem_arg) => (0x2A, mem_arg),
+        MemoryInstruction::F64Load(mem_arg) => (0x2B, mem_arg),
+        MemoryInstruction::I32Load8S(mem_arg) => (0x2C, mem_arg),
+        MemoryInstruction::I32Load8U(mem_arg) => (0x2D, mem_arg),
+        MemoryInstruction::I32Load16S(mem_arg) => (0x2E, mem_arg),
+        MemoryInstruction::I32Load16U(mem_arg) => (0x2F, mem_arg),
+        MemoryInstruction::I64Load8S(mem_arg) => (0x30, mem_arg),
+        MemoryInstruction::I64Load8U(mem_arg) => (0x31, mem_arg),
+        MemoryInstruction::I64Load16S(mem_arg) => (0x32, mem_arg),
+        MemoryInstruction::I64Load16U(mem_arg) => (0x33, mem_arg),
+        MemoryInstruction::I64Load32S(mem_arg) => (0x34, mem_arg),
+        MemoryInstruction::I64Load32U(mem_arg) => (0x35, mem_arg),
+        MemoryInstruction::I32Store(mem_arg) => (0x36, mem_arg),
+        MemoryInstruction::I64Store(mem_arg) => (0x37, mem_arg),
+        MemoryInstruction::F32Store(mem_arg) => (0x38, mem_arg),
+        MemoryInstruction::F64Store(mem_arg) => (0x39, mem_arg),
+        MemoryInstruction::I32Store8(mem_arg) => (0x3A, mem_arg),
+        MemoryInstruction::I32Store16(mem_arg) => (0x3B, mem_arg),
+        MemoryInstruction::I64Store8(mem_arg) => (0x3C, mem_arg),
+        MemoryInstruction::I64Store16(mem_arg) => (0x3D, mem_arg),
+        MemoryInstruction::I64Store32(mem_arg) => (0x3E, mem_arg),
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn memory_indices() {
+    use crate::{modules::Module, text};
+    //i32.load with memory 1, align 2 and offset 4
+    let load = [0x28, 0x42, 0x01, 0x04];
+    let (_, instruction) = MemoryInstruction::parse_simple(&load).unwrap();
+    match &instruction {
+        MemoryInstruction::I32Load(mem_arg) => {
+            assert_eq!((mem_arg.align, mem_arg.offset, mem_arg.memory), (2, 4, 1))
+        }
+        other => panic!("expected i32.load, found {:?}", other),
+    }
+    assert_eq!(instruction.to_bytes(), load);
+    //the same with memory 0 spelled out
+    let explicit = [0x28, 0x42, 0x00, 0x04];
+    let (_, instruction) = MemoryInstruction::parse_simple(&explicit).unwrap();
+    assert_eq!(instruction.to_bytes(), explicit);
+    let copy = [0xFC, 0x0A, 0x01, 0x00];
+    let (_, instruction) = MemoryInstruction::parse_simple(&copy).unwrap();
+    assert!(matches!(instruction, MemoryInstruction::Copy(_, 1, 0)));
+    assert_eq!(instruction.to_bytes(), copy);
+
+    let source = r#"
+        (memory 1)
+        (memory $b 1)
+        (func (result i32)
+          i32.const 0
+          i32.const 0
+          i32.const 1
+          memory.copy 0 $b
+          memory.size $b
+          i32.load $b offset=4)"#;
+    let module = Module::from_wat(source).unwrap();
+    crate::validation::validate(&module).unwrap();
+    let printed = text::print(&module);
+    assert!(printed.contains("memory.copy 0 1"));
+    assert!(printed.contains("memory.size 1"));
+    assert!(printed.contains("i32.load 1 offset=4"));
+}
@@ -1,8 +1,9 @@
+use crate::Suffix;
 use nom::combinator::verify;
-use wasm_core::values::Parse;
-use wasm_derive::Parse;
+use wasm_core::values::{Encode, Parse};
+use wasm_derive::{Encode, Parse};
 
-#[derive(Parse, Debug)]
+#[derive(Parse, Encode, Debug)]
 pub enum NumericInstruction {
     //const
     #[starting = 0x41]
@@ -15,7 +16,7 @@ pub enum NumericInstruction {
     I32Eq,
     I32Ne,
     I32LtS,
-    I32NeU,
+    I32LtU,
     I32GtS,
     I32GtU,
     I32LeS,
@@ -27,7 +28,7 @@ pub enum NumericInstruction {
     I64Eq,
     I64Ne,
     I64LtS,
-    I64NeU,
+    I64LtU,
     I64GtS,
     I64GtU,
     I64LeS,
@@ -60,6 +61,7 @@ pub enum NumericInstruction {
     I32RemS,
     I32RemU,
     I32And,
+    I32Or,
     I32Xor,
     I32Shl,
     I32ShrS,
@@ -78,6 +80,7 @@ pub enum NumericInstruction {
     I64RemS,
     I64RemU,
     I64And,
+    I64Or,
     I64Xor,
     I64Shl,
     I64ShrS,
@@ -164,11 +167,12 @@ pub enum SaturatingTruncationInstruction {
     I64TruncSatF64S,
     I64TruncSatF64U,
 }
-impl Parse for SaturatingTruncationInstruction {
-    fn parse<'a, E>(i: &'a [u8]) -> nom::IResult<&[u8], Self, E>
+impl<'a> Parse<'a> for SaturatingTruncationInstruction {
+    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
     where
         E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
     {
+        let (i, _) = <Suffix<0xFC>>::parse(i)?;
         let (i, prefix) = verify(u32::parse, |prefix| *prefix <= 7)(i)?;
         let ins = match prefix {
             0 => Self::I32TruncSatF32S,
@@ -184,3 +188,19 @@ impl Parse for SaturatingTruncationInstruction {
         Ok((i, ins))
     }
 }
+impl Encode for SaturatingTruncationInstruction {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let prefix: u32 = match self {
+            Self::I32TruncSatF32S => 0,
+            Self::I32TruncSatF32U => 1,
+            Self::I32TruncSatF64S => 2,
+            Self::I32TruncSatF64U => 3,
+            Self::I64TruncSatF32S => 4,
+            Self::I64TruncSatF32U => 5,
+            Self::I64TruncSatF64S => 6,
+            Self::I64TruncSatF64U => 7,
+        };
+        0xFCu8.encode(out);
+        prefix.encode(out);
+    }
+}
@@ -0,0 +1,466 @@
+use super::{
+    control::{BlockType, Catch, ControlInstruction},
+    Expression, Instruction, Instructions,
+};
+use crate::error::Error;
+use crate::modules::indices::{LabelIdx, TagIdx};
+use nom::{combinator::map, error::VerboseError, sequence::tuple};
+use std::fmt::{self, Display};
+use wasm_core::values::{in_context, Encode, Parse};
+
+///an instruction of a flat expression, where blocks are followed by the operators they
+///contain and an explicit `end`, or `delegate` for a legacy `try`
+#[derive(Debug)]
+pub enum Operator {
+    Block(BlockType),
+    Loop(BlockType),
+    If(BlockType),
+    Else,
+    TryTable(BlockType, Vec<Catch>),
+    Try(BlockType),
+    Catch(TagIdx),
+    CatchAll,
+    Delegate(LabelIdx),
+    End,
+    ///any instruction without nested ones
+    Instruction(Instruction),
+}
+impl<'a> Parse<'a> for Operator {
+    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
+    where
+        E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
+    {
+        let (rest, opcode) = u8::parse(i)?;
+        let parsed = match opcode {
+            0x02 => map(BlockType::parse, Self::Block)(rest),
+            0x03 => map(BlockType::parse, Self::Loop)(rest),
+            0x04 => map(BlockType::parse, Self::If)(rest),
+            0x05 => Ok((rest, Self::Else)),
+            0x06 => map(BlockType::parse, Self::Try)(rest),
+            0x07 => map(TagIdx::parse, Self::Catch)(rest),
+            0x18 => map(LabelIdx::parse, Self::Delegate)(rest),
+            0x19 => Ok((rest, Self::CatchAll)),
+            0x1F => {
+                let catches = <Vec<Catch>>::parse;
+                map(tuple((BlockType::parse, catches)), |(block, catches)| {
+                    Self::TryTable(block, catches)
+                })(rest)
+            }
+            0x0B => Ok((rest, Self::End)),
+            _ => map(Instruction::parse, Self::Instruction)(i),
+        };
+        in_context(i, "Operator", parsed)
+    }
+}
+impl Encode for Operator {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::Block(block) => {
+                0x02u8.encode(out);
+                block.encode(out);
+            }
+            Self::Loop(block) => {
+                0x03u8.encode(out);
+                block.encode(out);
+            }
+            Self::If(block) => {
+                0x04u8.encode(out);
+                block.encode(out);
+            }
+            Self::Else => 0x05u8.encode(out),
+            Self::TryTable(block, catches) => {
+                0x1Fu8.encode(out);
+                block.encode(out);
+                catches.encode(out);
+            }
+            Self::Try(block) => {
+                0x06u8.encode(out);
+                block.encode(out);
+            }
+            Self::Catch(tag) => {
+                0x07u8.encode(out);
+                tag.encode(out);
+            }
+            Self::CatchAll => 0x19u8.encode(out),
+            Self::Delegate(label) => {
+                0x18u8.encode(out);
+                label.encode(out);
+            }
+            Self::End => 0x0Bu8.encode(out),
+            Self::Instruction(instruction) => instruction.encode(out),
+        }
+    }
+}
+
+///reads the operators of an expression one at a time, up to the `end` closing it, without
+///building the nested instructions
+#[derive(Debug, Clone)]
+pub struct OperatorReader<'a> {
+    input: &'a [u8],
+    rest: &'a [u8],
+    ///offset of `input` in the module
+    base: usize,
+    ///blocks opened and not closed yet
+    depth: u32,
+    done: bool,
+}
+impl<'a> OperatorReader<'a> {
+    ///reads the expression at the start of `bytes`, with offsets in `bytes`
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self::with_offset(bytes, 0)
+    }
+    ///with offsets moved by `base`, the offset of `bytes` in the module
+    pub fn with_offset(bytes: &'a [u8], base: usize) -> Self {
+        Self {
+            input: bytes,
+            rest: bytes,
+            base,
+            depth: 0,
+            done: false,
+        }
+    }
+    ///the bytes after the operators read so far
+    pub fn rest(&self) -> &'a [u8] {
+        self.rest
+    }
+    ///the offset of the next operator
+    pub fn offset(&self) -> usize {
+        self.base + self.input.len() - self.rest.len()
+    }
+}
+impl<'a> Iterator for OperatorReader<'a> {
+    type Item = Result<(usize, Operator), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let offset = self.offset();
+        let (rest, operator) = match Operator::parse::<VerboseError<_>>(self.rest) {
+            Ok(parsed) => parsed,
+            Err(error) => {
+                self.done = true;
+                let mut error = Error::from_nom(self.input, error);
+                error.offset += self.base;
+                return Some(Err(error));
+            }
+        };
+        match operator {
+            Operator::Block(_)
+            | Operator::Loop(_)
+            | Operator::If(_)
+            | Operator::TryTable(..)
+            | Operator::Try(_) => self.depth += 1,
+            Operator::End | Operator::Delegate(_) if self.depth == 0 => self.done = true,
+            Operator::End | Operator::Delegate(_) => self.depth -= 1,
+            _ => {}
+        }
+        self.rest = rest;
+        Some(Ok((offset, operator)))
+    }
+}
+
+///why flat operators don't fold into an [Expression], with the index of the operator at fault
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FoldError {
+    ///an `else` outside of an `if`, or a second one in the same `if`
+    UnexpectedElse(usize),
+    ///a `catch`, `catch_all` or `delegate` outside of a legacy `try`, or after its
+    ///`catch_all`. `delegate` also can't follow handlers
+    UnexpectedHandler(usize),
+    ///operators after the `end` of the expression
+    TrailingOperators(usize),
+    ///the operators ended with blocks, or the expression itself, still open
+    MissingEnd,
+}
+impl Display for FoldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FoldError::UnexpectedElse(index) => write!(f, "operator {}: unexpected else", index),
+            FoldError::UnexpectedHandler(index) => {
+                write!(f, "operator {}: unexpected exception handler", index)
+            }
+            FoldError::TrailingOperators(index) => {
+                write!(f, "operator {}: after the end of the expression", index)
+            }
+            FoldError::MissingEnd => write!(f, "missing end"),
+        }
+    }
+}
+impl std::error::Error for FoldError {}
+
+impl Expression {
+    ///the flat operators of the expression, ending with its `end`
+    pub fn into_operators(self) -> Vec<Operator> {
+        let mut operators = vec![];
+        flatten(self.0, &mut operators);
+        operators.push(Operator::End);
+        operators
+    }
+    ///folds flat operators, ending with the `end` of the expression, into nested instructions
+    pub fn from_operators(
+        operators: impl IntoIterator<Item = Operator>,
+    ) -> Result<Self, FoldError> {
+        //the branches of the blocks still open, the expression first. a branch starts with
+        //the operator opening it, e.g. `if` then `else`, or `try` then `catch`
+        let mut open: Vec<Vec<(Option<Operator>, Vec<Instruction>)>> = vec![vec![(None, vec![])]];
+        let mut operators = operators.into_iter().enumerate();
+        for (index, operator) in &mut operators {
+            let branches = open.last_mut().unwrap();
+            match operator {
+                Operator::Block(_)
+                | Operator::Loop(_)
+                | Operator::If(_)
+                | Operator::TryTable(..)
+                | Operator::Try(_) => open.push(vec![(Some(operator), vec![])]),
+                Operator::Else => match &branches[..] {
+                    [(Some(Operator::If(_)), _)] => branches.push((Some(operator), vec![])),
+                    _ => return Err(FoldError::UnexpectedElse(index)),
+                },
+                Operator::Catch(_) | Operator::CatchAll => match &branches[..] {
+                    [(Some(Operator::Try(_)), _), ..]
+                        if !matches!(branches.last(), Some((Some(Operator::CatchAll), _))) =>
+                    {
+                        branches.push((Some(operator), vec![]))
+                    }
+                    _ => return Err(FoldError::UnexpectedHandler(index)),
+                },
+                Operator::End | Operator::Delegate(_) => {
+                    let mut branches = open.pop().unwrap().into_iter();
+                    let handlers = branches.len() - 1;
+                    let (block, instructions) = branches.next().unwrap();
+                    let instructions = Instructions(instructions);
+                    let control = match (block, operator) {
+                        (Some(Operator::Try(block)), Operator::Delegate(label))
+                            if handlers == 0 =>
+                        {
+                            ControlInstruction::Delegate(block, instructions, label)
+                        }
+                        (_, Operator::Delegate(_)) => {
+                            return Err(FoldError::UnexpectedHandler(index))
+                        }
+                        (Some(Operator::Block(block)), _) => {
+                            ControlInstruction::Block(block, instructions)
+                        }
+                        (Some(Operator::Loop(block)), _) => {
+                            ControlInstruction::Loop(block, instructions)
+                        }
+                        (Some(Operator::If(block)), _) => {
+                            let else_branch =
+                                branches.next().map(|(_, branch)| Instructions(branch));
+                            ControlInstruction::IfElse(block, instructions, else_branch)
+                        }
+                        (Some(Operator::TryTable(block, catches)), _) => {
+                            ControlInstruction::TryTable(block, catches, instructions)
+                        }
+                        (Some(Operator::Try(block)), _) => {
+                            let mut catches = vec![];
+                            let mut catch_all = None;
+                            for (handler, instructions) in branches {
+                                match handler {
+                                    Some(Operator::Catch(tag)) => {
+                                        catches.push((tag, Instructions(instructions)))
+                                    }
+                                    _ => catch_all = Some(Instructions(instructions)),
+                                }
+                            }
+                            ControlInstruction::Try {
+                                block,
+                                body: instructions,
+                                catches,
+                                catch_all,
+                            }
+                        }
+                        _ => {
+                            return match operators.next() {
+                                Some((index, _)) => Err(FoldError::TrailingOperators(index)),
+                                None => Ok(Self(instructions.0)),
+                            }
+                        }
+                    };
+                    let (_, parent) = open.last_mut().unwrap().last_mut().unwrap();
+                    parent.push(Instruction::Control(control));
+                }
+                Operator::Instruction(instruction) => {
+                    branches.last_mut().unwrap().1.push(instruction)
+                }
+            }
+        }
+        Err(FoldError::MissingEnd)
+    }
+}
+///pushes the flat operators of `instructions`
+fn flatten(instructions: Vec<Instruction>, operators: &mut Vec<Operator>) {
+    for instruction in instructions {
+        let control = match instruction {
+            Instruction::Control(control) => control,
+            instruction => {
+                operators.push(Operator::Instruction(instruction));
+                continue;
+            }
+        };
+        match control {
+            ControlInstruction::Block(block, body) => {
+                operators.push(Operator::Block(block));
+                flatten(body.0, operators);
+            }
+            ControlInstruction::Loop(block, body) => {
+                operators.push(Operator::Loop(block));
+                flatten(body.0, operators);
+            }
+            ControlInstruction::IfElse(block, if_branch, else_branch) => {
+                operators.push(Operator::If(block));
+                flatten(if_branch.0, operators);
+                if let Some(else_branch) = else_branch {
+                    operators.push(Operator::Else);
+                    flatten(else_branch.0, operators);
+                }
+            }
+            ControlInstruction::TryTable(block, catches, body) => {
+                operators.push(Operator::TryTable(block, catches));
+                flatten(body.0, operators);
+            }
+            ControlInstruction::Try {
+                block,
+                body,
+                catches,
+                catch_all,
+            } => {
+                operators.push(Operator::Try(block));
+                flatten(body.0, operators);
+                for (tag, handler) in catches {
+                    operators.push(Operator::Catch(tag));
+                    flatten(handler.0, operators);
+                }
+                if let Some(handler) = catch_all {
+                    operators.push(Operator::CatchAll);
+                    flatten(handler.0, operators);
+                }
+            }
+            ControlInstruction::Delegate(block, body, label) => {
+                operators.push(Operator::Try(block));
+                flatten(body.0, operators);
+                operators.push(Operator::Delegate(label));
+                continue;
+            }
+            control => {
+                operators.push(Operator::Instruction(Instruction::Control(control)));
+                continue;
+            }
+        }
+        operators.push(Operator::End);
+    }
+}
+
+#[test]
+fn flat() {
+    use crate::{
+        error::ErrorKind,
+        modules::{Module, Section},
+    };
+    let file = include_bytes!("../if.wasm");
+    let module = Module::from_bytes(file).unwrap();
+    let codes = module.sections.iter().find_map(|section| match section {
+        Section::CodeSection(codes) => Some(&codes.0),
+        _ => None,
+    });
+    for code in codes.unwrap() {
+        let body = code.code.body.to_bytes();
+        let start = code.range.end - body.len();
+        let operators: Vec<_> = OperatorReader::with_offset(&file[start..], start)
+            .map(Result::unwrap)
+            .collect();
+        let bytes: Vec<u8> = operators.iter().flat_map(|(_, op)| op.to_bytes()).collect();
+        assert_eq!(bytes, body);
+        let instructions = operators
+            .iter()
+            .filter_map(|(offset, operator)| match operator {
+                Operator::Else | Operator::End => None,
+                _ => Some(*offset),
+            });
+        let offsets = code.instruction_offsets(file).unwrap();
+        let expected = offsets.iter().map(|(offset, _)| *offset);
+        assert!(instructions.eq(expected));
+    }
+
+    //`block nop end` without the closing `end`
+    let mut reader = OperatorReader::with_offset(&[0x02, 0x40, 0x01, 0x0B], 10);
+    assert_eq!(reader.by_ref().take(3).filter(Result::is_ok).count(), 3);
+    let error = reader.next().unwrap().unwrap_err();
+    assert_eq!((error.kind, error.offset), (ErrorKind::UnexpectedEof, 14));
+    assert!(reader.next().is_none());
+}
+#[test]
+fn fold() {
+    use crate::modules::{Module, Section};
+    use wasm_core::values::Parse;
+    let file = include_bytes!("../if.wasm");
+    let module = Module::from_bytes(file).unwrap();
+    let codes = module.sections.iter().find_map(|section| match section {
+        Section::CodeSection(codes) => Some(&codes.0),
+        _ => None,
+    });
+    for code in codes.unwrap() {
+        let body = code.code.body.to_bytes();
+        let (_, expression) = Expression::parse_simple(&body).unwrap();
+        let operators = expression.into_operators();
+        let read: Vec<_> = OperatorReader::new(&body)
+            .map(|operator| operator.unwrap().1)
+            .collect();
+        let encode = |operators: &[Operator]| -> Vec<u8> {
+            operators.iter().flat_map(Operator::to_bytes).collect()
+        };
+        assert_eq!(encode(&operators), encode(&read));
+        let folded = Expression::from_operators(read).unwrap();
+        assert_eq!(folded.to_bytes(), body);
+    }
+
+    let nop = || Operator::Instruction(Instruction::Control(ControlInstruction::Nop));
+    let block = || Operator::Block(BlockType::Empty);
+    let fold = |operators: Vec<Operator>| Expression::from_operators(operators).unwrap_err();
+    let mut if_else = vec![Operator::If(BlockType::Empty), nop(), Operator::Else, nop()];
+    if_else.extend([Operator::End, Operator::End]);
+    let folded = Expression::from_operators(if_else);
+    assert_eq!(
+        folded.unwrap().to_bytes(),
+        [0x04, 0x40, 0x01, 0x05, 0x01, 0x0B, 0x0B]
+    );
+    assert_eq!(
+        fold(vec![Operator::Else, Operator::End]),
+        FoldError::UnexpectedElse(0)
+    );
+    let block_else = vec![block(), Operator::Else, Operator::End, Operator::End];
+    assert_eq!(fold(block_else), FoldError::UnexpectedElse(1));
+    assert_eq!(fold(vec![block(), Operator::End]), FoldError::MissingEnd);
+    assert_eq!(
+        fold(vec![Operator::End, nop()]),
+        FoldError::TrailingOperators(1)
+    );
+}
+#[test]
+fn exceptions() {
+    let body = [
+        //try_table (catch 0 0) (catch_all_ref 1) throw 0 end
+        0x1F, 0x40, 0x02, 0x00, 0x00, 0x00, 0x03, 0x01, 0x08, 0x00, 0x0B,
+        //try nop catch 0 nop catch_all nop end
+        0x06, 0x40, 0x01, 0x07, 0x00, 0x01, 0x19, 0x01, 0x0B, //try nop delegate 0
+        0x06, 0x40, 0x01, 0x18, 0x00, 0x0B,
+    ];
+    let (_, expression) = Expression::parse_simple(&body).unwrap();
+    assert_eq!(expression.to_bytes(), body);
+    let read: Vec<_> = OperatorReader::new(&body)
+        .map(|operator| operator.unwrap().1)
+        .collect();
+    let encode = |operators: &[Operator]| -> Vec<u8> {
+        operators.iter().flat_map(Operator::to_bytes).collect()
+    };
+    assert_eq!(encode(&expression.into_operators()), encode(&read));
+    assert_eq!(Expression::from_operators(read).unwrap().to_bytes(), body);
+
+    let block = || Operator::Block(BlockType::Empty);
+    let fold = |operators: Vec<Operator>| Expression::from_operators(operators).unwrap_err();
+    let catch_all = vec![block(), Operator::CatchAll, Operator::End, Operator::End];
+    assert_eq!(fold(catch_all), FoldError::UnexpectedHandler(1));
+    let delegate = vec![block(), Operator::Delegate(0), Operator::End];
+    assert_eq!(fold(delegate), FoldError::UnexpectedHandler(1));
+}
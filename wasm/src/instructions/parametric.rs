@@ -1,9 +1,9 @@
-use wasm_core::values::Parse;
-use wasm_derive::Parse;
+use wasm_core::values::{Encode, Parse};
+use wasm_derive::{Encode, Parse};
 
 use crate::types::ValueType;
 
-#[derive(Parse, Debug)]
+#[derive(Parse, Encode, Debug)]
 pub enum ParametricInstruction {
     #[starting = 0x1A]
     Drop,
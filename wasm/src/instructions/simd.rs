@@ -0,0 +1,502 @@
+//!the fixed-width SIMD proposal, prefixed by `0xFD`
+use super::memory::MemArg;
+use nom::{bytes::complete::take, combinator::map};
+use wasm_core::values::{Encode, Parse};
+use wasm_derive::{Encode, Parse};
+
+///the 16 bytes of a `v128.const`, in little endian order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct V128(pub [u8; 16]);
+impl<'a> Parse<'a> for V128 {
+    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
+    where
+        E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
+    {
+        map(take(16_usize), |bytes: &[u8]| {
+            let mut v128 = [0; 16];
+            v128.copy_from_slice(bytes);
+            Self(v128)
+        })(i)
+    }
+}
+impl Encode for V128 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.0);
+    }
+}
+///the lanes of the two operands picked by `i8x16.shuffle`, each below 32
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ShuffleLanes(pub [LaneIdx; 16]);
+impl<'a> Parse<'a> for ShuffleLanes {
+    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
+    where
+        E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
+    {
+        map(V128::parse, |lanes| Self(lanes.0))(i)
+    }
+}
+impl Encode for ShuffleLanes {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.0);
+    }
+}
+pub type LaneIdx = u8;
+///the immediates of the loads and stores of a single lane
+#[derive(Parse, Encode, Debug)]
+pub struct MemLane {
+    pub mem_arg: MemArg,
+    pub lane: LaneIdx,
+}
+
+prefixed!(
+    0xFD,
+    ///the fixed-width SIMD instructions
+    SimdInstruction {
+    0x00 => V128Load(MemArg),
+    0x01 => V128Load8x8S(MemArg),
+    0x02 => V128Load8x8U(MemArg),
+    0x03 => V128Load16x4S(MemArg),
+    0x04 => V128Load16x4U(MemArg),
+    0x05 => V128Load32x2S(MemArg),
+    0x06 => V128Load32x2U(MemArg),
+    0x07 => V128Load8Splat(MemArg),
+    0x08 => V128Load16Splat(MemArg),
+    0x09 => V128Load32Splat(MemArg),
+    0x0A => V128Load64Splat(MemArg),
+    0x0B => V128Store(MemArg),
+    0x0C => V128Const(V128),
+    0x0D => I8x16Shuffle(ShuffleLanes),
+    0x0E => I8x16Swizzle,
+    0x0F => I8x16Splat,
+    0x10 => I16x8Splat,
+    0x11 => I32x4Splat,
+    0x12 => I64x2Splat,
+    0x13 => F32x4Splat,
+    0x14 => F64x2Splat,
+    0x15 => I8x16ExtractLaneS(LaneIdx),
+    0x16 => I8x16ExtractLaneU(LaneIdx),
+    0x17 => I8x16ReplaceLane(LaneIdx),
+    0x18 => I16x8ExtractLaneS(LaneIdx),
+    0x19 => I16x8ExtractLaneU(LaneIdx),
+    0x1A => I16x8ReplaceLane(LaneIdx),
+    0x1B => I32x4ExtractLane(LaneIdx),
+    0x1C => I32x4ReplaceLane(LaneIdx),
+    0x1D => I64x2ExtractLane(LaneIdx),
+    0x1E => I64x2ReplaceLane(LaneIdx),
+    0x1F => F32x4ExtractLane(LaneIdx),
+    0x20 => F32x4ReplaceLane(LaneIdx),
+    0x21 => F64x2ExtractLane(LaneIdx),
+    0x22 => F64x2ReplaceLane(LaneIdx),
+    0x23 => I8x16Eq,
+    0x24 => I8x16Ne,
+    0x25 => I8x16LtS,
+    0x26 => I8x16LtU,
+    0x27 => I8x16GtS,
+    0x28 => I8x16GtU,
+    0x29 => I8x16LeS,
+    0x2A => I8x16LeU,
+    0x2B => I8x16GeS,
+    0x2C => I8x16GeU,
+    0x2D => I16x8Eq,
+    0x2E => I16x8Ne,
+    0x2F => I16x8LtS,
+    0x30 => I16x8LtU,
+    0x31 => I16x8GtS,
+    0x32 => I16x8GtU,
+    0x33 => I16x8LeS,
+    0x34 => I16x8LeU,
+    0x35 => I16x8GeS,
+    0x36 => I16x8GeU,
+    0x37 => I32x4Eq,
+    0x38 => I32x4Ne,
+    0x39 => I32x4LtS,
+    0x3A => I32x4LtU,
+    0x3B => I32x4GtS,
+    0x3C => I32x4GtU,
+    0x3D => I32x4LeS,
+    0x3E => I32x4LeU,
+    0x3F => I32x4GeS,
+    0x40 => I32x4GeU,
+    0x41 => F32x4Eq,
+    0x42 => F32x4Ne,
+    0x43 => F32x4Lt,
+    0x44 => F32x4Gt,
+    0x45 => F32x4Le,
+    0x46 => F32x4Ge,
+    0x47 => F64x2Eq,
+    0x48 => F64x2Ne,
+    0x49 => F64x2Lt,
+    0x4A => F64x2Gt,
+    0x4B => F64x2Le,
+    0x4C => F64x2Ge,
+    0x4D => V128Not,
+    0x4E => V128And,
+    0x4F => V128AndNot,
+    0x50 => V128Or,
+    0x51 => V128Xor,
+    0x52 => V128Bitselect,
+    0x53 => V128AnyTrue,
+    0x54 => V128Load8Lane(MemLane),
+    0x55 => V128Load16Lane(MemLane),
+    0x56 => V128Load32Lane(MemLane),
+    0x57 => V128Load64Lane(MemLane),
+    0x58 => V128Store8Lane(MemLane),
+    0x59 => V128Store16Lane(MemLane),
+    0x5A => V128Store32Lane(MemLane),
+    0x5B => V128Store64Lane(MemLane),
+    0x5C => V128Load32Zero(MemArg),
+    0x5D => V128Load64Zero(MemArg),
+    0x5E => F32x4DemoteF64x2Zero,
+    0x5F => F64x2PromoteLowF32x4,
+    0x60 => I8x16Abs,
+    0x61 => I8x16Neg,
+    0x62 => I8x16Popcnt,
+    0x63 => I8x16AllTrue,
+    0x64 => I8x16Bitmask,
+    0x65 => I8x16NarrowI16x8S,
+    0x66 => I8x16NarrowI16x8U,
+    0x67 => F32x4Ceil,
+    0x68 => F32x4Floor,
+    0x69 => F32x4Trunc,
+    0x6A => F32x4Nearest,
+    0x6B => I8x16Shl,
+    0x6C => I8x16ShrS,
+    0x6D => I8x16ShrU,
+    0x6E => I8x16Add,
+    0x6F => I8x16AddSatS,
+    0x70 => I8x16AddSatU,
+    0x71 => I8x16Sub,
+    0x72 => I8x16SubSatS,
+    0x73 => I8x16SubSatU,
+    0x74 => F64x2Ceil,
+    0x75 => F64x2Floor,
+    0x76 => I8x16MinS,
+    0x77 => I8x16MinU,
+    0x78 => I8x16MaxS,
+    0x79 => I8x16MaxU,
+    0x7A => F64x2Trunc,
+    0x7B => I8x16AvgrU,
+    0x7C => I16x8ExtaddPairwiseI8x16S,
+    0x7D => I16x8ExtaddPairwiseI8x16U,
+    0x7E => I32x4ExtaddPairwiseI16x8S,
+    0x7F => I32x4ExtaddPairwiseI16x8U,
+    0x80 => I16x8Abs,
+    0x81 => I16x8Neg,
+    0x82 => I16x8Q15mulrSatS,
+    0x83 => I16x8AllTrue,
+    0x84 => I16x8Bitmask,
+    0x85 => I16x8NarrowI32x4S,
+    0x86 => I16x8NarrowI32x4U,
+    0x87 => I16x8ExtendLowI8x16S,
+    0x88 => I16x8ExtendHighI8x16S,
+    0x89 => I16x8ExtendLowI8x16U,
+    0x8A => I16x8ExtendHighI8x16U,
+    0x8B => I16x8Shl,
+    0x8C => I16x8ShrS,
+    0x8D => I16x8ShrU,
+    0x8E => I16x8Add,
+    0x8F => I16x8AddSatS,
+    0x90 => I16x8AddSatU,
+    0x91 => I16x8Sub,
+    0x92 => I16x8SubSatS,
+    0x93 => I16x8SubSatU,
+    0x94 => F64x2Nearest,
+    0x95 => I16x8Mul,
+    0x96 => I16x8MinS,
+    0x97 => I16x8MinU,
+    0x98 => I16x8MaxS,
+    0x99 => I16x8MaxU,
+    0x9B => I16x8AvgrU,
+    0x9C => I16x8ExtmulLowI8x16S,
+    0x9D => I16x8ExtmulHighI8x16S,
+    0x9E => I16x8ExtmulLowI8x16U,
+    0x9F => I16x8ExtmulHighI8x16U,
+    0xA0 => I32x4Abs,
+    0xA1 => I32x4Neg,
+    0xA3 => I32x4AllTrue,
+    0xA4 => I32x4Bitmask,
+    0xA7 => I32x4ExtendLowI16x8S,
+    0xA8 => I32x4ExtendHighI16x8S,
+    0xA9 => I32x4ExtendLowI16x8U,
+    0xAA => I32x4ExtendHighI16x8U,
+    0xAB => I32x4Shl,
+    0xAC => I32x4ShrS,
+    0xAD => I32x4ShrU,
+    0xAE => I32x4Add,
+    0xB1 => I32x4Sub,
+    0xB5 => I32x4Mul,
+    0xB6 => I32x4MinS,
+    0xB7 => I32x4MinU,
+    0xB8 => I32x4MaxS,
+    0xB9 => I32x4MaxU,
+    0xBA => I32x4DotI16x8S,
+    0xBC => I32x4ExtmulLowI16x8S,
+    0xBD => I32x4ExtmulHighI16x8S,
+    0xBE => I32x4ExtmulLowI16x8U,
+    0xBF => I32x4ExtmulHighI16x8U,
+    0xC0 => I64x2Abs,
+    0xC1 => I64x2Neg,
+    0xC3 => I64x2AllTrue,
+    0xC4 => I64x2Bitmask,
+    0xC7 => I64x2ExtendLowI32x4S,
+    0xC8 => I64x2ExtendHighI32x4S,
+    0xC9 => I64x2ExtendLowI32x4U,
+    0xCA => I64x2ExtendHighI32x4U,
+    0xCB => I64x2Shl,
+    0xCC => I64x2ShrS,
+    0xCD => I64x2ShrU,
+    0xCE => I64x2Add,
+    0xD1 => I64x2Sub,
+    0xD5 => I64x2Mul,
+    0xD6 => I64x2Eq,
+    0xD7 => I64x2Ne,
+    0xD8 => I64x2LtS,
+    0xD9 => I64x2GtS,
+    0xDA => I64x2LeS,
+    0xDB => I64x2GeS,
+    0xDC => I64x2ExtmulLowI32x4S,
+    0xDD => I64x2ExtmulHighI32x4S,
+    0xDE => I64x2ExtmulLowI32x4U,
+    0xDF => I64x2ExtmulHighI32x4U,
+    0xE0 => F32x4Abs,
+    0xE1 => F32x4Neg,
+    0xE3 => F32x4Sqrt,
+    0xE4 => F32x4Add,
+    0xE5 => F32x4Sub,
+    0xE6 => F32x4Mul,
+    0xE7 => F32x4Div,
+    0xE8 => F32x4Min,
+    0xE9 => F32x4Max,
+    0xEA => F32x4Pmin,
+    0xEB => F32x4Pmax,
+    0xEC => F64x2Abs,
+    0xED => F64x2Neg,
+    0xEF => F64x2Sqrt,
+    0xF0 => F64x2Add,
+    0xF1 => F64x2Sub,
+    0xF2 => F64x2Mul,
+    0xF3 => F64x2Div,
+    0xF4 => F64x2Min,
+    0xF5 => F64x2Max,
+    0xF6 => F64x2Pmin,
+    0xF7 => F64x2Pmax,
+    0xF8 => I32x4TruncSatF32x4S,
+    0xF9 => I32x4TruncSatF32x4U,
+    0xFA => F32x4ConvertI32x4S,
+    0xFB => F32x4ConvertI32x4U,
+    0xFC => I32x4TruncSatF64x2SZero,
+    0xFD => I32x4TruncSatF64x2UZero,
+    0xFE => F64x2ConvertLowI32x4S,
+    0xFF => F64x2ConvertLowI32x4U,
+});
+
+prefixed!(
+    0xFD,
+    ///the relaxed SIMD proposal, whose results may depend on the platform
+    RelaxedSimdInstruction {
+    0x100 => I8x16RelaxedSwizzle,
+    0x101 => I32x4RelaxedTruncF32x4S,
+    0x102 => I32x4RelaxedTruncF32x4U,
+    0x103 => I32x4RelaxedTruncF64x2SZero,
+    0x104 => I32x4RelaxedTruncF64x2UZero,
+    0x105 => F32x4RelaxedMadd,
+    0x106 => F32x4RelaxedNmadd,
+    0x107 => F64x2RelaxedMadd,
+    0x108 => F64x2RelaxedNmadd,
+    0x109 => I8x16RelaxedLaneselect,
+    0x10A => I16x8RelaxedLaneselect,
+    0x10B => I32x4RelaxedLaneselect,
+    0x10C => I64x2RelaxedLaneselect,
+    0x10D => F32x4RelaxedMin,
+    0x10E => F32x4RelaxedMax,
+    0x10F => F64x2RelaxedMin,
+    0x110 => F64x2RelaxedMax,
+    0x111 => I16x8RelaxedQ15mulrS,
+    0x112 => I16x8RelaxedDotI8x16I7x16S,
+    0x113 => I32x4RelaxedDotI8x16I7x16AddS,
+});
+
+impl SimdInstruction {
+    ///the memory immediate of loads and stores, with the width in bits of the access
+    pub fn memory_access(&self) -> Option<(&MemArg, u32)> {
+        use SimdInstruction::*;
+        let access = match self {
+            V128Load(mem_arg) | V128Store(mem_arg) => (mem_arg, 128),
+            V128Load8x8S(mem_arg)
+            | V128Load8x8U(mem_arg)
+            | V128Load16x4S(mem_arg)
+            | V128Load16x4U(mem_arg)
+            | V128Load32x2S(mem_arg)
+            | V128Load32x2U(mem_arg)
+            | V128Load64Splat(mem_arg)
+            | V128Load64Zero(mem_arg) => (mem_arg, 64),
+            V128Load8Splat(mem_arg) => (mem_arg, 8),
+            V128Load16Splat(mem_arg) => (mem_arg, 16),
+            V128Load32Splat(mem_arg) | V128Load32Zero(mem_arg) => (mem_arg, 32),
+            V128Load8Lane(lane) | V128Store8Lane(lane) => (&lane.mem_arg, 8),
+            V128Load16Lane(lane) | V128Store16Lane(lane) => (&lane.mem_arg, 16),
+            V128Load32Lane(lane) | V128Store32Lane(lane) => (&lane.mem_arg, 32),
+            V128Load64Lane(lane) | V128Store64Lane(lane) => (&lane.mem_arg, 64),
+            _ => return None,
+        };
+        Some(access)
+    }
+    ///the lane immediate, with the number of lanes of its shape
+    pub fn lane(&self) -> Option<(LaneIdx, u8)> {
+        use SimdInstruction::*;
+        let lane = match self {
+            I8x16ExtractLaneS(lane) | I8x16ExtractLaneU(lane) | I8x16ReplaceLane(lane) => {
+                (*lane, 16)
+            }
+            I16x8ExtractLaneS(lane) | I16x8ExtractLaneU(lane) | I16x8ReplaceLane(lane) => {
+                (*lane, 8)
+            }
+            I32x4ExtractLane(lane)
+            | I32x4ReplaceLane(lane)
+            | F32x4ExtractLane(lane)
+            | F32x4ReplaceLane(lane) => (*lane, 4),
+            I64x2ExtractLane(lane)
+            | I64x2ReplaceLane(lane)
+            | F64x2ExtractLane(lane)
+            | F64x2ReplaceLane(lane) => (*lane, 2),
+            V128Load8Lane(lane) | V128Store8Lane(lane) => (lane.lane, 16),
+            V128Load16Lane(lane) | V128Store16Lane(lane) => (lane.lane, 8),
+            V128Load32Lane(lane) | V128Store32Lane(lane) => (lane.lane, 4),
+            V128Load64Lane(lane) | V128Store64Lane(lane) => (lane.lane, 2),
+            _ => return None,
+        };
+        Some(lane)
+    }
+}
+
+#[test]
+fn simd() {
+    use super::Instruction;
+    use crate::{error::ErrorKind, modules::Module, validation::validate};
+    use nom::error::VerboseError;
+    let mut v128_const = vec![0xFD, 0x0C];
+    v128_const.extend(1..=16);
+    let mut shuffle = vec![0xFD, 0x0D];
+    shuffle.extend((0..16).rev());
+    let instructions = vec![
+        v128_const,
+        shuffle,
+        vec![0xFD, 0x1B, 0x03],             //i32x4.extract_lane 3
+        vec![0xFD, 0x56, 0x02, 0x04, 0x01], //v128.load32_lane offset=4 1
+        vec![0xFD, 0xAE, 0x01],             //i32x4.add
+    ];
+    for bytes in &instructions {
+        let (rest, instruction) = Instruction::parse_simple(bytes).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(&instruction.to_bytes(), bytes);
+    }
+    let (_, load) = SimdInstruction::parse_simple(&instructions[3]).unwrap();
+    assert_eq!(
+        load.memory_access().map(|(arg, width)| (arg.offset, width)),
+        Some((4, 32))
+    );
+    assert_eq!(load.lane(), Some((1, 4)));
+    let unknown = Instruction::parse::<VerboseError<_>>(&[0xFD, 0x9A, 0x01]);
+    let error = crate::error::Error::from_nom(&[0xFD, 0x9A, 0x01], unknown.unwrap_err());
+    let opcode = ErrorKind::UnknownOpcode {
+        prefix: Some(0xFD),
+        opcode: 0x9A,
+    };
+    assert_eq!(error.kind, opcode);
+    assert_eq!(
+        error.to_string(),
+        "0x0: unknown opcode 0xfd 154 (in Instruction)"
+    );
+
+    let module = |body: &[u8]| {
+        let mut file = vec![
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, //header
+            0x01, 0x07, 0x01, 0x60, 0x02, 0x7B, 0x7B, 0x01,
+            0x7B, //type section: [v128 v128] -> [v128]
+            0x03, 0x02, 0x01, 0x00, //function section
+        ];
+        file.extend([0x0A, body.len() as u8 + 3, 0x01, body.len() as u8 + 1, 0x00]);
+        file.extend(body);
+        file
+    };
+    //local.get 0 local.get 1 i32x4.add end
+    let add = module(&[0x20, 0x00, 0x20, 0x01, 0xFD, 0xAE, 0x01, 0x0B]);
+    let (_, add) = Module::parse::<VerboseError<_>>(&add).unwrap();
+    assert!(validate(&add).is_ok());
+    //local.get 0 i32x4.extract_lane 4 end
+    let lane = module(&[0x20, 0x00, 0xFD, 0x1B, 0x04, 0x0B]);
+    let (_, lane) = Module::parse::<VerboseError<_>>(&lane).unwrap();
+    assert!(validate(&lane).is_err());
+}
+#[test]
+fn simd_text() {
+    use crate::{modules::Module, text, validation::validate};
+    let source = r#"
+        (module
+          (memory 1)
+          (memory $lanes 1)
+          (func (param v128) (result i32)
+            i32.const 0
+            i32.const 0
+            v128.load offset=16
+            v128.load8_lane 15
+            i32.const 0
+            local.get 0
+            v128.load32_lane $lanes offset=4 align=2 3
+            i8x16.shuffle 0 17 2 19 4 21 6 23 8 25 10 27 12 29 14 31
+            v128.const i8x16 -1 255 0 1 2 3 4 5 6 7 8 9 10 11 12 -128
+            i16x8.add
+            v128.const f32x4 1.5 -0 nan inf
+            v128.and
+            f32.const 1
+            f32x4.replace_lane 2
+            i32x4.extract_lane 3))"#;
+    let module = text::parse(source).unwrap();
+    validate(&module).unwrap();
+    let printed = module.to_wat();
+    assert!(printed.contains("v128.load32_lane 1 offset=4 align=2 3"));
+    assert!(printed.contains("v128.load8_lane 15"));
+    assert!(printed.contains("v128.const i32x4 0x0100ffff"));
+    let reparsed = Module::from_wat(&printed).unwrap();
+    assert_eq!(reparsed.to_bytes(), module.to_bytes());
+    assert_eq!(reparsed.to_wat(), printed);
+
+    let error = |source: &str| text::parse(source).unwrap_err().to_string();
+    let lane = error("(module (func (param v128) (result i32) local.get 0 i32x4.extract_lane 4))");
+    assert!(lane.contains("invalid lane index literal 4"));
+    let shape = error("(module (func (result v128) v128.const i8x8 0))");
+    assert!(shape.contains("expected a vector shape"));
+
+    let global = text::parse("(module (global v128 (v128.const i32x4 0 0 0 0)))").unwrap();
+    validate(&global).unwrap();
+    let is_null = "(module (func (param v128) (result i32) local.get 0 ref.is_null))";
+    let invalid = validate(&text::parse(is_null).unwrap()).unwrap_err();
+    assert!(invalid
+        .to_string()
+        .ends_with("expected a reference, found V128"));
+}
+#[test]
+fn relaxed_simd() {
+    use super::Instruction;
+    use crate::{text, validation::validate};
+    use wasm_core::values::Encode;
+    let madd = [0xFD, 0x85, 0x02];
+    let (_, instruction) = Instruction::parse_simple(&madd).unwrap();
+    assert!(matches!(
+        instruction,
+        Instruction::RelaxedSimd(RelaxedSimdInstruction::F32x4RelaxedMadd)
+    ));
+    assert_eq!(instruction.to_bytes(), madd);
+    let source = r#"
+        (module
+          (func (param v128 v128 v128) (result v128)
+            local.get 0
+            local.get 1
+            local.get 2
+            f32x4.relaxed_madd))"#;
+    let module = text::parse(source).unwrap();
+    assert!(validate(&module).is_ok());
+    assert!(text::print(&module).contains("f32x4.relaxed_madd"));
+    let bytes = module.to_bytes();
+    assert!(bytes.windows(3).any(|window| window == madd));
+}
@@ -3,7 +3,7 @@ use nom::{
     combinator::{map, verify},
     sequence::tuple,
 };
-use wasm_core::values::Parse;
+use wasm_core::values::{Encode, Parse};
 
 use crate::modules::indices::{ElemIdx, TableIdx};
 
@@ -18,8 +18,8 @@ pub enum TableInstruction {
     TableSize(TableIdx),
     TableFill(TableIdx),
 }
-impl Parse for TableInstruction {
-    fn parse<'a, E>(i: &'a [u8]) -> nom::IResult<&[u8], Self, E>
+impl<'a> Parse<'a> for TableInstruction {
+    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
     where
         E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
     {
@@ -50,7 +50,7 @@ impl Parse for TableInstruction {
                         let (i, content) = tuple((TableIdx::parse, TableIdx::parse))(i)?;
                         (i, Self::TableCopy(content.0, content.1))
                     }
-                    prefix @ (15 | 16 | 17) => {
+                    prefix @ (15..=17) => {
                         let (i, idx) = TableIdx::parse(i)?;
                         let op = match prefix {
                             15 => Self::TableGrow(idx),
@@ -68,3 +68,49 @@ impl Parse for TableInstruction {
         }
     }
 }
+impl Encode for TableInstruction {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::TableGet(idx) => {
+                0x25u8.encode(out);
+                idx.encode(out);
+            }
+            Self::TableSet(idx) => {
+                0x26u8.encode(out);
+                idx.encode(out);
+            }
+            Self::TableInit(elem, table) => {
+                0xFCu8.encode(out);
+                12u32.encode(out);
+                elem.encode(out);
+                table.encode(out);
+            }
+            Self::TableDrop(elem) => {
+                0xFCu8.encode(out);
+                13u32.encode(out);
+                elem.encode(out);
+            }
+            Self::TableCopy(destination, source) => {
+                0xFCu8.encode(out);
+                14u32.encode(out);
+                destination.encode(out);
+                source.encode(out);
+            }
+            Self::TableGrow(idx) => {
+                0xFCu8.encode(out);
+                15u32.encode(out);
+                idx.encode(out);
+            }
+            Self::TableSize(idx) => {
+                0xFCu8.encode(out);
+                16u32.encode(out);
+                idx.encode(out);
+            }
+            Self::TableFill(idx) => {
+                0xFCu8.encode(out);
+                17u32.encode(out);
+                idx.encode(out);
+            }
+        }
+    }
+}
@@ -1,9 +1,9 @@
-use wasm_core::values::Parse;
-use wasm_derive::Parse;
+use wasm_core::values::{Encode, Parse};
+use wasm_derive::{Encode, Parse};
 
 use crate::modules::indices::{GlobalIdx, LocalIdx};
 
-#[derive(Parse, Debug)]
+#[derive(Parse, Encode, Debug)]
 pub enum VariableInstruction {
     #[starting = 0x20]
     LocalGet(LocalIdx),
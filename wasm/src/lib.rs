@@ -1,37 +1,55 @@
 use nom::combinator::{map, verify};
-use wasm_core::values::Parse;
+use wasm_core::values::{Encode, Parse};
 
+pub mod error;
+#[cfg(test)]
+mod fixtures;
 pub mod instructions;
 pub mod modules;
+pub mod reader;
+pub mod streaming;
+pub mod text;
 pub mod types;
+pub mod validation;
+
+pub use error::{CodeError, Error, ErrorKind};
 
 #[derive(Debug)]
 pub struct Prefix<const P: u32>;
-impl<const P: u32> Parse for Prefix<P> {
-    fn parse<'a, E>(i: &'a [u8]) -> nom::IResult<&[u8], Self, E>
+impl<'a, const P: u32> Parse<'a> for Prefix<P> {
+    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
     where
         E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
     {
         map(verify(u32::parse, |prefix| *prefix == P), |_| Self)(i)
     }
 }
+impl<const P: u32> Encode for Prefix<P> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        P.encode(out)
+    }
+}
 #[derive(Debug)]
 pub struct Suffix<const P: u8>;
 
-impl<const P: u8> Parse for Suffix<P> {
-    fn parse<'a, E>(i: &'a [u8]) -> nom::IResult<&[u8], Self, E>
+impl<'a, const P: u8> Parse<'a> for Suffix<P> {
+    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
     where
         E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
     {
         map(verify(u8::parse, |prefix| *prefix == P), |_| Self)(i)
     }
 }
+impl<const P: u8> Encode for Suffix<P> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        P.encode(out)
+    }
+}
 
 #[test]
 fn test1() {
     use modules::Module;
     let file = include_bytes!("loop.wasm");
-    let file = file.split_last().unwrap().1;
     let module = Module::parse_dbg::<nom_supreme::error::ErrorTree<_>>(file);
     //assert_eq!(module.0.len(), 0);
     println!("{:#?}", module);
@@ -45,3 +63,76 @@ fn test2() {
     assert_eq!(module.0.len(), 0);
     println!("{:#?}", module);
 }
+#[test]
+fn round_trip() {
+    use modules::Module;
+    use nom::error::VerboseError;
+    let loop_file = &include_bytes!("loop.wasm")[..];
+    for file in [loop_file, &include_bytes!("if.wasm")[..]] {
+        let (_, module) = Module::parse::<VerboseError<_>>(file).unwrap();
+        let bytes = module.to_bytes();
+        let (rest, reparsed) = Module::parse::<VerboseError<_>>(&bytes).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(reparsed.to_bytes(), bytes);
+        assert_eq!(bytes, file);
+    }
+}
+#[test]
+fn derived_encoders() {
+    use instructions::{numeric::NumericInstruction, variable::VariableInstruction};
+    use modules::export::ExportDescriptor;
+    assert_eq!(types::NumType::I32.to_bytes(), [0x7F]);
+    assert_eq!(NumericInstruction::I32Or.to_bytes(), [0x72]);
+    assert_eq!(NumericInstruction::I64Const(-1).to_bytes(), [0x42, 0x7F]);
+    assert_eq!(
+        VariableInstruction::GlobalSet(300).to_bytes(),
+        [0x24, 0xAC, 0x02]
+    );
+    assert_eq!(ExportDescriptor::Mem(0).to_bytes(), [0x02, 0x00]);
+    #[derive(wasm_derive::Encode)]
+    struct Unit;
+    assert!(Unit.to_bytes().is_empty());
+}
+#[test]
+fn block_types() {
+    use instructions::control::BlockType;
+    assert_eq!(BlockType::TypeIdx(63).to_bytes(), [0x3F]);
+    assert_eq!(BlockType::TypeIdx(64).to_bytes(), [0xC0, 0x00]);
+    for index in [0, 63, 64, 105, 111, 112, 123, 127, 128, 300, u32::MAX] {
+        let bytes = BlockType::TypeIdx(index).to_bytes();
+        let (rest, parsed) = BlockType::parse_simple(&bytes).unwrap();
+        assert!(rest.is_empty());
+        assert!(matches!(parsed, BlockType::TypeIdx(parsed) if parsed == index));
+    }
+}
+#[test]
+fn empty_else() {
+    use instructions::{control::ControlInstruction, Expression};
+    use modules::{Module, Section};
+    //`if end` and `if else end`
+    for bytes in [&[0x04, 0x40, 0x0B][..], &[0x04, 0x40, 0x05, 0x0B]] {
+        let (rest, parsed) = ControlInstruction::parse_simple(bytes).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(parsed.to_bytes(), bytes);
+    }
+    let expression = [0x41, 0x00, 0x04, 0x40, 0x05, 0x0B, 0x0B];
+    let (_, parsed) = Expression::parse_simple(&expression).unwrap();
+    let folded = Expression::from_operators(parsed.into_operators()).unwrap();
+    assert_eq!(folded.to_bytes(), expression);
+
+    let module = text::parse("(module (func i32.const 0 if else end))").unwrap();
+    let bytes = module.to_bytes();
+    assert!(bytes
+        .windows(4)
+        .any(|window| window == [0x04, 0x40, 0x05, 0x0B]));
+    let printed = text::print(&module);
+    assert!(printed.contains("else"));
+    assert_eq!(text::parse(&printed).unwrap().to_bytes(), bytes);
+    let parsed = Module::from_bytes(&bytes).unwrap();
+    match parsed.sections.last() {
+        Some(Section::CodeSection(code)) => {
+            assert_eq!(code.0[0].instruction_offsets(&bytes).unwrap().len(), 2)
+        }
+        other => panic!("expected the code section, found {:?}", other),
+    }
+}
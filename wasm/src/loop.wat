@@ -0,0 +1,14 @@
+(module
+  (memory (export "memory") 1)
+  (func (export "upper") (param $length i32) (local $i i32) (local $sym i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (local.get $length)))
+        (local.set $sym (i32.load8_u (local.get $i)))
+        (if (i32.ge_u (local.get $sym) (i32.const 97))
+          (then
+            (if (i32.le_u (local.get $sym) (i32.const 122))
+              (then
+                (i32.store8 (local.get $i) (i32.sub (local.get $sym) (i32.const 32)))))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))))
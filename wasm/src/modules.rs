@@ -1,5 +1,5 @@
 use self::{
-    code::Code,
+    code::{Code, LazyCode},
     data::Data,
     element::Elem,
     export::Export,
@@ -7,157 +7,612 @@ use self::{
     import::Import,
     indices::{FuncIdx, TypeIdx},
     memory::Memory,
+    names::NameSection,
     table::Table,
+    tag::Tag,
+};
+use crate::{
+    error::{
+        CodeError, Error, BAD_MAGIC, BOUNDED, DUPLICATE_SECTION, SECTION_OUT_OF_ORDER,
+        SECTION_SIZE_MISMATCH, UNKNOWN_SECTION_ID, UNSUPPORTED_VERSION,
+    },
+    types::FuncType,
 };
-use crate::types::FuncType;
 use nom::{
     bytes::complete::{tag, take},
-    combinator::{consumed, map, opt, verify},
-    multi::many0,
+    combinator::{map, verify},
+    error::{context, ErrorKind, VerboseError},
+    sequence::tuple,
 };
-use wasm_core::values::{Name, Parse};
-use wasm_derive::Parse;
+use std::{borrow::Cow, ops::Range};
+use wasm_core::values::{in_context, Encode, Name, Parse};
+use wasm_derive::{Encode, Parse};
 
-mod code;
-mod data;
-mod element;
-mod export;
-mod global;
-mod import;
+pub mod code;
+pub mod data;
+pub mod element;
+pub mod export;
+pub mod global;
+pub mod import;
 pub mod indices;
-mod memory;
-mod table;
+pub mod memory;
+pub mod names;
+pub mod table;
+pub mod tag;
+
+///the id of the tag section, the highest one
+pub(crate) const LAST_SECTION_ID: u8 = 13;
 
 #[derive(Debug)]
-pub enum Section {
-    CustomSection(CustomSection),
+pub enum Section<'a> {
+    CustomSection(CustomSection<'a>),
     TypeSection(TypeSection),
-    ImportSection(ImportSection),
+    ImportSection(ImportSection<'a>),
     FunctionSection(FunctionSection),
     TableSection(TableSection),
     MemorySection(MemorySection),
     GlobalSection(GlobalSection),
-    ExportSection(ExportSection),
+    ExportSection(ExportSection<'a>),
     StartSection(StartSection),
     ElementSection(ElementSection),
     CodeSection(CodeSection),
-    DataSection(DataSection),
+    ///the code section of modules parsed with [ParseOptions::lazy_code]
+    LazyCodeSection(LazyCodeSection<'a>),
+    DataSection(DataSection<'a>),
     DataCountSection(DataCountSection),
+    TagSection(TagSection),
 }
 
 #[derive(Debug)]
-pub struct CustomSection {
-    pub name: Name,
-    pub data: Vec<u8>,
-}
-#[derive(Parse, Debug)]
-pub struct TypeSection(Vec<FuncType>);
-#[derive(Parse, Debug)]
-pub struct ImportSection(Vec<Import>);
-#[derive(Parse, Debug)]
-pub struct FunctionSection(Vec<TypeIdx>);
-#[derive(Parse, Debug)]
-pub struct TableSection(Vec<Table>);
-#[derive(Debug, Parse)]
-pub struct MemorySection(Vec<Memory>);
-#[derive(Parse, Debug)]
-pub struct GlobalSection(Vec<Global>);
-#[derive(Debug, Parse)]
-pub struct ExportSection(Vec<Export>);
-#[derive(Parse, Debug)]
-pub struct StartSection(Option<FuncIdx>);
-#[derive(Parse, Debug)]
-pub struct ElementSection(Vec<Elem>);
-#[derive(Parse, Debug)]
-pub struct CodeSection(Vec<Code>);
-#[derive(Parse, Debug)]
-pub struct DataSection(Vec<Data>);
-#[derive(Parse, Debug)]
-pub struct DataCountSection(Option<u32>);
+pub struct CustomSection<'a> {
+    pub name: Name<'a>,
+    pub data: Cow<'a, [u8]>,
+}
+impl CustomSection<'_> {
+    pub fn into_owned(self) -> CustomSection<'static> {
+        CustomSection {
+            name: self.name.into_owned(),
+            data: Cow::Owned(self.data.into_owned()),
+        }
+    }
+}
+#[derive(Parse, Encode, Debug)]
+pub struct TypeSection(pub Vec<FuncType>);
+#[derive(Parse, Encode, Debug)]
+pub struct ImportSection<'a>(pub Vec<Import<'a>>);
+#[derive(Parse, Encode, Debug)]
+pub struct FunctionSection(pub Vec<TypeIdx>);
+#[derive(Parse, Encode, Debug)]
+pub struct TableSection(pub Vec<Table>);
+#[derive(Debug, Parse, Encode)]
+pub struct MemorySection(pub Vec<Memory>);
+#[derive(Parse, Encode, Debug)]
+pub struct TagSection(pub Vec<Tag>);
+#[derive(Parse, Encode, Debug)]
+pub struct GlobalSection(pub Vec<Global>);
+#[derive(Debug, Parse, Encode)]
+pub struct ExportSection<'a>(pub Vec<Export<'a>>);
+#[derive(Parse, Encode, Debug)]
+pub struct StartSection(pub Option<FuncIdx>);
+#[derive(Parse, Encode, Debug)]
+pub struct ElementSection(pub Vec<Elem>);
+#[derive(Encode, Debug)]
+pub struct CodeSection(pub Vec<Code>);
+#[derive(Encode, Debug, Clone)]
+pub struct LazyCodeSection<'a>(pub Vec<LazyCode<'a>>);
+#[derive(Parse, Encode, Debug)]
+pub struct DataSection<'a>(pub Vec<Data<'a>>);
+#[derive(Parse, Encode, Debug)]
+pub struct DataCountSection(pub Option<u32>);
 
 #[derive(Debug)]
-pub struct Module {
+///a module, borrowing names, custom sections and data segments from the parsed input
+pub struct Module<'a> {
     pub magic: Magic,
     pub version: Version,
-    pub sections: Vec<Section>,
+    pub sections: Vec<Section<'a>>,
+    ///bytes of each of `sections` in the parsed input, empty if the module wasn't parsed
+    pub section_ranges: Vec<Range<usize>>,
 }
 #[derive(Debug)]
 pub struct Magic;
-impl Parse for Magic {
-    fn parse<'a, E>(i: &'a [u8]) -> nom::IResult<&[u8], Self, E>
+impl<'a> Parse<'a> for Magic {
+    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
     where
         E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
     {
         let magic = [0x00, 0x61, 0x73, 0x6D];
-        map(tag(magic), |_| Self)(i)
+        context(BAD_MAGIC, map(tag(magic), |_| Self))(i)
+    }
+}
+impl Encode for Magic {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&[0x00, 0x61, 0x73, 0x6D]);
     }
 }
 #[derive(Debug)]
 pub struct Version(pub [u8; 4]);
 
-impl Parse for Version {
-    fn parse<'a, E>(i: &'a [u8]) -> nom::IResult<&[u8], Self, E>
+impl<'a> Parse<'a> for Version {
+    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
     where
         E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
     {
-        map(take(4_usize), |version: &[u8]| {
-            Self([version[0], version[1], version[2], version[3]])
-        })(i)
+        let version = verify(take(4_usize), |version: &[u8]| version == [1, 0, 0, 0]);
+        context(
+            UNSUPPORTED_VERSION,
+            map(version, |version: &[u8]| {
+                Self([version[0], version[1], version[2], version[3]])
+            }),
+        )(i)
+    }
+}
+impl Encode for Version {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.0);
     }
 }
 
-impl Parse for Section {
-    fn parse<'a, E>(i: &'a [u8]) -> nom::IResult<&[u8], Self, E>
+impl<'a> Parse<'a> for Section<'a> {
+    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
     where
         E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
     {
-        let (i, id) = verify(u8::parse, |id| *id <= 12)(i)?;
+        Self::parse_with(i, false)
+    }
+}
+impl<'a> Section<'a> {
+    ///with `lazy_code` the code section is parsed as a [LazyCodeSection]
+    pub fn parse_with<E>(i: &'a [u8], lazy_code: bool) -> nom::IResult<&'a [u8], Self, E>
+    where
+        E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
+    {
+        let start = i;
+        let (i, id) = context(
+            UNKNOWN_SECTION_ID,
+            verify(u8::parse, |id| *id <= LAST_SECTION_ID),
+        )(i)?;
         let (i, length) = u32::parse(i)?;
-        let section = match id {
-            //0 => map(CustomSection::parse, |sec| Self::CustomSection(sec))(i),
+        let header = start.len() - i.len();
+
+        let contents = |i: &'a [u8]| match id {
             0 => {
-                let (i, (cons, name)) = consumed(Name::parse)(i)?;
-                let (i, bytes) = take(length as usize - cons.len())(i)?;
+                let (data, name) = Name::parse(i)?;
                 let sec = Self::CustomSection(CustomSection {
                     name,
-                    data: bytes.to_owned(),
+                    data: data.into(),
                 });
-                Ok((i, sec))
+                Ok((&data[data.len()..], sec))
             }
-            1 => map(TypeSection::parse_dbg, |sec| Self::TypeSection(sec))(i),
-            2 => map(ImportSection::parse_dbg, |sec| Self::ImportSection(sec))(i),
-            3 => map(FunctionSection::parse_dbg, |sec| Self::FunctionSection(sec))(i),
-            4 => map(TableSection::parse_dbg, |sec| Self::TableSection(sec))(i),
-            5 => map(MemorySection::parse_dbg, |sec| Self::MemorySection(sec))(i),
-            6 => map(GlobalSection::parse_dbg, |sec| Self::GlobalSection(sec))(i),
-            7 => map(ExportSection::parse_dbg, |sec| Self::ExportSection(sec))(i),
-            8 => map(StartSection::parse_dbg, |sec| Self::StartSection(sec))(i),
-            9 => map(ElementSection::parse_dbg, |sec| Self::ElementSection(sec))(i),
-            10 => map(CodeSection::parse_dbg, |sec| Self::CodeSection(sec))(i),
-            11 => map(DataSection::parse_dbg, |sec| Self::DataSection(sec))(i),
-            12 => map(DataCountSection::parse_dbg, |sec| {
-                Self::DataCountSection(sec)
+            1 => map(TypeSection::parse, Self::TypeSection)(i),
+            2 => map(ImportSection::parse, Self::ImportSection)(i),
+            3 => map(FunctionSection::parse, Self::FunctionSection)(i),
+            4 => map(TableSection::parse, Self::TableSection)(i),
+            5 => map(MemorySection::parse, Self::MemorySection)(i),
+            6 => map(GlobalSection::parse, Self::GlobalSection)(i),
+            7 => map(ExportSection::parse, Self::ExportSection)(i),
+            8 => map(StartSection::parse, Self::StartSection)(i),
+            9 => map(ElementSection::parse, Self::ElementSection)(i),
+            10 if lazy_code => map(LazyCodeSection::parse, |mut sec| {
+                sec.shift(header);
+                Self::LazyCodeSection(sec)
+            })(i),
+            10 => map(CodeSection::parse, |mut sec| {
+                sec.shift(header);
+                Self::CodeSection(sec)
             })(i),
+            11 => map(DataSection::parse, Self::DataSection)(i),
+            12 => map(DataCountSection::parse, Self::DataCountSection)(i),
+            13 => map(TagSection::parse, Self::TagSection)(i),
             _ => unreachable!(),
         };
-        section
+        sized(start, i, length, SECTION_SIZE_MISMATCH, contents)
+    }
+}
+///parses exactly `length` bytes of `i` with `contents`, failing with the `mismatch` context
+///at `start`, where the entry begins, if they are shorter or longer than that
+pub(crate) fn sized<'a, T, E>(
+    start: &'a [u8],
+    i: &'a [u8],
+    length: u32,
+    mismatch: &'static str,
+    mut contents: impl FnMut(&'a [u8]) -> nom::IResult<&'a [u8], T, E>,
+) -> nom::IResult<&'a [u8], T, E>
+where
+    E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
+{
+    let mismatched = || {
+        let error = E::from_error_kind(start, ErrorKind::Verify);
+        nom::Err::Error(E::add_context(start, mismatch, error))
+    };
+    let (rest, bounded) = take(length as usize)(i)?;
+    //positions in `bounded` end with it, the `BOUNDED` marker at `rest` moves them to `i`
+    let bounded_error =
+        |error| E::add_context(start, mismatch, E::add_context(rest, BOUNDED, error));
+    match contents(bounded) {
+        Ok(([], parsed)) => Ok((rest, parsed)),
+        //contents shorter than declared, or running past the declared end with LEB128
+        //integers cut short, which are incomplete
+        Ok(_) | Err(nom::Err::Incomplete(_)) => Err(mismatched()),
+        Err(nom::Err::Error(error)) => Err(nom::Err::Error(bounded_error(error))),
+        Err(nom::Err::Failure(error)) => Err(nom::Err::Failure(bounded_error(error))),
+    }
+}
+impl<'a> Parse<'a> for CodeSection {
+    ///the ranges of the entries start from the section's contents
+    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
+    where
+        E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
+    {
+        let (i, codes) = code_entries(i, Code::shift)?;
+        Ok((i, Self(codes)))
+    }
+}
+impl<'a> Parse<'a> for LazyCodeSection<'a> {
+    ///the ranges of the entries start from the section's contents
+    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
+    where
+        E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
+    {
+        let (i, codes) = code_entries(i, LazyCode::shift)?;
+        Ok((i, Self(codes)))
+    }
+}
+///the vector of a code section, with `shift` moving each entry to its offset in `i`
+fn code_entries<'a, T: Parse<'a>, E>(
+    i: &'a [u8],
+    shift: fn(&mut T, usize),
+) -> nom::IResult<&'a [u8], Vec<T>, E>
+where
+    E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
+{
+    let start = i;
+    let (mut i, length) = in_context(start, "CodeSection", u32::parse(i))?;
+    let mut codes = vec![];
+    for _ in 0..length {
+        let (rest, mut code) = in_context(start, "CodeSection", T::parse(i))?;
+        shift(&mut code, start.len() - i.len());
+        codes.push(code);
+        i = rest;
+    }
+    Ok((i, codes))
+}
+impl CodeSection {
+    fn shift(&mut self, offset: usize) {
+        for code in &mut self.0 {
+            code.shift(offset);
+        }
+    }
+}
+impl LazyCodeSection<'_> {
+    fn shift(&mut self, offset: usize) {
+        for code in &mut self.0 {
+            code.shift(offset);
+        }
+    }
+    ///decodes every body, failing on the first malformed one
+    pub fn decode(&self) -> Result<CodeSection, CodeError> {
+        let codes = self.0.iter().enumerate().map(decode_entry);
+        Ok(CodeSection(codes.collect::<Result<_, _>>()?))
+    }
+    ///like [LazyCodeSection::decode], with the bodies spread over the rayon thread pool.
+    ///the error is still the one of the first malformed body
+    #[cfg(feature = "parallel")]
+    pub fn decode_parallel(&self) -> Result<CodeSection, CodeError> {
+        use rayon::prelude::*;
+        let codes = self.0.par_iter().enumerate().map(decode_entry);
+        match codes.collect::<Result<_, _>>() {
+            Ok(codes) => Ok(CodeSection(codes)),
+            //stops at any error, only the bodies before it may hold an earlier one
+            Err(error) => {
+                let before = self.0[..error.entry as usize].par_iter().enumerate();
+                Err(before
+                    .find_map_first(|entry| decode_entry(entry).err())
+                    .unwrap_or(error))
+            }
+        }
+    }
+}
+fn decode_entry((index, code): (usize, &LazyCode<'_>)) -> Result<Code, CodeError> {
+    code.decode_code().map_err(|error| CodeError {
+        entry: index as u32,
+        error,
+    })
+}
+impl Section<'_> {
+    ///moves the ranges of nested entries by `offset`
+    pub(crate) fn shift(&mut self, offset: usize) {
+        match self {
+            Section::CodeSection(sec) => sec.shift(offset),
+            Section::LazyCodeSection(sec) => sec.shift(offset),
+            _ => {}
+        }
+    }
+    ///copies what the section borrows from the input
+    pub fn into_owned(self) -> Section<'static> {
+        match self {
+            Section::CustomSection(sec) => Section::CustomSection(sec.into_owned()),
+            Section::ImportSection(sec) => {
+                let imports = sec.0.into_iter().map(Import::into_owned);
+                Section::ImportSection(ImportSection(imports.collect()))
+            }
+            Section::ExportSection(sec) => {
+                let exports = sec.0.into_iter().map(Export::into_owned);
+                Section::ExportSection(ExportSection(exports.collect()))
+            }
+            Section::DataSection(sec) => {
+                let datas = sec.0.into_iter().map(Data::into_owned);
+                Section::DataSection(DataSection(datas.collect()))
+            }
+            Section::TypeSection(sec) => Section::TypeSection(sec),
+            Section::FunctionSection(sec) => Section::FunctionSection(sec),
+            Section::TableSection(sec) => Section::TableSection(sec),
+            Section::MemorySection(sec) => Section::MemorySection(sec),
+            Section::GlobalSection(sec) => Section::GlobalSection(sec),
+            Section::StartSection(sec) => Section::StartSection(sec),
+            Section::ElementSection(sec) => Section::ElementSection(sec),
+            Section::CodeSection(sec) => Section::CodeSection(sec),
+            Section::LazyCodeSection(sec) => {
+                let codes = sec.0.into_iter().map(LazyCode::into_owned);
+                Section::LazyCodeSection(LazyCodeSection(codes.collect()))
+            }
+            Section::DataCountSection(sec) => Section::DataCountSection(sec),
+            Section::TagSection(sec) => Section::TagSection(sec),
+        }
+    }
+    pub fn id(&self) -> u8 {
+        match self {
+            Section::CustomSection(_) => 0,
+            Section::TypeSection(_) => 1,
+            Section::ImportSection(_) => 2,
+            Section::FunctionSection(_) => 3,
+            Section::TableSection(_) => 4,
+            Section::MemorySection(_) => 5,
+            Section::GlobalSection(_) => 6,
+            Section::ExportSection(_) => 7,
+            Section::StartSection(_) => 8,
+            Section::ElementSection(_) => 9,
+            Section::CodeSection(_) | Section::LazyCodeSection(_) => 10,
+            Section::DataSection(_) => 11,
+            Section::DataCountSection(_) => 12,
+            Section::TagSection(_) => 13,
+        }
+    }
+}
+impl Encode for Section<'_> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let content = match self {
+            Section::CustomSection(sec) => sec.to_bytes(),
+            Section::TypeSection(sec) => sec.to_bytes(),
+            Section::ImportSection(sec) => sec.to_bytes(),
+            Section::FunctionSection(sec) => sec.to_bytes(),
+            Section::TableSection(sec) => sec.to_bytes(),
+            Section::MemorySection(sec) => sec.to_bytes(),
+            Section::GlobalSection(sec) => sec.to_bytes(),
+            Section::ExportSection(sec) => sec.to_bytes(),
+            Section::StartSection(sec) => sec.to_bytes(),
+            Section::ElementSection(sec) => sec.to_bytes(),
+            Section::CodeSection(sec) => sec.to_bytes(),
+            Section::LazyCodeSection(sec) => sec.to_bytes(),
+            Section::DataSection(sec) => sec.to_bytes(),
+            Section::DataCountSection(sec) => sec.to_bytes(),
+            Section::TagSection(sec) => sec.to_bytes(),
+        };
+        self.id().encode(out);
+        (content.len() as u32).encode(out);
+        out.extend_from_slice(&content);
+    }
+}
+impl Encode for CustomSection<'_> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.name.encode(out);
+        out.extend_from_slice(&self.data);
     }
 }
 
-impl Parse for Module {
-    fn parse<'a, E>(i: &'a [u8]) -> nom::IResult<&[u8], Self, E>
+///how [Module::parse_with] reads a module, the default is what [Module::parse] does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ParseOptions {
+    pub order: SectionOrder,
+    ///keeps function bodies encoded, in a [LazyCodeSection]
+    pub lazy_code: bool,
+}
+///which sequences of sections [Module::parse_with] accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SectionOrder {
+    ///the order of the spec, each non custom section at most once
+    #[default]
+    Strict,
+    ///any order and multiplicity, for inspecting malformed modules
+    Any,
+}
+impl Section<'_> {
+    ///position of the section in the order of the spec, `None` for custom sections
+    pub fn rank(&self) -> Option<u8> {
+        rank(self.id())
+    }
+}
+fn rank(id: u8) -> Option<u8> {
+    match id {
+        0 => None,
+        id @ 1..=5 => Some(id),
+        //the tag section comes between the memory and global sections
+        13 => Some(6),
+        id @ 6..=9 => Some(id + 1),
+        //the data count section comes right before the code section
+        12 => Some(11),
+        id => Some(id + 2),
+    }
+}
+///the context of the error for a section `id` coming after a section of `last_rank`,
+///which is moved to the rank of `id` otherwise
+pub(crate) fn misplaced(last_rank: &mut Option<u8>, id: u8) -> Option<&'static str> {
+    let rank = rank(id)?;
+    match *last_rank {
+        Some(last) if last == rank => Some(DUPLICATE_SECTION),
+        Some(last) if last > rank => Some(SECTION_OUT_OF_ORDER),
+        _ => {
+            *last_rank = Some(rank);
+            None
+        }
+    }
+}
+
+impl<'a> Parse<'a> for Module<'a> {
+    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
     where
         E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
     {
-        let (i, magic) = Magic::parse(i)?;
-        let (i, version) = Version::parse(i)?;
-        let (i, sections) = many0(Section::parse_dbg)(i)?;
-        let (i, _) = opt(tag(b"\n"))(i)?;
+        Self::parse_with(i, ParseOptions::default())
+    }
+}
+impl<'a> Module<'a> {
+    pub fn parse_with<E>(i: &'a [u8], options: ParseOptions) -> nom::IResult<&'a [u8], Self, E>
+    where
+        E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
+    {
+        let input = i;
+        let (mut i, (magic, version)) = tuple((Magic::parse, Version::parse))(i)?;
+        let mut sections = vec![];
+        let mut section_ranges = vec![];
+        let mut last_rank = None;
+        while !i.is_empty() {
+            let (rest, mut section) =
+                in_context(input, "Module", Section::parse_with(i, options.lazy_code))?;
+            if options.order == SectionOrder::Strict {
+                if let Some(misplaced) = misplaced(&mut last_rank, section.id()) {
+                    let error = E::from_error_kind(i, ErrorKind::Verify);
+                    let error = E::add_context(i, misplaced, error);
+                    return Err(nom::Err::Error(E::add_context(input, "Module", error)));
+                }
+            }
+            let start = input.len() - i.len();
+            section.shift(start);
+            sections.push(section);
+            section_ranges.push(start..input.len() - rest.len());
+            i = rest;
+        }
         let module = Self {
             magic,
             version,
             sections,
+            section_ranges,
         };
         Ok((i, module))
     }
+    ///parses a whole binary module
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, Error> {
+        Self::from_bytes_with(bytes, ParseOptions::default())
+    }
+    pub fn from_bytes_with(bytes: &'a [u8], options: ParseOptions) -> Result<Self, Error> {
+        match Self::parse_with::<VerboseError<_>>(bytes, options) {
+            Ok((_, module)) => Ok(module),
+            Err(error) => Err(Error::from_nom(bytes, error)),
+        }
+    }
+    ///copies what the module borrows from the input, so it can outlive it
+    pub fn into_owned(self) -> Module<'static> {
+        Module {
+            magic: self.magic,
+            version: self.version,
+            sections: self.sections.into_iter().map(Section::into_owned).collect(),
+            section_ranges: self.section_ranges,
+        }
+    }
+    ///replaces the lazy code sections with decoded ones, failing on the first malformed body
+    pub fn decode_code(&mut self) -> Result<(), CodeError> {
+        for section in &mut self.sections {
+            if let Section::LazyCodeSection(sec) = section {
+                *section = Section::CodeSection(sec.decode()?);
+            }
+        }
+        Ok(())
+    }
+    ///[Module::decode_code] with [LazyCodeSection::decode_parallel]
+    #[cfg(feature = "parallel")]
+    pub fn decode_code_parallel(&mut self) -> Result<(), CodeError> {
+        for section in &mut self.sections {
+            if let Section::LazyCodeSection(sec) = section {
+                *section = Section::CodeSection(sec.decode_parallel()?);
+            }
+        }
+        Ok(())
+    }
+    ///each custom section with the id of the last non custom section before it,
+    ///`None` if it comes first. `sections` keeps the parsed order, so encoding preserves it
+    pub fn custom_placements(&self) -> Vec<(Option<u8>, &CustomSection<'a>)> {
+        let mut previous = None;
+        let mut placements = vec![];
+        for section in &self.sections {
+            match section {
+                Section::CustomSection(custom) => placements.push((previous, custom)),
+                section => previous = Some(section.id()),
+            }
+        }
+        placements
+    }
+    ///the `name` custom section, `None` if it is missing or malformed
+    pub fn names(&self) -> Option<NameSection<'_>> {
+        self.sections.iter().find_map(|section| match section {
+            Section::CustomSection(custom) if custom.name.as_bytes() == NameSection::NAME => {
+                let (_, names) = NameSection::parse_simple(&custom.data).ok()?;
+                Some(names)
+            }
+            _ => None,
+        })
+    }
+}
+impl Encode for Module<'_> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.magic.encode(out);
+        self.version.encode(out);
+        for section in &self.sections {
+            section.encode(out);
+        }
+    }
+}
+
+#[test]
+fn section_order() {
+    use crate::{error::ErrorKind, fixtures::module_bytes};
+    let types: &[u8] = &[0x01, 0x04, 0x01, 0x60, 0x00, 0x00];
+    let functions: &[u8] = &[0x03, 0x02, 0x01, 0x00];
+    let custom: &[u8] = &[0x00, 0x03, 0x02, b'h', b'i'];
+    let (data_count, code): (&[u8], &[u8]) = (&[0x0C, 0x01, 0x00], &[0x0A, 0x01, 0x00]);
+
+    let swapped = module_bytes(&[functions, types]);
+    let error = Module::from_bytes(&swapped).unwrap_err();
+    assert_eq!(
+        (error.kind, error.offset),
+        (ErrorKind::SectionOutOfOrder(1), 12)
+    );
+    let order = SectionOrder::Any;
+    let options = ParseOptions {
+        order,
+        ..Default::default()
+    };
+    assert!(Module::from_bytes_with(&swapped, options).is_ok());
+    let error = Module::from_bytes(&module_bytes(&[types, types])).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::DuplicateSection(1));
+    assert!(Module::from_bytes(&module_bytes(&[data_count, code])).is_ok());
+    let error = Module::from_bytes(&module_bytes(&[code, data_count])).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::SectionOutOfOrder(12));
+    //the tag section sits between the memory and global sections
+    let (memory, tags): (&[u8], &[u8]) = (
+        &[0x05, 0x03, 0x01, 0x00, 0x01],
+        &[0x0D, 0x03, 0x01, 0x00, 0x00],
+    );
+    assert!(Module::from_bytes(&module_bytes(&[types, memory, tags])).is_ok());
+    let error = Module::from_bytes(&module_bytes(&[types, tags, memory])).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::SectionOutOfOrder(5));
+
+    let bytes = module_bytes(&[custom, types, custom, custom, functions]);
+    let parsed = Module::from_bytes(&bytes).unwrap();
+    let placements: Vec<_> = parsed
+        .custom_placements()
+        .iter()
+        .map(|(after, _)| *after)
+        .collect();
+    assert_eq!(placements, [None, Some(1), Some(1)]);
+    assert_eq!(parsed.to_bytes(), bytes);
 }
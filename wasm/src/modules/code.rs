@@ -1,19 +1,393 @@
-use crate::{instructions::Expression, types::ValueType};
-use wasm_core::values::Parse;
-use wasm_derive::Parse;
+use super::sized;
+use crate::{
+    error::{Error, ErrorKind, CODE_SIZE_MISMATCH},
+    instructions::{
+        control::{BlockType, Catch, ControlInstruction},
+        operator::OperatorReader,
+        Expression, Instruction,
+    },
+    modules::indices::{LabelIdx, TagIdx},
+    types::ValueType,
+};
+use nom::{bytes::complete::take, error::VerboseError};
+use std::{borrow::Cow, ops::Range};
+use wasm_core::values::{in_context, Encode, Parse};
+use wasm_derive::{Encode, Parse};
 
-#[derive(Parse, Debug)]
+#[derive(Debug)]
 pub struct Code {
     pub size: u32,
     pub code: Func,
+    ///bytes of the entry in the parsed input, size included, empty if it wasn't parsed
+    pub range: Range<usize>,
 }
-#[derive(Parse, Debug)]
+#[derive(Parse, Encode, Debug)]
 pub struct Func {
     pub locals: Vec<Local>,
     pub body: Expression,
 }
-#[derive(Parse, Debug)]
+#[derive(Parse, Encode, Debug)]
 pub struct Local {
     pub count: u32,
     pub value_type: ValueType,
 }
+
+impl<'a> Parse<'a> for Code {
+    ///`range` starts at 0, the enclosing parsers move it with [Code::shift]
+    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
+    where
+        E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
+    {
+        let start = i;
+        let parsed = u32::parse(i).and_then(|(i, size)| {
+            let (rest, code) = sized(start, i, size, CODE_SIZE_MISMATCH, Func::parse)?;
+            Ok((rest, (size, code)))
+        });
+        let (rest, (size, code)) = in_context(start, "Code", parsed)?;
+        let range = 0..start.len() - rest.len();
+        Ok((rest, Self { size, code, range }))
+    }
+}
+impl Encode for Code {
+    ///`size` is recomputed from the encoded body rather than trusted
+    fn encode(&self, out: &mut Vec<u8>) {
+        let code = self.code.to_bytes();
+        (code.len() as u32).encode(out);
+        out.extend_from_slice(&code);
+    }
+}
+
+///a function body kept encoded until [LazyCode::decode] is called, borrowing from the input
+#[derive(Debug, Clone)]
+pub struct LazyCode<'a> {
+    pub size: u32,
+    ///the encoded locals and expression, `size` bytes long
+    pub body: Cow<'a, [u8]>,
+    ///bytes of the entry in the parsed input, size included, empty if it wasn't parsed
+    pub range: Range<usize>,
+}
+impl<'a> Parse<'a> for LazyCode<'a> {
+    ///`range` starts at 0, like the one of [Code]
+    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
+    where
+        E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
+    {
+        let start = i;
+        let parsed = u32::parse(i).and_then(|(i, size)| {
+            let (rest, body) = take(size as usize)(i)?;
+            Ok((rest, (size, body)))
+        });
+        let (rest, (size, body)) = in_context(start, "Code", parsed)?;
+        let range = 0..start.len() - rest.len();
+        let body = Cow::Borrowed(body);
+        Ok((rest, Self { size, body, range }))
+    }
+}
+impl Encode for LazyCode<'_> {
+    ///the size prefix is taken from the body, which may have been replaced since parsing
+    fn encode(&self, out: &mut Vec<u8>) {
+        (self.body.len() as u32).encode(out);
+        out.extend_from_slice(&self.body);
+    }
+}
+impl LazyCode<'_> {
+    pub(crate) fn shift(&mut self, offset: usize) {
+        self.range = self.range.start + offset..self.range.end + offset;
+    }
+    pub fn into_owned(self) -> LazyCode<'static> {
+        LazyCode {
+            size: self.size,
+            body: Cow::Owned(self.body.into_owned()),
+            range: self.range,
+        }
+    }
+    ///the offset of the body in the parsed input, 0 if it wasn't parsed
+    fn base(&self) -> usize {
+        //the body ends where the entry does
+        self.range.end.saturating_sub(self.body.len())
+    }
+    ///decodes the locals and instructions of the body, with the error offsets in the parsed
+    ///input, or in the body if it wasn't parsed
+    pub fn decode(&self) -> Result<Func, Error> {
+        match Func::parse::<VerboseError<_>>(&self.body) {
+            Ok(([], func)) => Ok(func),
+            Ok(_) => Err(Error {
+                kind: ErrorKind::CodeSizeMismatch {
+                    declared: self.size,
+                },
+                offset: self.range.start,
+                context: vec!["Code"],
+            }),
+            Err(error) => {
+                let mut error = Error::from_nom(&self.body, error);
+                error.offset += self.base();
+                error.context.insert(0, "Code");
+                Err(error)
+            }
+        }
+    }
+    ///reads the operators of the body one at a time, with offsets like [LazyCode::decode]
+    pub fn operators(&self) -> Result<OperatorReader<'_>, Error> {
+        let base = self.base();
+        match <Vec<Local>>::parse::<VerboseError<_>>(&self.body) {
+            Ok((expression, _)) => {
+                let start = base + self.body.len() - expression.len();
+                Ok(OperatorReader::with_offset(expression, start))
+            }
+            Err(error) => {
+                let mut error = Error::from_nom(&self.body, error);
+                error.offset += base;
+                Err(error)
+            }
+        }
+    }
+    ///the decoded entry, keeping its range
+    pub fn decode_code(&self) -> Result<Code, Error> {
+        let code = self.decode()?;
+        let range = self.range.clone();
+        Ok(Code {
+            size: self.size,
+            code,
+            range,
+        })
+    }
+}
+
+impl Code {
+    pub(crate) fn shift(&mut self, offset: usize) {
+        self.range = self.range.start + offset..self.range.end + offset;
+    }
+    ///every instruction of the body in binary order, blocks before their contents, with its
+    ///offset in `input`, the module this code was parsed from.
+    ///`None` if `input` doesn't hold this code
+    pub fn instruction_offsets<'c>(
+        &'c self,
+        input: &[u8],
+    ) -> Option<Vec<(usize, &'c Instruction)>> {
+        let bytes = input.get(self.range.clone())?;
+        let (i, _) = u32::parse_simple(bytes).ok()?;
+        let (i, _) = <Vec<Local>>::parse_simple(i).ok()?;
+        let mut offsets = vec![];
+        let base = self.range.end;
+        let i = locate(&self.code.body.0, i, base, &mut offsets)?;
+        match i {
+            [0x0B] => Some(offsets),
+            _ => None,
+        }
+    }
+}
+///pushes the offsets of `instructions`, encoded at the start of `i`, which ends at `end`
+fn locate<'c, 'i>(
+    instructions: &'c [Instruction],
+    mut i: &'i [u8],
+    end: usize,
+    offsets: &mut Vec<(usize, &'c Instruction)>,
+) -> Option<&'i [u8]> {
+    let opcode = |i: &'i [u8], opcode: u8| match i.split_first() {
+        Some((first, rest)) if *first == opcode => Some(rest),
+        _ => None,
+    };
+    //the bytes after the opcode and block type
+    let block = |i: &'i [u8]| Some(BlockType::parse_simple(i.get(1..)?).ok()?.0);
+    for instruction in instructions {
+        offsets.push((end - i.len(), instruction));
+        let control = match instruction {
+            Instruction::Control(control) => control,
+            _ => {
+                i = Instruction::parse_simple(i).ok()?.0;
+                continue;
+            }
+        };
+        match control {
+            ControlInstruction::Block(_, body) | ControlInstruction::Loop(_, body) => {
+                i = locate(&body.0, block(i)?, end, offsets)?;
+            }
+            ControlInstruction::IfElse(_, body, otherwise) => {
+                i = locate(&body.0, block(i)?, end, offsets)?;
+                if let Some(otherwise) = otherwise {
+                    i = locate(&otherwise.0, opcode(i, 0x05)?, end, offsets)?;
+                }
+            }
+            ControlInstruction::TryTable(_, _, body) => {
+                let (rest, _) = <Vec<Catch>>::parse_simple(block(i)?).ok()?;
+                i = locate(&body.0, rest, end, offsets)?;
+            }
+            ControlInstruction::Try {
+                body,
+                catches,
+                catch_all,
+                ..
+            } => {
+                i = locate(&body.0, block(i)?, end, offsets)?;
+                for (_, handler) in catches {
+                    let (rest, _) = TagIdx::parse_simple(opcode(i, 0x07)?).ok()?;
+                    i = locate(&handler.0, rest, end, offsets)?;
+                }
+                if let Some(handler) = catch_all {
+                    i = locate(&handler.0, opcode(i, 0x19)?, end, offsets)?;
+                }
+            }
+            ControlInstruction::Delegate(_, body, _) => {
+                i = locate(&body.0, block(i)?, end, offsets)?;
+                i = LabelIdx::parse_simple(opcode(i, 0x18)?).ok()?.0;
+                continue;
+            }
+            _ => {
+                i = Instruction::parse_simple(i).ok()?.0;
+                continue;
+            }
+        }
+        i = opcode(i, 0x0B)?;
+    }
+    Some(i)
+}
+
+#[test]
+fn offsets() {
+    use super::{Module, Section};
+    use nom::error::VerboseError;
+    let loop_file = &include_bytes!("../loop.wasm")[..];
+    for file in [loop_file, &include_bytes!("../if.wasm")[..]] {
+        let (_, module) = Module::parse::<VerboseError<_>>(file).unwrap();
+        let mut end = 8;
+        for (section, range) in module.sections.iter().zip(&module.section_ranges) {
+            assert_eq!(range.start, end);
+            assert_eq!(file[range.start], section.id());
+            end = range.end;
+        }
+        assert_eq!(end, file.len());
+        let codes = module.sections.iter().find_map(|section| match section {
+            Section::CodeSection(codes) => Some(&codes.0),
+            _ => None,
+        });
+        for code in codes.unwrap() {
+            assert_eq!(file[code.range.clone()], code.to_bytes()[..]);
+            let offsets = code.instruction_offsets(file).unwrap();
+            for (offset, instruction) in offsets {
+                assert_eq!(file[offset], instruction.to_bytes()[0]);
+            }
+        }
+    }
+    let (_, module) = Module::parse::<VerboseError<_>>(loop_file).unwrap();
+    if let Some(Section::CodeSection(codes)) = module.sections.get(4) {
+        let offsets = codes.0[0].instruction_offsets(loop_file).unwrap();
+        //block, loop, then the first local.get
+        let starts: Vec<_> = offsets.iter().take(3).map(|(offset, _)| *offset).collect();
+        assert_eq!(starts, [0x33, 0x35, 0x37]);
+        assert!(codes.0[0].instruction_offsets(&loop_file[1..]).is_none());
+    } else {
+        panic!("expected the code section");
+    }
+}
+
+#[test]
+fn lazy() {
+    use super::{Module, ParseOptions, Section};
+    use crate::fixtures::module_bytes;
+    let options = ParseOptions {
+        lazy_code: true,
+        ..Default::default()
+    };
+    let file = include_bytes!("../loop.wasm");
+    let mut module = Module::from_bytes_with(file, options).unwrap();
+    let lazy_codes = match &module.sections[4] {
+        Section::LazyCodeSection(codes) => codes.clone(),
+        _ => panic!("expected the lazy code section"),
+    };
+    let range = file.as_ptr_range();
+    let body = &lazy_codes.0[0].body;
+    assert!(matches!(body, Cow::Borrowed(_)) && range.contains(&body.as_ptr()));
+    let owned = lazy_codes.0[0].clone().into_owned();
+    assert!(matches!(owned.body, Cow::Owned(_)));
+    assert_eq!(owned.decode().unwrap().to_bytes(), owned.body[..]);
+    assert!(crate::validation::validate(&module).is_err());
+    let bytes = module.to_bytes();
+    module.decode_code().unwrap();
+    assert_eq!(module.to_bytes(), bytes);
+    let eager = Module::from_bytes(file).unwrap();
+    if let (Section::CodeSection(lazy), Section::CodeSection(eager)) =
+        (&module.sections[4], &eager.sections[4])
+    {
+        assert_eq!(lazy.0[0].range, eager.0[0].range);
+        let offsets = |code: &Code| code.instruction_offsets(file).unwrap().len();
+        assert_eq!(offsets(&lazy.0[0]), offsets(&eager.0[0]));
+        let operators = |code: &LazyCode| code.operators().unwrap().next().unwrap().unwrap();
+        let (offset, _) = operators(&lazy_codes.0[0]);
+        assert_eq!(offset, eager.0[0].instruction_offsets(file).unwrap()[0].0);
+    } else {
+        panic!("expected the code sections");
+    }
+
+    //`block (unknown) end end`, found only when decoding
+    let types = [0x01, 0x04, 0x01, 0x60, 0x00, 0x00, 0x03, 0x02, 0x01, 0x00];
+    let code = [0x0A, 0x08, 0x01, 0x06, 0x00, 0x02, 0x40, 0xFF, 0x0B, 0x0B];
+    let bytes = module_bytes(&[&types, &code]);
+    let mut module = Module::from_bytes_with(&bytes, options).unwrap();
+    let error = module.decode_code().unwrap_err();
+    assert_eq!(error.entry, 0);
+    assert!(error.to_string().starts_with("code section, entry 0: "));
+    let error = error.error;
+    let unknown = ErrorKind::UnknownOpcode {
+        prefix: None,
+        opcode: 0xFF,
+    };
+    assert_eq!(error.kind, unknown);
+    assert_eq!(error.offset, Module::from_bytes(&bytes).unwrap_err().offset);
+
+    //`nop end` followed by a byte past the end of the expression
+    let longer = LazyCode {
+        size: 4,
+        body: Cow::Borrowed(&[0x00, 0x01, 0x0B, 0x01]),
+        range: 10..15,
+    };
+    let error = longer.decode().unwrap_err();
+    let size = ErrorKind::CodeSizeMismatch { declared: 4 };
+    assert_eq!((error.kind, error.offset), (size, 10));
+
+    //a replaced body is written with its own length
+    let mut replaced = longer.into_owned();
+    replaced.body = Cow::Owned(vec![0x00, 0x0B]);
+    let bytes = replaced.to_bytes();
+    assert_eq!(bytes, [0x02, 0x00, 0x0B]);
+    let (_, reparsed) = LazyCode::parse::<VerboseError<_>>(&bytes).unwrap();
+    assert_eq!(reparsed.decode().unwrap().to_bytes(), [0x00, 0x0B]);
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn parallel() {
+    use super::{Module, ParseOptions, Section};
+    use crate::fixtures::module_bytes;
+    let options = ParseOptions {
+        lazy_code: true,
+        ..Default::default()
+    };
+    //100 functions with `nop end` as their body, the 60th and 80th have `(unknown) end`
+    let mut types = vec![0x01, 0x04, 0x01, 0x60, 0x00, 0x00, 0x03, 0x65, 0x64];
+    types.extend([0x00; 100]);
+    let mut codes = vec![0x64];
+    for index in 0..100 {
+        let opcode = if index == 60 || index == 80 {
+            0xFF
+        } else {
+            0x01
+        };
+        codes.extend([0x03, 0x00, opcode, 0x0B]);
+    }
+    let code = [&[0x0A, 0x91, 0x03][..], &codes].concat();
+    let bytes = module_bytes(&[&types, &code]);
+    let mut module = Module::from_bytes_with(&bytes, options).unwrap();
+    let sequential = module.decode_code().unwrap_err();
+    let error = module.decode_code_parallel().unwrap_err();
+    assert_eq!(error, sequential);
+    assert_eq!(error.entry, 60);
+
+    let file = include_bytes!("../loop.wasm");
+    let mut module = Module::from_bytes_with(file, options).unwrap();
+    module.decode_code_parallel().unwrap();
+    assert!(matches!(module.sections[4], Section::CodeSection(_)));
+    assert_eq!(
+        module.to_bytes(),
+        Module::from_bytes(file).unwrap().to_bytes()
+    );
+}
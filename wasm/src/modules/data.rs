@@ -1,11 +1,55 @@
 use super::indices::MemIdx;
 use crate::instructions::Expression;
-use wasm_core::values::Parse;
-use wasm_derive::Parse;
+use std::borrow::Cow;
+use wasm_core::values::{Encode, Parse};
+use wasm_derive::{Encode, Parse};
 
-#[derive(Parse, Debug)]
-pub enum Data {
-    Active(Expression, Vec<u8>),
-    Passive(Vec<u8>),
-    ActiveExplicit(MemIdx, Expression, Vec<u8>),
+#[derive(Parse, Encode, Debug)]
+///the bytes of the segments borrow from the input
+pub enum Data<'a> {
+    Active(Expression, Cow<'a, [u8]>),
+    Passive(Cow<'a, [u8]>),
+    ActiveExplicit(MemIdx, Expression, Cow<'a, [u8]>),
+}
+impl Data<'_> {
+    pub fn into_owned(self) -> Data<'static> {
+        let owned = |bytes: Cow<[u8]>| Cow::Owned(bytes.into_owned());
+        match self {
+            Data::Active(offset, bytes) => Data::Active(offset, owned(bytes)),
+            Data::Passive(bytes) => Data::Passive(owned(bytes)),
+            Data::ActiveExplicit(memory, offset, bytes) => {
+                Data::ActiveExplicit(memory, offset, owned(bytes))
+            }
+        }
+    }
+}
+
+#[test]
+fn borrowed() {
+    use super::{Module, Section};
+    use crate::fixtures::module_bytes;
+    let memory = [0x05, 0x03, 0x01, 0x00, 0x01];
+    let data = [0x0B, 0x06, 0x01, 0x01, 0x03, b'a', b'b', b'c'];
+    let custom = [0x00, 0x04, 0x01, b'x', 0x2A, 0x2B];
+    let bytes = module_bytes(&[&memory, &data, &custom]);
+    let module = Module::from_bytes(&bytes).unwrap();
+    let range = bytes.as_ptr_range();
+    let borrowed =
+        |bytes: &Cow<[u8]>| matches!(bytes, Cow::Borrowed(_)) && range.contains(&bytes.as_ptr());
+    match (&module.sections[1], &module.sections[2]) {
+        (Section::DataSection(datas), Section::CustomSection(custom)) => {
+            assert!(matches!(&datas.0[0], Data::Passive(bytes) if borrowed(bytes)));
+            assert!(borrowed(&custom.data));
+            assert_eq!(custom.name.as_bytes(), b"x");
+        }
+        _ => panic!("expected the data and custom sections"),
+    }
+    let owned = module.into_owned();
+    drop(bytes);
+    match &owned.sections[1] {
+        Section::DataSection(datas) => {
+            assert!(matches!(&datas.0[0], Data::Passive(Cow::Owned(bytes)) if bytes == b"abc"));
+        }
+        _ => panic!("expected the data section"),
+    }
 }
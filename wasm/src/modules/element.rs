@@ -1,13 +1,13 @@
 use super::indices::{FuncIdx, TableIdx};
 use crate::{instructions::Expression, types::RefType};
-use wasm_core::values::Parse;
-use wasm_derive::Parse;
+use wasm_core::values::{Encode, Parse};
+use wasm_derive::{Encode, Parse};
 
-#[derive(Parse, Debug)]
+#[derive(Parse, Encode, Debug)]
 pub enum ElementKind {
     FuncRef,
 }
-#[derive(Parse, Debug)]
+#[derive(Parse, Encode, Debug)]
 pub enum Elem {
     ActiveIndex(Expression, Vec<FuncIdx>),
     PassiveIndex(ElementKind, Vec<FuncIdx>),
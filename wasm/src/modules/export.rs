@@ -1,16 +1,25 @@
-use super::indices::{FuncIdx, GlobalIdx, MemIdx, TableIdx};
-use wasm_core::values::{Name, Parse};
-use wasm_derive::Parse;
+use super::indices::{FuncIdx, GlobalIdx, MemIdx, TableIdx, TagIdx};
+use wasm_core::values::{Encode, Name, Parse};
+use wasm_derive::{Encode, Parse};
 
-#[derive(Parse, Debug)]
-pub struct Export {
-    pub name: Name,
+#[derive(Parse, Encode, Debug)]
+pub struct Export<'a> {
+    pub name: Name<'a>,
     pub descriptor: ExportDescriptor,
 }
-#[derive(Parse, Debug)]
+impl Export<'_> {
+    pub fn into_owned(self) -> Export<'static> {
+        Export {
+            name: self.name.into_owned(),
+            descriptor: self.descriptor,
+        }
+    }
+}
+#[derive(Parse, Encode, Debug)]
 pub enum ExportDescriptor {
     Func(FuncIdx),
     Table(TableIdx),
     Mem(MemIdx),
     Global(GlobalIdx),
+    Tag(TagIdx),
 }
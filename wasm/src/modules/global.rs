@@ -1,8 +1,8 @@
 use crate::{instructions::Expression, types::GlobalType};
-use wasm_core::values::Parse;
-use wasm_derive::Parse;
+use wasm_core::values::{Encode, Parse};
+use wasm_derive::{Encode, Parse};
 
-#[derive(Parse, Debug)]
+#[derive(Parse, Encode, Debug)]
 pub struct Global {
     pub global_type: GlobalType,
     pub expression: Expression,
@@ -1,20 +1,30 @@
-use wasm_core::values::{Name, Parse};
+use wasm_core::values::{Encode, Name, Parse};
 
-use crate::types::{GlobalType, MemType, TableType};
+use crate::types::{GlobalType, MemType, TableType, TagType};
 
 use super::indices;
-use wasm_derive::Parse;
+use wasm_derive::{Encode, Parse};
 
-#[derive(Parse, Debug)]
-pub struct Import {
-    pub module: Name,
-    pub name: Name,
+#[derive(Parse, Encode, Debug)]
+pub struct Import<'a> {
+    pub module: Name<'a>,
+    pub name: Name<'a>,
     pub descriptor: ImportDescriptor,
 }
-#[derive(Parse, Debug)]
+impl Import<'_> {
+    pub fn into_owned(self) -> Import<'static> {
+        Import {
+            module: self.module.into_owned(),
+            name: self.name.into_owned(),
+            descriptor: self.descriptor,
+        }
+    }
+}
+#[derive(Parse, Encode, Debug)]
 pub enum ImportDescriptor {
     Func(indices::TypeIdx),
     Table(TableType),
     Mem(MemType),
     Global(GlobalType),
+    Tag(TagType),
 }
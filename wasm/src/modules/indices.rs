@@ -7,3 +7,4 @@ pub type ElemIdx = u32;
 pub type DataIdx = u32;
 pub type LocalIdx = u32;
 pub type LabelIdx = u32;
+pub type TagIdx = u32;
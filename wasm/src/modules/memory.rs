@@ -1,8 +1,8 @@
 use crate::types::MemType;
-use wasm_core::values::Parse;
-use wasm_derive::Parse;
+use wasm_core::values::{Encode, Parse};
+use wasm_derive::{Encode, Parse};
 
-#[derive(Parse, Debug)]
+#[derive(Parse, Encode, Debug)]
 pub struct Memory {
-    memory_type: MemType,
+    pub memory_type: MemType,
 }
@@ -0,0 +1,162 @@
+use super::CustomSection;
+use nom::{bytes::complete::take, combinator::all_consuming};
+use wasm_core::values::{Encode, Name, Parse};
+use wasm_derive::{Encode, Parse};
+
+#[derive(Parse, Encode, Debug, Clone)]
+pub struct NameAssoc<'a> {
+    pub index: u32,
+    pub name: Name<'a>,
+}
+///names of an index space, in increasing index order
+#[derive(Parse, Encode, Debug, Clone, Default)]
+pub struct NameMap<'a>(pub Vec<NameAssoc<'a>>);
+impl<'a> NameMap<'a> {
+    pub fn get(&self, index: u32) -> Option<&Name<'a>> {
+        self.0
+            .iter()
+            .find(|assoc| assoc.index == index)
+            .map(|assoc| &assoc.name)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+#[derive(Parse, Encode, Debug, Clone)]
+pub struct IndirectNameAssoc<'a> {
+    pub index: u32,
+    pub names: NameMap<'a>,
+}
+///names of index spaces local to each function
+#[derive(Parse, Encode, Debug, Clone, Default)]
+pub struct IndirectNameMap<'a>(pub Vec<IndirectNameAssoc<'a>>);
+impl<'a> IndirectNameMap<'a> {
+    ///the names local to `index`
+    pub fn names(&self, index: u32) -> Option<&NameMap<'a>> {
+        self.0
+            .iter()
+            .find(|assoc| assoc.index == index)
+            .map(|assoc| &assoc.names)
+    }
+    pub fn get(&self, index: u32, local: u32) -> Option<&Name<'a>> {
+        self.names(index)?.get(local)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+///the `name` custom section, with the subsections of the extended name section proposal
+#[derive(Debug, Clone, Default)]
+pub struct NameSection<'a> {
+    pub module: Option<Name<'a>>,
+    pub funcs: NameMap<'a>,
+    pub locals: IndirectNameMap<'a>,
+    ///labels are numbered by the order of their blocks in each function, `if`, `try_table` and
+    ///legacy `try` included
+    pub labels: IndirectNameMap<'a>,
+    pub types: NameMap<'a>,
+    pub tables: NameMap<'a>,
+    pub memories: NameMap<'a>,
+    pub globals: NameMap<'a>,
+    pub elems: NameMap<'a>,
+    pub datas: NameMap<'a>,
+    pub tags: NameMap<'a>,
+}
+impl NameSection<'_> {
+    pub const NAME: &'static [u8] = b"name";
+}
+
+///parses the whole of `content` as a `T`
+fn subsection<'a, T: Parse<'a>, E>(content: &'a [u8]) -> nom::IResult<&'a [u8], T, E>
+where
+    E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
+{
+    all_consuming(T::parse)(content)
+}
+impl<'a> Parse<'a> for NameSection<'a> {
+    ///unknown subsections are skipped
+    fn parse<E>(mut i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
+    where
+        E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
+    {
+        let mut names = Self::default();
+        while !i.is_empty() {
+            let (rest, id) = u8::parse(i)?;
+            let (rest, size) = u32::parse(rest)?;
+            let (rest, content) = take(size as usize)(rest)?;
+            match id {
+                0 => names.module = Some(subsection(content)?.1),
+                1 => names.funcs = subsection(content)?.1,
+                2 => names.locals = subsection(content)?.1,
+                3 => names.labels = subsection(content)?.1,
+                4 => names.types = subsection(content)?.1,
+                5 => names.tables = subsection(content)?.1,
+                6 => names.memories = subsection(content)?.1,
+                7 => names.globals = subsection(content)?.1,
+                8 => names.elems = subsection(content)?.1,
+                9 => names.datas = subsection(content)?.1,
+                11 => names.tags = subsection(content)?.1,
+                _ => {}
+            }
+            i = rest;
+        }
+        Ok((i, names))
+    }
+}
+impl Encode for NameSection<'_> {
+    ///empty subsections are left out
+    fn encode(&self, out: &mut Vec<u8>) {
+        let mut subsection = |id: u8, content: Vec<u8>| {
+            id.encode(out);
+            (content.len() as u32).encode(out);
+            out.extend_from_slice(&content);
+        };
+        if let Some(module) = &self.module {
+            subsection(0, module.to_bytes());
+        }
+        let subsections = [
+            (1, self.funcs.is_empty(), &self.funcs as &dyn Encode),
+            (2, self.locals.is_empty(), &self.locals),
+            (3, self.labels.is_empty(), &self.labels),
+            (4, self.types.is_empty(), &self.types),
+            (5, self.tables.is_empty(), &self.tables),
+            (6, self.memories.is_empty(), &self.memories),
+            (7, self.globals.is_empty(), &self.globals),
+            (8, self.elems.is_empty(), &self.elems),
+            (9, self.datas.is_empty(), &self.datas),
+            (11, self.tags.is_empty(), &self.tags),
+        ];
+        for (id, empty, map) in subsections {
+            if !empty {
+                subsection(id, map.to_bytes());
+            }
+        }
+    }
+}
+impl From<&NameSection<'_>> for CustomSection<'static> {
+    fn from(names: &NameSection) -> Self {
+        Self {
+            name: NameSection::NAME.to_vec().into(),
+            data: names.to_bytes().into(),
+        }
+    }
+}
+
+#[test]
+fn decode_fixture() {
+    use super::{Module, Section};
+    use nom::error::VerboseError;
+    let file = &include_bytes!("../loop.wasm")[..];
+    let (_, module) = Module::parse::<VerboseError<_>>(file).unwrap();
+    let names = module.names().unwrap();
+    assert!(names.module.is_none() && names.funcs.is_empty());
+    assert_eq!(names.locals.get(0, 0).unwrap().as_bytes(), b"length");
+    assert_eq!(names.locals.get(0, 2).unwrap().to_string(), "sym");
+    assert!(names.locals.get(0, 3).is_none());
+    let custom = module.sections.iter().find_map(|section| match section {
+        Section::CustomSection(custom) => Some(custom),
+        _ => None,
+    });
+    assert_eq!(names.to_bytes(), &custom.unwrap().data[..]);
+}
@@ -1,8 +1,8 @@
 use crate::types::TableType;
-use wasm_core::values::Parse;
-use wasm_derive::Parse;
+use wasm_core::values::{Encode, Parse};
+use wasm_derive::{Encode, Parse};
 
-#[derive(Parse, Debug)]
+#[derive(Parse, Encode, Debug)]
 pub struct Table {
-    table_type: TableType,
+    pub table_type: TableType,
 }
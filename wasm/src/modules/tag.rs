@@ -0,0 +1,8 @@
+use crate::types::TagType;
+use wasm_core::values::{Encode, Parse};
+use wasm_derive::{Encode, Parse};
+
+#[derive(Parse, Encode, Debug)]
+pub struct Tag {
+    pub tag_type: TagType,
+}
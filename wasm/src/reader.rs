@@ -0,0 +1,185 @@
+//!pull based reading of the sections of a module, decoding only the ones asked for
+use crate::{
+    error::{Error, SECTION_SIZE_MISMATCH, UNKNOWN_SECTION_ID},
+    modules::{
+        misplaced, sized, Magic, ParseOptions, Section, SectionOrder, Version, LAST_SECTION_ID,
+    },
+};
+use nom::{
+    bytes::complete::take,
+    combinator::verify,
+    error::{context, ContextError, ErrorKind, ParseError, VerboseError},
+    sequence::tuple,
+};
+use std::ops::Range;
+use wasm_core::values::Parse;
+
+///iterates over the sections of a module without decoding them, stopping after the first
+///error
+#[derive(Debug, Clone)]
+pub struct ModuleReader<'a> {
+    input: &'a [u8],
+    ///the sections not read yet
+    rest: &'a [u8],
+    options: ParseOptions,
+    last_rank: Option<u8>,
+}
+///a section found by [ModuleReader], decoded on demand
+#[derive(Debug, Clone)]
+pub struct SectionReader<'a> {
+    pub id: u8,
+    ///bytes of the section in the module, header included
+    pub range: Range<usize>,
+    ///bytes of the contents in the module
+    pub contents: Range<usize>,
+    input: &'a [u8],
+    lazy_code: bool,
+}
+
+impl<'a> ModuleReader<'a> {
+    ///checks the header of the module
+    pub fn new(bytes: &'a [u8]) -> Result<Self, Error> {
+        Self::new_with(bytes, ParseOptions::default())
+    }
+    ///[ParseOptions::lazy_code] applies to [SectionReader::section]
+    pub fn new_with(bytes: &'a [u8], options: ParseOptions) -> Result<Self, Error> {
+        let header = tuple((Magic::parse, Version::parse))(bytes);
+        let (rest, _) = header.map_err(|error| Error::from_nom(bytes, error))?;
+        Ok(Self {
+            input: bytes,
+            rest,
+            options,
+            last_rank: None,
+        })
+    }
+    fn read(&mut self) -> Result<SectionReader<'a>, Error> {
+        let (input, i) = (self.input, self.rest);
+        let offset = |rest: &[u8]| input.len() - rest.len();
+        let mut header = tuple((
+            context(
+                UNKNOWN_SECTION_ID,
+                verify(u8::parse, |id| *id <= LAST_SECTION_ID),
+            ),
+            u32::parse,
+        ));
+        let parsed = header(i).and_then(|(contents, (id, length))| {
+            let (rest, _) = take(length as usize)(contents)?;
+            if self.options.order == SectionOrder::Strict {
+                if let Some(misplaced) = misplaced(&mut self.last_rank, id) {
+                    let error = VerboseError::from_error_kind(i, ErrorKind::Verify);
+                    return Err(nom::Err::Error(VerboseError::add_context(
+                        i, misplaced, error,
+                    )));
+                }
+            }
+            Ok((rest, (id, contents)))
+        });
+        let (rest, (id, contents)) = parsed.map_err(|error| Error::from_nom(input, error))?;
+        self.rest = rest;
+        Ok(SectionReader {
+            id,
+            range: offset(i)..offset(rest),
+            contents: offset(contents)..offset(rest),
+            input,
+            lazy_code: self.options.lazy_code,
+        })
+    }
+}
+impl<'a> Iterator for ModuleReader<'a> {
+    type Item = Result<SectionReader<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rest.is_empty() {
+            return None;
+        }
+        let section = self.read();
+        if section.is_err() {
+            self.rest = &[];
+        }
+        Some(section)
+    }
+}
+
+impl<'a> SectionReader<'a> {
+    ///the bytes of the contents
+    pub fn bytes(&self) -> &'a [u8] {
+        &self.input[self.contents.clone()]
+    }
+    ///decodes the contents as `T`, which must be the type of this section, like
+    ///[ExportSection](crate::modules::ExportSection) for id 7. code entries have ranges in the
+    ///contents, [SectionReader::section] moves them to the module
+    pub fn decode<T: Parse<'a>>(&self) -> Result<T, Error> {
+        let start = &self.input[self.range.start..];
+        let i = &self.input[self.contents.start..];
+        let length = (self.contents.end - self.contents.start) as u32;
+        match sized(start, i, length, SECTION_SIZE_MISMATCH, T::parse) {
+            Ok((_, contents)) => Ok(contents),
+            Err(error) => Err(Error::from_nom(self.input, error)),
+        }
+    }
+    ///decodes the whole section, with ranges in the module
+    pub fn section(&self) -> Result<Section<'a>, Error> {
+        let i = &self.input[self.range.start..];
+        match Section::parse_with::<VerboseError<_>>(i, self.lazy_code) {
+            Ok((_, mut section)) => {
+                section.shift(self.range.start);
+                Ok(section)
+            }
+            Err(error) => Err(Error::from_nom(self.input, error)),
+        }
+    }
+}
+
+#[test]
+fn sections() {
+    use crate::{
+        error::ErrorKind,
+        fixtures::{module_bytes, HEADER},
+        modules::{ExportSection, Module},
+    };
+    let file = include_bytes!("loop.wasm");
+    let module = Module::from_bytes(file).unwrap();
+    let sections: Vec<_> = ModuleReader::new(file)
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    let ids: Vec<_> = module.sections.iter().map(Section::id).collect();
+    assert_eq!(sections.iter().map(|sec| sec.id).collect::<Vec<_>>(), ids);
+    let ranges: Vec<_> = sections.iter().map(|sec| sec.range.clone()).collect();
+    assert_eq!(ranges, module.section_ranges);
+
+    let exports = sections.iter().find(|sec| sec.id == 7).unwrap();
+    let ExportSection(exports) = exports.decode().unwrap();
+    let names: Vec<_> = exports
+        .iter()
+        .map(|export| export.name.as_bytes())
+        .collect();
+    assert_eq!(names, [&b"memory"[..], b"upper"]);
+    let code = sections.iter().find(|sec| sec.id == 10).unwrap();
+    let code_ranges = |section: &Section| match section {
+        Section::CodeSection(code) => Some(code.0[0].range.clone()),
+        _ => None,
+    };
+    let expected = module.sections.iter().find_map(code_ranges);
+    assert_eq!(code_ranges(&code.section().unwrap()), expected);
+
+    let module = |sections: &[u8]| module_bytes(&[sections]);
+    let error = |bytes: &[u8]| {
+        let errors = ModuleReader::new(bytes).unwrap().filter_map(Result::err);
+        errors.collect::<Vec<_>>()
+    };
+    let order = error(&module(&[0x03, 0x01, 0x00, 0x01, 0x01, 0x00]));
+    assert_eq!(order[0].kind, ErrorKind::SectionOutOfOrder(1));
+    assert_eq!(order[0].offset, HEADER.len() + 3);
+    let truncated = error(&module(&[0x01, 0x05, 0x01]));
+    assert_eq!(truncated[0].kind, ErrorKind::UnexpectedEof);
+    let longer = ModuleReader::new(&module(&[0x07, 0x02, 0x00, 0x00]))
+        .unwrap()
+        .map(|sec| sec.unwrap().decode::<ExportSection>().unwrap_err())
+        .next()
+        .unwrap();
+    let size = ErrorKind::SectionSizeMismatch { id: 7, declared: 2 };
+    assert_eq!((longer.kind, longer.offset), (size, HEADER.len()));
+    let header = ModuleReader::new(b"\0asm").err().unwrap();
+    assert_eq!(header.kind, ErrorKind::UnexpectedEof);
+}
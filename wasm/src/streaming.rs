@@ -0,0 +1,350 @@
+//!push based parsing of modules arriving in chunks
+use crate::{
+    error::{Error, ErrorKind, UNKNOWN_SECTION_ID},
+    modules::{code::Code, misplaced, Magic, Section, Version, LAST_SECTION_ID},
+};
+use nom::{
+    bytes::streaming::take,
+    error::{ContextError, ErrorKind as NomErrorKind, ParseError, VerboseError},
+    number::streaming::u8 as byte,
+    sequence::tuple,
+    IResult,
+};
+use std::ops::Range;
+use wasm_core::values::Parse;
+
+///what [StreamParser::next_event] found, with ranges in the whole module
+#[derive(Debug)]
+pub enum Event {
+    ///the magic number and version were read
+    Header,
+    ///a complete section other than the code section
+    Section(Section<'static>, Range<usize>),
+    ///the start of the code section, its `count` entries follow as [Event::Code]
+    CodeSectionStart {
+        count: u32,
+        range: Range<usize>,
+    },
+    Code(Code),
+    ///the buffered bytes don't hold the next event, more chunks must be pushed
+    NeedMoreData,
+    ///the module ended after a complete section, see [StreamParser::finish]
+    End,
+}
+
+#[derive(Debug)]
+enum State {
+    Header,
+    Sections,
+    Code {
+        remaining: u32,
+        start: usize,
+        end: usize,
+        declared: u32,
+    },
+    Done,
+}
+
+///accepts chunks of a module with [StreamParser::push] and yields its sections, and the
+///entries of its code section, as soon as they are complete.
+///sections must be in the order of the spec
+#[derive(Debug)]
+pub struct StreamParser {
+    buffer: Vec<u8>,
+    ///start of the unread bytes in `buffer`, the bytes before it are dropped in bulk
+    cursor: usize,
+    ///offset of the unread bytes in the module
+    offset: usize,
+    state: State,
+    last_rank: Option<u8>,
+    finished: bool,
+}
+impl Default for StreamParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+type Res<'a, T> = IResult<&'a [u8], T, VerboseError<&'a [u8]>>;
+///the bytes consumed from the buffer and the event, `None` if more data is needed
+type Step = Result<Option<(usize, Event)>, Error>;
+
+impl StreamParser {
+    pub fn new() -> Self {
+        Self {
+            buffer: vec![],
+            cursor: 0,
+            offset: 0,
+            state: State::Header,
+            last_rank: None,
+            finished: false,
+        }
+    }
+    pub fn push(&mut self, chunk: &[u8]) {
+        self.buffer.extend_from_slice(chunk);
+    }
+    ///no more chunks will be pushed, running out of data is then an error
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+    ///the next complete event, [Event::NeedMoreData] while it is incomplete.
+    ///after an error or [Event::End] only [Event::End] is returned
+    pub fn next_event(&mut self) -> Result<Event, Error> {
+        let step = match self.state {
+            State::Header => self.header(),
+            State::Sections if self.finished && self.pending().is_empty() => {
+                self.state = State::Done;
+                return Ok(Event::End);
+            }
+            State::Sections => self.section(),
+            State::Code { .. } => self.code(),
+            State::Done => return Ok(Event::End),
+        };
+        match step {
+            Ok(Some((consumed, event))) => {
+                self.consume(consumed);
+                Ok(event)
+            }
+            Ok(None) if self.finished => {
+                self.state = State::Done;
+                Err(Error {
+                    kind: ErrorKind::UnexpectedEof,
+                    offset: self.offset + self.pending().len(),
+                    context: vec![],
+                })
+            }
+            Ok(None) => Ok(Event::NeedMoreData),
+            Err(error) => {
+                self.state = State::Done;
+                Err(error)
+            }
+        }
+    }
+
+    ///the buffered bytes not read yet
+    fn pending(&self) -> &[u8] {
+        &self.buffer[self.cursor..]
+    }
+    ///moves past `consumed` bytes. they are only removed once they make up half of the
+    ///buffer, so that parsing stays linear in the size of the module
+    fn consume(&mut self, consumed: usize) {
+        self.cursor += consumed;
+        self.offset += consumed;
+        if self.cursor > self.buffer.len() / 2 {
+            self.buffer.drain(..self.cursor);
+            self.cursor = 0;
+        }
+    }
+
+    ///the bytes of `input`, a prefix of the buffer, read by `parsed`. `None` if they are
+    ///incomplete, unless `input` was supposed to hold all of them
+    fn read<T>(
+        &self,
+        input: &[u8],
+        parsed: Res<'_, T>,
+        complete: bool,
+    ) -> Result<Option<(usize, T)>, Error> {
+        match parsed {
+            Ok((rest, value)) => Ok(Some((input.len() - rest.len(), value))),
+            Err(nom::Err::Incomplete(_)) if !complete => Ok(None),
+            Err(error) => {
+                let mut error = Error::from_nom(input, error);
+                error.offset += self.offset;
+                Err(error)
+            }
+        }
+    }
+    ///the error marked by `marker` at the start of the unread bytes
+    fn fail(&self, marker: &'static str) -> Error {
+        let i = self.pending();
+        let error = VerboseError::from_error_kind(i, NomErrorKind::Verify);
+        let error = VerboseError::add_context(i, marker, error);
+        let mut error = Error::from_nom(i, nom::Err::Error(error));
+        error.offset += self.offset;
+        error
+    }
+
+    fn header(&mut self) -> Step {
+        let i = self.pending();
+        let header = match self.read(i, take(8_usize)(i), false)? {
+            Some((header, _)) => header,
+            None => return Ok(None),
+        };
+        let i = &self.pending()[..header];
+        self.read(i, tuple((Magic::parse, Version::parse))(i), true)?;
+        self.state = State::Sections;
+        Ok(Some((header, Event::Header)))
+    }
+    fn section(&mut self) -> Step {
+        let i = self.pending();
+        let (header, (id, declared)) = match self.read(i, tuple((byte, u32::parse))(i), false)? {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+        if id > LAST_SECTION_ID {
+            return Err(self.fail(UNKNOWN_SECTION_ID));
+        }
+        //only kept once the event is complete, it is checked again otherwise
+        let mut last_rank = self.last_rank;
+        if let Some(misplaced) = misplaced(&mut last_rank, id) {
+            return Err(self.fail(misplaced));
+        }
+        let start = self.offset;
+        let end = start + header + declared as usize;
+        if id == 10 {
+            let (consumed, count) =
+                match self.read(i, tuple((byte, u32::parse, u32::parse))(i), false)? {
+                    Some((consumed, (_, _, count))) => (consumed, count),
+                    None => return Ok(None),
+                };
+            self.last_rank = last_rank;
+            self.state = State::Code {
+                remaining: count,
+                start,
+                end,
+                declared,
+            };
+            if count == 0 {
+                self.end_code(start + consumed)?;
+            }
+            let range = start..end;
+            return Ok(Some((consumed, Event::CodeSectionStart { count, range })));
+        }
+        if self.pending().len() < end - start {
+            return Ok(None);
+        }
+        let i = &self.pending()[..end - start];
+        let (consumed, section) = match self.read(i, Section::parse(i), true)? {
+            Some(section) => section,
+            None => return Ok(None),
+        };
+        let section = section.into_owned();
+        self.last_rank = last_rank;
+        Ok(Some((consumed, Event::Section(section, start..end))))
+    }
+    fn code(&mut self) -> Step {
+        let (remaining, end) = match self.state {
+            State::Code { remaining, end, .. } => (remaining, end),
+            _ => unreachable!(),
+        };
+        let i = self.pending();
+        let parsed = u32::parse(i).and_then(|(rest, size)| take(size)(rest));
+        let length = match self.read(i, parsed, false)? {
+            Some((length, _)) => length,
+            //the entry can't end within the section
+            None if self.offset + i.len() >= end => return Err(self.size_mismatch()),
+            None => return Ok(None),
+        };
+        if self.offset + length > end {
+            return Err(self.size_mismatch());
+        }
+        let i = &self.pending()[..length];
+        let (length, mut code) = match self.read(i, Code::parse(i), true)? {
+            Some(code) => code,
+            None => return Ok(None),
+        };
+        code.shift(self.offset);
+        if let State::Code { remaining, .. } = &mut self.state {
+            *remaining -= 1;
+        }
+        if remaining == 1 {
+            self.end_code(self.offset + length)?;
+        }
+        Ok(Some((length, Event::Code(code))))
+    }
+    ///goes back to reading sections once the code entries end at `at`, which must be the
+    ///end of the code section
+    fn end_code(&mut self, at: usize) -> Result<(), Error> {
+        match self.state {
+            State::Code { end, .. } if at == end => {
+                self.state = State::Sections;
+                Ok(())
+            }
+            _ => Err(self.size_mismatch()),
+        }
+    }
+    fn size_mismatch(&self) -> Error {
+        let (start, declared) = match self.state {
+            State::Code {
+                start, declared, ..
+            } => (start, declared),
+            _ => unreachable!(),
+        };
+        Error {
+            kind: ErrorKind::SectionSizeMismatch { id: 10, declared },
+            offset: start,
+            context: vec![],
+        }
+    }
+}
+
+#[test]
+fn chunks() {
+    use crate::modules::Module;
+    let file = include_bytes!("loop.wasm");
+    let module = Module::from_bytes(file).unwrap();
+    let events = |chunk: usize, bytes: &[u8]| {
+        let mut parser = StreamParser::new();
+        let mut events = vec![];
+        for chunk in bytes.chunks(chunk) {
+            parser.push(chunk);
+            loop {
+                match parser.next_event() {
+                    Ok(Event::NeedMoreData) => break,
+                    event => events.push(event),
+                }
+            }
+        }
+        parser.finish();
+        events.push(parser.next_event());
+        events
+    };
+    for chunk in [1, 3, 64, file.len()] {
+        let events = events(chunk, file);
+        assert!(matches!(events[0], Ok(Event::Header)));
+        assert!(matches!(events.last(), Some(Ok(Event::End))));
+        let sections = events.iter().filter(|event| {
+            matches!(
+                event,
+                Ok(Event::Section(..)) | Ok(Event::CodeSectionStart { .. })
+            )
+        });
+        assert_eq!(sections.count(), module.sections.len());
+        let ranges: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                Ok(Event::Code(code)) => Some(code.range.clone()),
+                _ => None,
+            })
+            .collect();
+        let code = module.sections.iter().find_map(|section| match section {
+            Section::CodeSection(code) => Some(&code.0),
+            _ => None,
+        });
+        let expected: Vec<_> = code
+            .unwrap()
+            .iter()
+            .map(|code| code.range.clone())
+            .collect();
+        assert_eq!(ranges, expected);
+    }
+
+    let truncated = events(5, &file[..file.len() - 2]);
+    let error = truncated.last().unwrap().as_ref().unwrap_err();
+    assert_eq!(error.kind, ErrorKind::UnexpectedEof);
+    assert_eq!(error.offset, file.len() - 2);
+    //a byte after the last section starts another one, for every way of reading a module
+    let trailing = [&file[..], b"\n"].concat();
+    let streamed = events(5, &trailing).pop().unwrap().unwrap_err();
+    let read = crate::reader::ModuleReader::new(&trailing).unwrap().last();
+    assert_eq!(streamed, read.unwrap().unwrap_err());
+    assert_eq!(streamed, Module::from_bytes(&trailing).unwrap_err());
+    assert_eq!(streamed.kind, ErrorKind::UnexpectedEof);
+    let mut parser = StreamParser::new();
+    parser.push(&file[..4]);
+    assert!(matches!(parser.next_event(), Ok(Event::NeedMoreData)));
+    parser.push(b"\x02\0\0\0");
+    let version = parser.next_event().unwrap_err();
+    assert_eq!(version.kind, ErrorKind::UnsupportedVersion([2, 0, 0, 0]));
+    assert_eq!(version.offset, 4);
+}
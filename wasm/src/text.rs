@@ -0,0 +1,246 @@
+//!the webassembly text format, parsed into and printed from the same [Module] as the binary format
+use crate::modules::Module;
+use std::fmt;
+
+mod cursor;
+mod fields;
+mod instructions;
+mod keywords;
+mod lexer;
+mod numbers;
+mod printer;
+
+///an error at a position of the source text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextError {
+    ///1-based
+    pub line: usize,
+    ///1-based, counted in characters
+    pub column: usize,
+    pub message: String,
+}
+impl TextError {
+    fn new(source: &str, error: Error) -> Self {
+        let before = &source[..error.offset];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+        let column = before[line_start..].chars().count() + 1;
+        Self {
+            line,
+            column,
+            message: error.message,
+        }
+    }
+}
+impl fmt::Display for TextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+impl std::error::Error for TextError {}
+
+///error at a byte offset, resolved to a line and column once parsing stops
+#[derive(Debug)]
+struct Error {
+    offset: usize,
+    message: String,
+}
+type Result<T> = std::result::Result<T, Error>;
+
+///parses a `(module ...)`, or a sequence of bare module fields
+pub fn parse(source: &str) -> std::result::Result<Module<'static>, TextError> {
+    let parse = || {
+        let sexps = lexer::parse(source)?;
+        fields::module(&sexps)
+    };
+    parse().map_err(|error| TextError::new(source, error))
+}
+///renders a module in the text format, using the names of the `name` section
+pub fn print(module: &Module) -> String {
+    printer::module(module)
+}
+impl Module<'_> {
+    ///parses the text format, see [parse]
+    pub fn from_wat(source: &str) -> std::result::Result<Self, TextError> {
+        parse(source)
+    }
+    ///see [print]
+    pub fn to_wat(&self) -> String {
+        print(self)
+    }
+}
+
+#[test]
+fn loop_fixture() {
+    use wasm_core::values::Encode;
+    let wasm = &include_bytes!("loop.wasm")[..];
+    let module = parse(include_str!("loop.wat")).unwrap();
+    //loop.wasm ends with a name section, which the text format does not produce
+    let custom_start = wasm.len() - 0x1C;
+    assert_eq!(wasm[custom_start], 0x00);
+    assert_eq!(module.to_bytes(), &wasm[..custom_start]);
+}
+#[test]
+fn flat_and_folded() {
+    use crate::validation::validate;
+    let flat = r#"
+        (module
+          (type $binary (func (param i32 i32) (result i32)))
+          (import "env" "log" (func $log (param i32)))
+          (func $add (export "add") (type $binary)
+            local.get 0
+            local.get 1
+            i32.add)
+          (func (export "max") (param $a i32) (param $b i32) (result i32)
+            local.get $a
+            local.get $b
+            i32.gt_s
+            if (result i32) ;; pick the larger one
+              local.get $a
+            else
+              local.get $b
+            end)
+          (table 2 funcref)
+          (elem (i32.const 0) $add 1)
+          (global $counter (mut i32) (i32.const -1))
+          (memory 1)
+          (data (i32.const 8) "hi\00\u{1F600}")
+          (func (param i32)
+            (call_indirect (type $binary) (i32.const 1) (i32.const 2) (local.get 0))
+            (drop)
+            (global.set $counter (f32.const 0x1.8p1) (drop) (i32.const 3))
+            (block $exit (loop $top (br_if $exit (i32.eqz (local.get 0))) (br $top)))
+            (i32.store offset=4 align=2 (i32.const 0) (i32.const 42))
+            (call $log (i32.load8_u (i32.const 8)))))
+    "#;
+    let module = parse(flat).unwrap();
+    validate(&module).unwrap();
+    let folded = r#"
+        (type $binary (func (param i32 i32) (result i32)))
+        (func (export "add") (type $binary) (i32.add (local.get 0) (local.get 1)))
+    "#;
+    let bare = parse(folded).unwrap();
+    validate(&bare).unwrap();
+}
+#[test]
+fn imported_signatures() {
+    use wasm_core::values::Encode;
+    let module = parse(
+        r#"(module
+          (import "env" "log" (func (param i32 f64) (result i64)))
+          (func (param i32 f64) (result i64) i64.const 0))"#,
+    )
+    .unwrap();
+    let printed = print(&module);
+    assert!(printed
+        .contains(r#"(import "env" "log" (func (;0;) (type 0) (param i32 f64) (result i64)))"#));
+    assert!(printed.contains("(func (;1;) (type 0) (param i32 f64) (result i64)"));
+    assert_eq!(parse(&printed).unwrap().to_bytes(), module.to_bytes());
+}
+#[test]
+fn errors() {
+    let error = parse("(module\n  (func (local.get $missing)))").unwrap_err();
+    assert_eq!(error.to_string(), "2:20: unknown local $missing");
+    let error = parse("(module (func i32.const 0x1_0000_0000))").unwrap_err();
+    assert_eq!(error.to_string(), "1:25: invalid i32 literal 0x1_0000_0000");
+    let error = parse("(module (func $f) (func $f))").unwrap_err();
+    assert_eq!(error.to_string(), "1:25: duplicate function $f");
+    let error = parse("(module (func block $a end $b))").unwrap_err();
+    assert_eq!(error.to_string(), "1:28: mismatched label $b, expected $a");
+    let error = parse("(module \"unterminated)").unwrap_err();
+    assert_eq!(error.to_string(), "1:9: unterminated string");
+}
+#[test]
+fn print_fixtures() {
+    use crate::modules::Section;
+    use nom::error::VerboseError;
+    use wasm_core::values::{Encode, Parse};
+    let loop_file = &include_bytes!("loop.wasm")[..];
+    for file in [loop_file, &include_bytes!("if.wasm")[..]] {
+        let (_, mut module) = Module::parse::<VerboseError<_>>(file).unwrap();
+        let text = module.to_wat();
+        let reparsed = Module::from_wat(&text).unwrap();
+        module
+            .sections
+            .retain(|section| !matches!(section, Section::CustomSection(_)));
+        assert_eq!(reparsed.to_bytes(), module.to_bytes());
+    }
+    let (_, module) = Module::parse::<VerboseError<_>>(loop_file).unwrap();
+    let text = module.to_wat();
+    assert!(
+        text.contains("(func (;0;) (type 0) (param $length i32) (local $i i32) (local $sym i32)\n")
+    );
+    assert!(text.contains("\n        i32.load8_u\n        local.set $sym\n"));
+}
+#[test]
+fn print_immediates() {
+    use wasm_core::values::Encode;
+    let source = r#"
+        (module
+          (memory 1)
+          (func (result f64)
+            i32.const 0
+            i64.load32_u offset=8 align=4
+            drop
+            i32.const 0
+            i32.load align=1
+            drop
+            f32.const -nan:0x7fffff
+            drop
+            f64.const 0x0.fffffffffffffp-1022))
+    "#;
+    let module = parse(source).unwrap();
+    let text = module.to_wat();
+    assert!(text.contains("i64.load32_u offset=8\n"));
+    assert!(text.contains("i32.load align=1\n"));
+    assert!(text.contains("f32.const -nan:0x7fffff\n"));
+    assert!(text.contains("f64.const 0x1.ffffffffffffep-1023)"));
+    assert_eq!(parse(&text).unwrap().to_bytes(), module.to_bytes());
+}
+#[test]
+fn print_names() {
+    use crate::modules::{
+        names::{IndirectNameAssoc, IndirectNameMap, NameAssoc, NameMap, NameSection},
+        Section,
+    };
+    use wasm_core::values::Encode;
+    let source = r#"
+        (type (func (param i32)))
+        (global (mut i32) (i32.const 0))
+        (func (type 0)
+          block
+            local.get 0
+            br_if 0
+            global.get 0
+            local.set 0
+          end)
+    "#;
+    let mut module = parse(source).unwrap();
+    let map = |name: &str| {
+        let name = name.as_bytes().to_vec().into();
+        NameMap(vec![NameAssoc { index: 0, name }])
+    };
+    let indirect = |name: &str| {
+        let names = map(name);
+        IndirectNameMap(vec![IndirectNameAssoc { index: 0, names }])
+    };
+    let names = NameSection {
+        funcs: map("main"),
+        locals: indirect("count"),
+        labels: indirect("done"),
+        types: map("unary"),
+        globals: map("total value"),
+        ..NameSection::default()
+    };
+    let bytes = module.to_bytes();
+    module
+        .sections
+        .push(Section::CustomSection((&names).into()));
+    let text = module.to_wat();
+    assert!(text.contains("(type $unary (;0;) (func (param i32)))"));
+    assert!(text.contains("(global $total_value (;0;) (mut i32) (i32.const 0))"));
+    assert!(text.contains("(func $main (;0;) (type $unary) (param $count i32)"));
+    assert!(text.contains("block $done\n      local.get $count\n      br_if $done\n"));
+    assert!(text.contains("global.get $total_value\n"));
+    assert_eq!(parse(&text).unwrap().to_bytes(), bytes);
+}
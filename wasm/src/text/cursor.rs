@@ -0,0 +1,283 @@
+use super::{
+    lexer::{Atom, Sexp},
+    numbers, Error, Result,
+};
+use crate::{
+    instructions::simd::{LaneIdx, V128},
+    types::{NumType, RefType, ValueType},
+};
+use std::collections::HashMap;
+
+///walks the items of a list
+#[derive(Clone)]
+pub(crate) struct Cursor<'s, 'a> {
+    items: &'s [Sexp<'a>],
+    position: usize,
+    ///offset of the list itself, reported once the items run out
+    offset: usize,
+}
+impl<'s, 'a> Cursor<'s, 'a> {
+    pub fn new(items: &'s [Sexp<'a>], offset: usize) -> Self {
+        Self {
+            items,
+            position: 0,
+            offset,
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.position == self.items.len()
+    }
+    ///offset of the next item
+    pub fn offset(&self) -> usize {
+        self.peek().map_or(self.offset, Sexp::offset)
+    }
+    pub fn error<T>(&self, message: impl Into<String>) -> Result<T> {
+        Err(Error {
+            offset: self.offset(),
+            message: message.into(),
+        })
+    }
+    pub fn peek(&self) -> Option<&'s Sexp<'a>> {
+        self.items.get(self.position)
+    }
+    ///fails unless every item was consumed
+    pub fn finish(&self) -> Result<()> {
+        match self.peek() {
+            None => Ok(()),
+            Some(Sexp::List(..)) => self.error("unexpected list"),
+            Some(Sexp::Atom(..)) => self.error("unexpected token"),
+        }
+    }
+
+    pub fn peek_keyword(&self) -> Option<&'a str> {
+        self.peek_keyword_at(0)
+    }
+    ///the keyword `n` places ahead
+    pub fn peek_keyword_at(&self, n: usize) -> Option<&'a str> {
+        match self.items.get(self.position + n) {
+            Some(Sexp::Atom(Atom::Keyword(keyword), _)) => Some(keyword),
+            _ => None,
+        }
+    }
+    pub fn keyword(&mut self) -> Result<&'a str> {
+        match self.peek_keyword() {
+            Some(keyword) => {
+                self.position += 1;
+                Ok(keyword)
+            }
+            None => self.error("expected a keyword"),
+        }
+    }
+    ///consumes `keyword` if it is next
+    pub fn take_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.peek_keyword() == Some(keyword);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+    pub fn peek_id(&self) -> Option<&'a str> {
+        match self.peek() {
+            Some(Sexp::Atom(Atom::Id(id), _)) => Some(id),
+            _ => None,
+        }
+    }
+    ///an optional `$id`
+    pub fn id(&mut self) -> Option<&'a str> {
+        let id = self.peek_id()?;
+        self.position += 1;
+        Some(id)
+    }
+    pub fn string(&mut self) -> Result<Vec<u8>> {
+        match self.peek() {
+            Some(Sexp::Atom(Atom::String(string), _)) => {
+                self.position += 1;
+                Ok(string.clone())
+            }
+            _ => self.error("expected a string"),
+        }
+    }
+    ///keyword at the head of the next item, if it is a list
+    pub fn peek_list(&self) -> Option<&'a str> {
+        match self.peek() {
+            Some(Sexp::List(items, _)) => match items.first() {
+                Some(Sexp::Atom(Atom::Keyword(keyword), _)) => Some(keyword),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+    ///consumes the next item if it is a list headed by `keyword`, returning a cursor past it
+    pub fn list(&mut self, keyword: &str) -> Option<Cursor<'s, 'a>> {
+        if self.peek_list() != Some(keyword) {
+            return None;
+        }
+        let mut list = self.next_list()?;
+        list.position = 1;
+        Some(list)
+    }
+    ///consumes the next item if it is a list
+    pub fn next_list(&mut self) -> Option<Cursor<'s, 'a>> {
+        match self.peek() {
+            Some(Sexp::List(items, offset)) => {
+                self.position += 1;
+                Some(Cursor::new(items, *offset))
+            }
+            _ => None,
+        }
+    }
+
+    ///a numeric literal
+    fn number<T>(&mut self, kind: &str, parse: impl FnOnce(&str) -> Option<T>) -> Result<T> {
+        let text = match self.peek_keyword() {
+            Some(text) => text,
+            None => return self.error(format!("expected {} literal", kind)),
+        };
+        match parse(text) {
+            Some(value) => {
+                self.position += 1;
+                Ok(value)
+            }
+            None => self.error(format!("invalid {} literal {}", kind, text)),
+        }
+    }
+    pub fn u32(&mut self) -> Result<u32> {
+        self.number("u32", numbers::u32)
+    }
+    pub fn u64(&mut self) -> Result<u64> {
+        self.number("u64", numbers::u64)
+    }
+    pub fn i32(&mut self) -> Result<i32> {
+        self.number("i32", numbers::i32)
+    }
+    pub fn i64(&mut self) -> Result<i64> {
+        self.number("i64", numbers::i64)
+    }
+    pub fn f32(&mut self) -> Result<f32> {
+        self.number("f32", numbers::f32)
+    }
+    pub fn f64(&mut self) -> Result<f64> {
+        self.number("f64", numbers::f64)
+    }
+    ///a lane index below `lanes`
+    pub fn lane(&mut self, lanes: u8) -> Result<LaneIdx> {
+        let lane = |text: &str| numbers::u32(text).filter(|lane| *lane < lanes as u32);
+        self.number("lane index", lane).map(|lane| lane as LaneIdx)
+    }
+    ///the shape and lanes of a `v128.const`
+    pub fn v128(&mut self) -> Result<V128> {
+        let shape = match self.peek_keyword() {
+            Some(shape @ ("i8x16" | "i16x8" | "i32x4" | "i64x2" | "f32x4" | "f64x2")) => shape,
+            _ => return self.error("expected a vector shape"),
+        };
+        self.position += 1;
+        let mut bytes = Vec::with_capacity(16);
+        while bytes.len() < 16 {
+            match shape {
+                "i8x16" => bytes.push(self.number("i8", |text| numbers::narrow(text, 8))? as u8),
+                "i16x8" => {
+                    let lane = self.number("i16", |text| numbers::narrow(text, 16))?;
+                    bytes.extend((lane as u16).to_le_bytes())
+                }
+                "i32x4" => bytes.extend(self.i32()?.to_le_bytes()),
+                "i64x2" => bytes.extend(self.i64()?.to_le_bytes()),
+                "f32x4" => bytes.extend(self.f32()?.to_bits().to_le_bytes()),
+                _ => bytes.extend(self.f64()?.to_bits().to_le_bytes()),
+            }
+        }
+        let mut v128 = [0; 16];
+        v128.copy_from_slice(&bytes);
+        Ok(V128(v128))
+    }
+    ///true if the next item is a numeric index or an `$id`
+    pub fn peek_index(&self) -> bool {
+        self.peek_index_at(0)
+    }
+    ///true if the item `n` places ahead is a numeric index or an `$id`
+    pub fn peek_index_at(&self, n: usize) -> bool {
+        match self.items.get(self.position + n) {
+            Some(Sexp::Atom(Atom::Id(_), _)) => true,
+            Some(Sexp::Atom(Atom::Keyword(keyword), _)) => numbers::u32(keyword).is_some(),
+            _ => false,
+        }
+    }
+    ///a numeric index or an `$id` resolved in `space`
+    pub fn index(&mut self, space: &Space<'a>) -> Result<u32> {
+        if let Some(Sexp::Atom(Atom::Id(id), _)) = self.peek() {
+            let index = match space.names.get(id) {
+                Some(index) => *index,
+                None => return self.error(format!("unknown {} ${}", space.kind, id)),
+            };
+            self.position += 1;
+            return Ok(index);
+        }
+        self.u32()
+    }
+
+    pub fn ref_type(&mut self) -> Result<RefType> {
+        match self.peek_keyword() {
+            Some("funcref") => {
+                self.position += 1;
+                Ok(RefType::FuncRef)
+            }
+            Some("externref") => {
+                self.position += 1;
+                Ok(RefType::ExternRef)
+            }
+            Some("exnref") => {
+                self.position += 1;
+                Ok(RefType::ExnRef)
+            }
+            _ => self.error("expected a reference type"),
+        }
+    }
+    pub fn value_type(&mut self) -> Result<ValueType> {
+        let num_type = match self.peek_keyword() {
+            Some("i32") => NumType::I32,
+            Some("i64") => NumType::I64,
+            Some("f32") => NumType::F32,
+            Some("f64") => NumType::F64,
+            Some("v128") => {
+                self.position += 1;
+                return Ok(ValueType::V128);
+            }
+            _ => return self.ref_type().map(ValueType::RefType),
+        };
+        self.position += 1;
+        Ok(ValueType::NumType(num_type))
+    }
+}
+
+///names and size of an index space
+pub(crate) struct Space<'a> {
+    ///used in error messages
+    kind: &'static str,
+    names: HashMap<&'a str, u32>,
+    pub len: u32,
+    ///entries coming from imports, which precede the defined ones
+    pub imported: u32,
+}
+impl<'a> Space<'a> {
+    pub fn new(kind: &'static str) -> Self {
+        Self {
+            kind,
+            names: HashMap::new(),
+            len: 0,
+            imported: 0,
+        }
+    }
+    ///appends an entry, `offset` locates a duplicate name
+    pub fn push(&mut self, name: Option<&'a str>, offset: usize) -> Result<u32> {
+        let index = self.len;
+        if let Some(name) = name {
+            if self.names.insert(name, index).is_some() {
+                return Err(Error {
+                    offset,
+                    message: format!("duplicate {} ${}", self.kind, name),
+                });
+            }
+        }
+        self.len += 1;
+        Ok(index)
+    }
+}
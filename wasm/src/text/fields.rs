@@ -0,0 +1,811 @@
+use super::{
+    cursor::{Cursor, Space},
+    instructions::Scope,
+    lexer::Sexp,
+    numbers, Error, Result,
+};
+use crate::{
+    instructions::{control::BlockType, numeric::NumericInstruction, Expression, Instruction},
+    modules::{
+        code::{Code, Func, Local},
+        data::Data,
+        element::{Elem, ElementKind},
+        export::{Export, ExportDescriptor},
+        global::Global,
+        import::{Import, ImportDescriptor},
+        indices::{FuncIdx, TableIdx, TypeIdx},
+        memory::Memory,
+        table::Table,
+        tag::Tag,
+        CodeSection, DataCountSection, DataSection, ElementSection, ExportSection, FunctionSection,
+        GlobalSection, ImportSection, Magic, MemorySection, Module, Section, StartSection,
+        TableSection, TagSection, TypeSection, Version,
+    },
+    types::{
+        FuncType, GlobalType, IndexType, Limit, MemType, Mutability, RefType, TableType, TagType,
+        ValueType,
+    },
+    Suffix,
+};
+use wasm_core::values::Encode;
+
+const PAGE_SIZE: usize = 0x10000;
+
+///index spaces of the module, filled before any field is converted
+pub(crate) struct Context<'a> {
+    ///explicit types first, then the ones implied by inline signatures
+    pub types: Vec<FuncType>,
+    pub type_names: Space<'a>,
+    pub funcs: Space<'a>,
+    pub tables: Space<'a>,
+    pub memories: Space<'a>,
+    pub globals: Space<'a>,
+    pub tags: Space<'a>,
+    pub elems: Space<'a>,
+    pub datas: Space<'a>,
+    ///set by instructions that need the data count section
+    pub uses_data_count: bool,
+}
+
+///name and offset of each param, when declared inline
+pub(crate) type ParamNames<'a> = Vec<(Option<&'a str>, usize)>;
+
+///`(param ...)*` and `(result ...)*`
+struct Signature<'a> {
+    params: Vec<ValueType>,
+    names: ParamNames<'a>,
+    results: Vec<ValueType>,
+}
+fn signature<'a>(c: &mut Cursor<'_, 'a>) -> Result<Signature<'a>> {
+    let mut signature = Signature {
+        params: vec![],
+        names: vec![],
+        results: vec![],
+    };
+    while let Some(mut param) = c.list("param") {
+        let offset = param.offset();
+        if let Some(id) = param.id() {
+            signature.params.push(param.value_type()?);
+            signature.names.push((Some(id), offset));
+            param.finish()?;
+        }
+        while !param.is_empty() {
+            signature.params.push(param.value_type()?);
+            signature.names.push((None, offset));
+        }
+    }
+    while let Some(mut result) = c.list("result") {
+        while !result.is_empty() {
+            signature.results.push(result.value_type()?);
+        }
+    }
+    Ok(signature)
+}
+
+fn limits(c: &mut Cursor, index_type: IndexType) -> Result<Limit> {
+    let bound = |c: &mut Cursor| match index_type {
+        IndexType::I32 => c.u32().map(u64::from),
+        IndexType::I64 => c.u64(),
+    };
+    let min = bound(c)?;
+    let max = match c.peek_keyword().and_then(numbers::u64) {
+        Some(_) => Some(bound(c)?),
+        None => None,
+    };
+    Ok(Limit {
+        min,
+        max,
+        index_type,
+    })
+}
+///the optional index type of a memory, `i32` by default
+fn index_type(c: &mut Cursor) -> IndexType {
+    match c.take_keyword("i64") {
+        true => IndexType::I64,
+        false => {
+            c.take_keyword("i32");
+            IndexType::I32
+        }
+    }
+}
+fn mem_type(c: &mut Cursor, index_type: IndexType) -> Result<MemType> {
+    let lim = limits(c, index_type)?;
+    let shared = c.take_keyword("shared");
+    Ok(MemType { lim, shared })
+}
+fn table_type(c: &mut Cursor) -> Result<TableType> {
+    let lim = limits(c, IndexType::I32)?;
+    let et = c.ref_type()?;
+    Ok(TableType { et, lim })
+}
+fn global_type(c: &mut Cursor) -> Result<GlobalType> {
+    match c.list("mut") {
+        Some(mut mutable) => {
+            let t = mutable.value_type()?;
+            mutable.finish()?;
+            Ok(GlobalType {
+                t,
+                m: Mutability::Var,
+            })
+        }
+        None => Ok(GlobalType {
+            t: c.value_type()?,
+            m: Mutability::Const,
+        }),
+    }
+}
+///`(i32.const 0)`, the offset of segments inlined in tables and memories
+fn zero_offset() -> Expression {
+    Expression(vec![Instruction::Numeric(NumericInstruction::I32Const(0))])
+}
+///the offset of the data inlined in a memory with addresses of `index_type`
+fn zero_address(index_type: IndexType) -> Expression {
+    match index_type {
+        IndexType::I32 => zero_offset(),
+        IndexType::I64 => Expression(vec![Instruction::Numeric(NumericInstruction::I64Const(0))]),
+    }
+}
+
+///the contents of an element segment
+enum ElemList {
+    Funcs(Vec<FuncIdx>),
+    Expressions(RefType, Vec<Expression>),
+}
+impl ElemList {
+    fn len(&self) -> usize {
+        match self {
+            ElemList::Funcs(funcs) => funcs.len(),
+            ElemList::Expressions(_, expressions) => expressions.len(),
+        }
+    }
+}
+///picks the shortest encoding of an active segment
+fn active_elem(table: TableIdx, offset: Expression, list: ElemList) -> Elem {
+    match list {
+        ElemList::Funcs(funcs) if table == 0 => Elem::ActiveIndex(offset, funcs),
+        ElemList::Funcs(funcs) => {
+            Elem::ActiveExplicitIndex(table, offset, ElementKind::FuncRef, funcs)
+        }
+        ElemList::Expressions(RefType::FuncRef, expressions) if table == 0 => {
+            Elem::ActiveExpression(offset, expressions)
+        }
+        ElemList::Expressions(ref_type, expressions) => {
+            Elem::ActiveExplicitExpression(table, offset, ref_type, expressions)
+        }
+    }
+}
+
+///next index of imported and defined entries of a space
+struct Counter {
+    imports: u32,
+    imported: u32,
+    defined: u32,
+}
+impl Counter {
+    fn new(space: &Space) -> Self {
+        Self {
+            imports: space.imported,
+            imported: 0,
+            defined: 0,
+        }
+    }
+    fn next(&mut self, import: bool) -> u32 {
+        if import {
+            self.imported += 1;
+            self.imported - 1
+        } else {
+            self.defined += 1;
+            self.imports + self.defined - 1
+        }
+    }
+}
+
+///the fields of `(module $id? field*)`, or bare fields
+pub(crate) fn module(sexps: &[Sexp]) -> Result<Module<'static>> {
+    let mut fields = Cursor::new(sexps, 0);
+    if fields.peek_list() == Some("module") && sexps.len() == 1 {
+        fields = fields.list("module").unwrap();
+        fields.id();
+    }
+    let mut context = Context {
+        types: vec![],
+        type_names: Space::new("type"),
+        funcs: Space::new("function"),
+        tables: Space::new("table"),
+        memories: Space::new("memory"),
+        globals: Space::new("global"),
+        tags: Space::new("tag"),
+        elems: Space::new("elem segment"),
+        datas: Space::new("data segment"),
+        uses_data_count: false,
+    };
+    //imports come first in every index space
+    context.declare(&fields, true)?;
+    for space in [
+        &mut context.funcs,
+        &mut context.tables,
+        &mut context.memories,
+        &mut context.globals,
+        &mut context.tags,
+    ] {
+        space.imported = space.len;
+    }
+    context.declare(&fields, false)?;
+    context.define(fields)
+}
+
+impl<'a> Context<'a> {
+    fn space(&mut self, kind: &str) -> Option<&mut Space<'a>> {
+        match kind {
+            "func" => Some(&mut self.funcs),
+            "table" => Some(&mut self.tables),
+            "memory" => Some(&mut self.memories),
+            "global" => Some(&mut self.globals),
+            "tag" => Some(&mut self.tags),
+            _ => None,
+        }
+    }
+
+    ///assigns indices to the imports, or to everything else
+    fn declare(&mut self, fields: &Cursor<'_, 'a>, imports: bool) -> Result<()> {
+        let mut fields = fields.clone();
+        while !fields.is_empty() {
+            let mut field = match fields.next_list() {
+                Some(field) => field,
+                None => return fields.error("expected a module field"),
+            };
+            let keyword_offset = field.offset();
+            let keyword = field.keyword()?;
+            let offset = field.offset();
+            let id = field.id();
+            match keyword {
+                "import" if imports => {
+                    field.string()?;
+                    field.string()?;
+                    let mut descriptor = match field.next_list() {
+                        Some(descriptor) => descriptor,
+                        None => return field.error("expected an import descriptor"),
+                    };
+                    let kind_offset = descriptor.offset();
+                    let kind = descriptor.keyword()?;
+                    let offset = descriptor.offset();
+                    let id = descriptor.id();
+                    match self.space(kind) {
+                        Some(space) => space.push(id, offset)?,
+                        None => {
+                            let message = format!("unknown import kind {}", kind);
+                            return Err(Error {
+                                offset: kind_offset,
+                                message,
+                            });
+                        }
+                    };
+                }
+                "func" | "table" | "memory" | "global" | "tag" => {
+                    while field.list("export").is_some() {}
+                    let imported = field.peek_list() == Some("import");
+                    if imported == imports {
+                        self.space(keyword).unwrap().push(id, offset)?;
+                    }
+                    if imports || imported {
+                        continue;
+                    }
+                    let inline_elem = matches!(field.peek_keyword(), Some("funcref" | "externref"));
+                    if keyword == "table" && inline_elem {
+                        self.elems.push(None, offset)?;
+                    }
+                    if keyword == "memory" && field.peek_list() == Some("data") {
+                        self.datas.push(None, offset)?;
+                    }
+                }
+                "type" if !imports => {
+                    let mut func = match field.list("func") {
+                        Some(func) => func,
+                        None => return field.error("expected (func ...)"),
+                    };
+                    let signature = signature(&mut func)?;
+                    func.finish()?;
+                    field.finish()?;
+                    self.type_names.push(id, offset)?;
+                    self.types.push(FuncType {
+                        rt1: signature.params,
+                        rt2: signature.results,
+                    });
+                }
+                "elem" if !imports => {
+                    self.elems.push(id, offset)?;
+                }
+                "data" if !imports => {
+                    self.datas.push(id, offset)?;
+                }
+                "import" | "type" | "elem" | "data" | "export" | "start" => {}
+                _ => {
+                    let message = format!("unknown module field {}", keyword);
+                    return Err(Error {
+                        offset: keyword_offset,
+                        message,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    ///index of `func_type`, appended to the types if no type matches
+    fn intern(&mut self, func_type: FuncType) -> TypeIdx {
+        match self
+            .types
+            .iter()
+            .position(|existing| *existing == func_type)
+        {
+            Some(index) => index as TypeIdx,
+            None => {
+                self.types.push(func_type);
+                (self.types.len() - 1) as TypeIdx
+            }
+        }
+    }
+    ///`(type x)? (param ...)* (result ...)*`, with the names of the params
+    pub fn type_use(&mut self, c: &mut Cursor<'_, 'a>) -> Result<(TypeIdx, ParamNames<'a>)> {
+        let explicit = match c.list("type") {
+            Some(mut list) => {
+                let offset = list.offset();
+                let index = list.index(&self.type_names)?;
+                list.finish()?;
+                Some((index, offset))
+            }
+            None => None,
+        };
+        let offset = c.offset();
+        let signature = signature(c)?;
+        let func_type = FuncType {
+            rt1: signature.params,
+            rt2: signature.results,
+        };
+        let (index, type_offset) = match explicit {
+            Some(explicit) => explicit,
+            None => return Ok((self.intern(func_type), signature.names)),
+        };
+        let declared = match self.types.get(index as usize) {
+            Some(declared) => declared,
+            None => {
+                let message = format!("unknown type {}", index);
+                return Err(Error {
+                    offset: type_offset,
+                    message,
+                });
+            }
+        };
+        if func_type.rt1.is_empty() && func_type.rt2.is_empty() {
+            return Ok((index, vec![(None, offset); declared.rt1.len()]));
+        }
+        if *declared != func_type {
+            let message = "inline signature does not match the type".to_string();
+            return Err(Error { offset, message });
+        }
+        Ok((index, signature.names))
+    }
+    pub fn block_type(&mut self, c: &mut Cursor<'_, 'a>) -> Result<BlockType> {
+        if c.peek_list() == Some("type") {
+            let (index, _) = self.type_use(c)?;
+            return Ok(BlockType::TypeIdx(index));
+        }
+        let signature = signature(c)?;
+        let block_type = match (&signature.params[..], &signature.results[..]) {
+            ([], []) => BlockType::Empty,
+            ([], [result]) => BlockType::ValType(*result),
+            _ => BlockType::TypeIdx(self.intern(FuncType {
+                rt1: signature.params,
+                rt2: signature.results,
+            })),
+        };
+        Ok(block_type)
+    }
+
+    fn import_descriptor(
+        &mut self,
+        kind: &str,
+        c: &mut Cursor<'_, 'a>,
+    ) -> Result<ImportDescriptor> {
+        let descriptor = match kind {
+            "func" => ImportDescriptor::Func(self.type_use(c)?.0),
+            "table" => ImportDescriptor::Table(table_type(c)?),
+            "memory" => {
+                let index_type = index_type(c);
+                ImportDescriptor::Mem(mem_type(c, index_type)?)
+            }
+            "global" => ImportDescriptor::Global(global_type(c)?),
+            _ => ImportDescriptor::Tag(self.tag_type(c)?),
+        };
+        c.finish()?;
+        Ok(descriptor)
+    }
+
+    fn tag_type(&mut self, c: &mut Cursor<'_, 'a>) -> Result<TagType> {
+        let (type_index, _) = self.type_use(c)?;
+        Ok(TagType {
+            attribute: Suffix,
+            type_index,
+        })
+    }
+
+    ///a function definition after its exports
+    fn func(&mut self, c: &mut Cursor<'_, 'a>) -> Result<(TypeIdx, Code)> {
+        let (type_idx, params) = self.type_use(c)?;
+        let mut locals = Space::new("local");
+        for (name, offset) in params {
+            locals.push(name, offset)?;
+        }
+        let mut declared: Vec<Local> = vec![];
+        let mut declare = |value_type| match declared.last_mut() {
+            Some(last) if last.value_type == value_type => last.count += 1,
+            _ => declared.push(Local {
+                count: 1,
+                value_type,
+            }),
+        };
+        while let Some(mut local) = c.list("local") {
+            let offset = local.offset();
+            if let Some(id) = local.id() {
+                declare(local.value_type()?);
+                locals.push(Some(id), offset)?;
+                local.finish()?;
+            }
+            while !local.is_empty() {
+                declare(local.value_type()?);
+                locals.push(None, offset)?;
+            }
+        }
+        let body = self.instructions(&mut Scope::new(locals), c, &[])?;
+        let code = Func {
+            locals: declared,
+            body: Expression(body),
+        };
+        let size = code.to_bytes().len() as u32;
+        let range = 0..0;
+        Ok((type_idx, Code { size, code, range }))
+    }
+
+    ///`func funcidx*`, `reftype item*`, or bare function indices when `legacy`
+    fn elem_list(&mut self, c: &mut Cursor<'_, 'a>, legacy: bool) -> Result<ElemList> {
+        if c.take_keyword("func") || (legacy && (c.is_empty() || c.peek_index())) {
+            let mut funcs = vec![];
+            while !c.is_empty() {
+                funcs.push(c.index(&self.funcs)?);
+            }
+            return Ok(ElemList::Funcs(funcs));
+        }
+        let ref_type = c.ref_type()?;
+        let mut expressions = vec![];
+        while !c.is_empty() {
+            let expression = match (c.list("item"), c.next_list()) {
+                (Some(mut item), _) => self.expression(&mut item)?,
+                (None, Some(mut list)) => self.folded_expression(&mut list)?,
+                (None, None) => return c.error("expected an element expression"),
+            };
+            expressions.push(expression);
+        }
+        Ok(ElemList::Expressions(ref_type, expressions))
+    }
+    ///an element segment after its `$id`
+    fn elem(&mut self, c: &mut Cursor<'_, 'a>) -> Result<Elem> {
+        if c.take_keyword("declare") {
+            let elem = match self.elem_list(c, false)? {
+                ElemList::Funcs(funcs) => Elem::DeclarativeIndex(ElementKind::FuncRef, funcs),
+                ElemList::Expressions(ref_type, expressions) => {
+                    Elem::DeclarativeExpression(ref_type, expressions)
+                }
+            };
+            return Ok(elem);
+        }
+        let table = match c.list("table") {
+            Some(mut table) => {
+                let index = table.index(&self.tables)?;
+                table.finish()?;
+                Some(index)
+            }
+            None => None,
+        };
+        let offset = match (c.list("offset"), c.peek_list()) {
+            (Some(mut offset), _) => Some(self.expression(&mut offset)?),
+            (None, Some(keyword)) if keyword != "item" => {
+                Some(self.folded_expression(&mut c.next_list().unwrap())?)
+            }
+            (None, _) if table.is_some() => return c.error("expected an offset"),
+            (None, _) => None,
+        };
+        let elem = match offset {
+            Some(offset) => {
+                let list = self.elem_list(c, table.is_none())?;
+                active_elem(table.unwrap_or(0), offset, list)
+            }
+            None => match self.elem_list(c, false)? {
+                ElemList::Funcs(funcs) => Elem::PassiveIndex(ElementKind::FuncRef, funcs),
+                ElemList::Expressions(ref_type, expressions) => {
+                    Elem::PassiveExpression(ref_type, expressions)
+                }
+            },
+        };
+        Ok(elem)
+    }
+    ///a data segment after its `$id`
+    fn data(&mut self, c: &mut Cursor<'_, 'a>) -> Result<Data<'static>> {
+        let memory = match c.list("memory") {
+            Some(mut memory) => {
+                let index = memory.index(&self.memories)?;
+                memory.finish()?;
+                Some(index)
+            }
+            None => None,
+        };
+        let offset = match c.list("offset") {
+            Some(mut offset) => Some(self.expression(&mut offset)?),
+            None => match c.next_list() {
+                Some(mut list) => Some(self.folded_expression(&mut list)?),
+                None => None,
+            },
+        };
+        let mut bytes = vec![];
+        while !c.is_empty() {
+            bytes.extend(c.string()?);
+        }
+        let data = match (memory, offset) {
+            (Some(_), None) => return c.error("expected an offset"),
+            (_, None) => Data::Passive(bytes.into()),
+            (None | Some(0), Some(offset)) => Data::Active(offset, bytes.into()),
+            (Some(memory), Some(offset)) => Data::ActiveExplicit(memory, offset, bytes.into()),
+        };
+        Ok(data)
+    }
+
+    ///converts the fields, in order, into sections
+    fn define(&mut self, mut fields: Cursor<'_, 'a>) -> Result<Module<'static>> {
+        let mut imports = vec![];
+        let mut functions = vec![];
+        let mut tables = vec![];
+        let mut memories = vec![];
+        let mut globals = vec![];
+        let mut tags = vec![];
+        let mut exports = vec![];
+        let mut start = None;
+        let mut elems = vec![];
+        let mut codes = vec![];
+        let mut datas = vec![];
+        let mut func_counter = Counter::new(&self.funcs);
+        let mut table_counter = Counter::new(&self.tables);
+        let mut memory_counter = Counter::new(&self.memories);
+        let mut global_counter = Counter::new(&self.globals);
+        let mut tag_counter = Counter::new(&self.tags);
+        //the first pass checked that every field is a keyword headed list
+        while let Some(mut field) = fields.next_list() {
+            let keyword = field.keyword()?;
+            field.id();
+            match keyword {
+                "import" => {
+                    let module = field.string()?;
+                    let name = field.string()?;
+                    let mut descriptor = field.next_list().unwrap();
+                    let kind = descriptor.keyword()?;
+                    descriptor.id();
+                    let descriptor = self.import_descriptor(kind, &mut descriptor)?;
+                    field.finish()?;
+                    imports.push(Import {
+                        module: module.into(),
+                        name: name.into(),
+                        descriptor,
+                    });
+                }
+                "func" | "table" | "memory" | "global" | "tag" => {
+                    let mut names = vec![];
+                    while let Some(mut export) = field.list("export") {
+                        names.push(export.string()?);
+                        export.finish()?;
+                    }
+                    let import = match field.list("import") {
+                        Some(mut import) => {
+                            let module = import.string()?;
+                            let name = import.string()?;
+                            import.finish()?;
+                            Some((module, name))
+                        }
+                        None => None,
+                    };
+                    let counter = match keyword {
+                        "func" => &mut func_counter,
+                        "table" => &mut table_counter,
+                        "memory" => &mut memory_counter,
+                        "global" => &mut global_counter,
+                        _ => &mut tag_counter,
+                    };
+                    let index = counter.next(import.is_some());
+                    for name in names {
+                        let descriptor = match keyword {
+                            "func" => ExportDescriptor::Func(index),
+                            "table" => ExportDescriptor::Table(index),
+                            "memory" => ExportDescriptor::Mem(index),
+                            "global" => ExportDescriptor::Global(index),
+                            _ => ExportDescriptor::Tag(index),
+                        };
+                        exports.push(Export {
+                            name: name.into(),
+                            descriptor,
+                        });
+                    }
+                    if let Some((module, name)) = import {
+                        let descriptor = self.import_descriptor(keyword, &mut field)?;
+                        imports.push(Import {
+                            module: module.into(),
+                            name: name.into(),
+                            descriptor,
+                        });
+                        continue;
+                    }
+                    match keyword {
+                        "func" => {
+                            let (type_idx, code) = self.func(&mut field)?;
+                            functions.push(type_idx);
+                            codes.push(code);
+                        }
+                        "table"
+                            if matches!(field.peek_keyword(), Some("funcref" | "externref")) =>
+                        {
+                            let ref_type = field.ref_type()?;
+                            let mut elem = match field.list("elem") {
+                                Some(elem) => elem,
+                                None => return field.error("expected (elem ...)"),
+                            };
+                            let list = match elem.peek() {
+                                Some(Sexp::List(..)) => {
+                                    let mut expressions = vec![];
+                                    while !elem.is_empty() {
+                                        let expression = match elem.list("item") {
+                                            Some(mut item) => self.expression(&mut item)?,
+                                            None => {
+                                                let mut list = elem.next_list().unwrap();
+                                                self.folded_expression(&mut list)?
+                                            }
+                                        };
+                                        expressions.push(expression);
+                                    }
+                                    ElemList::Expressions(ref_type, expressions)
+                                }
+                                _ => self.elem_list(&mut elem, true)?,
+                            };
+                            let len = list.len() as u64;
+                            let lim = Limit {
+                                min: len,
+                                max: Some(len),
+                                index_type: IndexType::I32,
+                            };
+                            let table_type = TableType { et: ref_type, lim };
+                            tables.push(Table { table_type });
+                            elems.push(active_elem(index, zero_offset(), list));
+                        }
+                        "table" => tables.push(Table {
+                            table_type: table_type(&mut field)?,
+                        }),
+                        "memory" => {
+                            let index_type = index_type(&mut field);
+                            match field.list("data") {
+                                Some(mut data) => {
+                                    let mut bytes = vec![];
+                                    while !data.is_empty() {
+                                        bytes.extend(data.string()?);
+                                    }
+                                    let pages = bytes.len().div_ceil(PAGE_SIZE) as u64;
+                                    let lim = Limit {
+                                        min: pages,
+                                        max: Some(pages),
+                                        index_type,
+                                    };
+                                    memories.push(Memory {
+                                        memory_type: MemType { lim, shared: false },
+                                    });
+                                    let offset = zero_address(index_type);
+                                    datas.push(match index {
+                                        0 => Data::Active(offset, bytes.into()),
+                                        _ => Data::ActiveExplicit(index, offset, bytes.into()),
+                                    });
+                                }
+                                None => memories.push(Memory {
+                                    memory_type: mem_type(&mut field, index_type)?,
+                                }),
+                            }
+                        }
+                        "tag" => tags.push(Tag {
+                            tag_type: self.tag_type(&mut field)?,
+                        }),
+                        _ => {
+                            let global_type = global_type(&mut field)?;
+                            let expression = self.expression(&mut field)?;
+                            globals.push(Global {
+                                global_type,
+                                expression,
+                            });
+                        }
+                    }
+                }
+                "export" => {
+                    let name = field.string()?;
+                    let mut descriptor = match field.next_list() {
+                        Some(descriptor) => descriptor,
+                        None => return field.error("expected an export descriptor"),
+                    };
+                    let descriptor = match descriptor.keyword()? {
+                        "func" => ExportDescriptor::Func(descriptor.index(&self.funcs)?),
+                        "table" => ExportDescriptor::Table(descriptor.index(&self.tables)?),
+                        "memory" => ExportDescriptor::Mem(descriptor.index(&self.memories)?),
+                        "global" => ExportDescriptor::Global(descriptor.index(&self.globals)?),
+                        "tag" => ExportDescriptor::Tag(descriptor.index(&self.tags)?),
+                        kind => return field.error(format!("unknown export kind {}", kind)),
+                    };
+                    exports.push(Export {
+                        name: name.into(),
+                        descriptor,
+                    });
+                }
+                "start" => {
+                    if start.is_some() {
+                        return field.error("multiple start functions");
+                    }
+                    start = Some(field.index(&self.funcs)?);
+                }
+                //converted while declaring
+                "type" => continue,
+                "elem" => elems.push(self.elem(&mut field)?),
+                "data" => datas.push(self.data(&mut field)?),
+                _ => {}
+            }
+            field.finish()?;
+        }
+
+        let mut sections = vec![];
+        if !self.types.is_empty() {
+            let types = std::mem::take(&mut self.types);
+            sections.push(Section::TypeSection(TypeSection(types)));
+        }
+        if !imports.is_empty() {
+            sections.push(Section::ImportSection(ImportSection(imports)));
+        }
+        if !functions.is_empty() {
+            sections.push(Section::FunctionSection(FunctionSection(functions)));
+        }
+        if !tables.is_empty() {
+            sections.push(Section::TableSection(TableSection(tables)));
+        }
+        if !memories.is_empty() {
+            sections.push(Section::MemorySection(MemorySection(memories)));
+        }
+        if !tags.is_empty() {
+            sections.push(Section::TagSection(TagSection(tags)));
+        }
+        if !globals.is_empty() {
+            sections.push(Section::GlobalSection(GlobalSection(globals)));
+        }
+        if !exports.is_empty() {
+            sections.push(Section::ExportSection(ExportSection(exports)));
+        }
+        if start.is_some() {
+            sections.push(Section::StartSection(StartSection(start)));
+        }
+        if !elems.is_empty() {
+            sections.push(Section::ElementSection(ElementSection(elems)));
+        }
+        if self.uses_data_count {
+            let count = Some(datas.len() as u32);
+            sections.push(Section::DataCountSection(DataCountSection(count)));
+        }
+        if !codes.is_empty() {
+            sections.push(Section::CodeSection(CodeSection(codes)));
+        }
+        if !datas.is_empty() {
+            sections.push(Section::DataSection(DataSection(datas)));
+        }
+        Ok(Module {
+            magic: Magic,
+            version: Version([1, 0, 0, 0]),
+            sections,
+            section_ranges: vec![],
+        })
+    }
+}
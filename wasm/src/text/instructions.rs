@@ -0,0 +1,563 @@
+use super::{
+    cursor::{Cursor, Space},
+    fields::Context,
+    keywords::{self, SimdImmediates},
+    numbers, Error, Result,
+};
+use crate::{
+    instructions::{
+        atomic::AtomicInstruction,
+        control::{Catch, ControlInstruction},
+        memory::{MemArg, MemoryInstruction},
+        numeric::NumericInstruction,
+        parametric::ParametricInstruction,
+        reference::ReferenceInstruction,
+        simd::SimdInstruction,
+        table::TableInstruction,
+        variable::VariableInstruction,
+        Expression, Instruction, Instructions,
+    },
+    modules::indices::MemIdx,
+    types::RefType,
+    Prefix, Suffix,
+};
+
+///locals and enclosing labels of the code being parsed
+pub(crate) struct Scope<'a> {
+    locals: Space<'a>,
+    labels: Vec<Option<&'a str>>,
+}
+impl<'a> Scope<'a> {
+    pub fn new(locals: Space<'a>) -> Self {
+        Self {
+            locals,
+            labels: vec![],
+        }
+    }
+    ///scope of constant expressions, without locals
+    pub fn constant() -> Self {
+        Self::new(Space::new("local"))
+    }
+    ///a label index, or a `$label` resolved to its depth
+    fn label(&self, c: &mut Cursor<'_, 'a>) -> Result<u32> {
+        let id = match c.peek_id() {
+            Some(id) => id,
+            None => return c.u32(),
+        };
+        match self.labels.iter().rposition(|label| *label == Some(id)) {
+            Some(position) => {
+                c.id();
+                Ok((self.labels.len() - 1 - position) as u32)
+            }
+            None => c.error(format!("unknown label ${}", id)),
+        }
+    }
+}
+
+///the optional `$label` repeated after `else` and `end`
+fn end_label(c: &mut Cursor, label: Option<&str>) -> Result<()> {
+    if let Some(id) = c.peek_id() {
+        if Some(id) != label {
+            let message = match label {
+                Some(label) => format!("mismatched label ${}, expected ${}", id, label),
+                None => format!("mismatched label ${}", id),
+            };
+            return c.error(message);
+        }
+        c.id();
+    }
+    Ok(())
+}
+fn end(c: &mut Cursor, label: Option<&str>) -> Result<()> {
+    if !c.take_keyword("end") {
+        return c.error("expected end");
+    }
+    end_label(c, label)
+}
+
+impl<'a> Context<'a> {
+    ///parses plain and folded instructions until the list ends or one of `terminators`
+    pub fn instructions(
+        &mut self,
+        scope: &mut Scope<'a>,
+        c: &mut Cursor<'_, 'a>,
+        terminators: &[&str],
+    ) -> Result<Vec<Instruction>> {
+        let mut instructions = vec![];
+        while !c.is_empty() {
+            if let Some(mut list) = c.next_list() {
+                self.folded(scope, &mut list, &mut instructions)?;
+                continue;
+            }
+            match c.peek_keyword() {
+                Some(keyword) if terminators.contains(&keyword) => break,
+                _ => self.plain(scope, c, &mut instructions)?,
+            }
+        }
+        Ok(instructions)
+    }
+    ///a constant expression filling the rest of the list
+    pub fn expression(&mut self, c: &mut Cursor<'_, 'a>) -> Result<Expression> {
+        let instructions = self.instructions(&mut Scope::constant(), c, &[])?;
+        Ok(Expression(instructions))
+    }
+    ///a constant expression written as a single folded instruction
+    pub fn folded_expression(&mut self, list: &mut Cursor<'_, 'a>) -> Result<Expression> {
+        let mut instructions = vec![];
+        self.folded(&mut Scope::constant(), list, &mut instructions)?;
+        Ok(Expression(instructions))
+    }
+
+    ///a block terminated by `end`, or `delegate`, or any other instruction
+    fn plain(
+        &mut self,
+        scope: &mut Scope<'a>,
+        c: &mut Cursor<'_, 'a>,
+        out: &mut Vec<Instruction>,
+    ) -> Result<()> {
+        let offset = c.offset();
+        let keyword = c.keyword()?;
+        let instruction = match keyword {
+            "block" | "loop" => {
+                let label = c.id();
+                let block_type = self.block_type(c)?;
+                scope.labels.push(label);
+                let body = Instructions(self.instructions(scope, c, &["end"])?);
+                scope.labels.pop();
+                end(c, label)?;
+                match keyword {
+                    "block" => ControlInstruction::Block(block_type, body),
+                    _ => ControlInstruction::Loop(block_type, body),
+                }
+            }
+            "if" => {
+                let label = c.id();
+                let block_type = self.block_type(c)?;
+                scope.labels.push(label);
+                let if_branch = Instructions(self.instructions(scope, c, &["else", "end"])?);
+                let else_branch = if c.take_keyword("else") {
+                    end_label(c, label)?;
+                    Some(Instructions(self.instructions(scope, c, &["end"])?))
+                } else {
+                    None
+                };
+                scope.labels.pop();
+                end(c, label)?;
+                ControlInstruction::IfElse(block_type, if_branch, else_branch)
+            }
+            "try_table" => {
+                let label = c.id();
+                let block_type = self.block_type(c)?;
+                let catches = self.catches(scope, c)?;
+                scope.labels.push(label);
+                let body = Instructions(self.instructions(scope, c, &["end"])?);
+                scope.labels.pop();
+                end(c, label)?;
+                ControlInstruction::TryTable(block_type, catches, body)
+            }
+            "try" => {
+                let label = c.id();
+                let block_type = self.block_type(c)?;
+                scope.labels.push(label);
+                let terminators = ["catch", "catch_all", "delegate", "end"];
+                let body = Instructions(self.instructions(scope, c, &terminators)?);
+                if c.take_keyword("delegate") {
+                    scope.labels.pop();
+                    let target = scope.label(c)?;
+                    out.push(Instruction::Control(ControlInstruction::Delegate(
+                        block_type, body, target,
+                    )));
+                    return Ok(());
+                }
+                let mut catches = vec![];
+                while c.take_keyword("catch") {
+                    let tag = c.index(&self.tags)?;
+                    let handler = self.instructions(scope, c, &terminators)?;
+                    catches.push((tag, Instructions(handler)));
+                }
+                let catch_all = match c.take_keyword("catch_all") {
+                    true => Some(Instructions(self.instructions(scope, c, &["end"])?)),
+                    false => None,
+                };
+                scope.labels.pop();
+                end(c, label)?;
+                ControlInstruction::Try {
+                    block: block_type,
+                    body,
+                    catches,
+                    catch_all,
+                }
+            }
+            _ => {
+                let instruction = self.instruction(scope, keyword, offset, c)?;
+                out.push(instruction);
+                return Ok(());
+            }
+        };
+        out.push(Instruction::Control(instruction));
+        Ok(())
+    }
+
+    ///`(instruction operand*)`, pushing the operands before the instruction
+    pub fn folded(
+        &mut self,
+        scope: &mut Scope<'a>,
+        c: &mut Cursor<'_, 'a>,
+        out: &mut Vec<Instruction>,
+    ) -> Result<()> {
+        let offset = c.offset();
+        let keyword = c.keyword()?;
+        let instruction = match keyword {
+            "block" | "loop" => {
+                let label = c.id();
+                let block_type = self.block_type(c)?;
+                scope.labels.push(label);
+                let body = Instructions(self.instructions(scope, c, &[])?);
+                scope.labels.pop();
+                match keyword {
+                    "block" => ControlInstruction::Block(block_type, body),
+                    _ => ControlInstruction::Loop(block_type, body),
+                }
+            }
+            "if" => {
+                let label = c.id();
+                let block_type = self.block_type(c)?;
+                while c.peek_list().is_some_and(|keyword| keyword != "then") {
+                    let mut condition = c.next_list().unwrap();
+                    self.folded(scope, &mut condition, out)?;
+                }
+                scope.labels.push(label);
+                let mut then = match c.list("then") {
+                    Some(then) => then,
+                    None => return c.error("expected (then ...)"),
+                };
+                let if_branch = Instructions(self.instructions(scope, &mut then, &[])?);
+                let else_branch = match c.list("else") {
+                    Some(mut otherwise) => Some(Instructions(self.instructions(
+                        scope,
+                        &mut otherwise,
+                        &[],
+                    )?)),
+                    None => None,
+                };
+                scope.labels.pop();
+                ControlInstruction::IfElse(block_type, if_branch, else_branch)
+            }
+            "try_table" => {
+                let label = c.id();
+                let block_type = self.block_type(c)?;
+                let catches = self.catches(scope, c)?;
+                scope.labels.push(label);
+                let body = Instructions(self.instructions(scope, c, &[])?);
+                scope.labels.pop();
+                ControlInstruction::TryTable(block_type, catches, body)
+            }
+            //`(try (do ...) (catch $tag ...)* (catch_all ...)?)` or `(try (do ...) (delegate l))`
+            "try" => {
+                let label = c.id();
+                let block_type = self.block_type(c)?;
+                scope.labels.push(label);
+                let mut body = match c.list("do") {
+                    Some(body) => body,
+                    None => return c.error("expected (do ...)"),
+                };
+                let body = Instructions(self.instructions(scope, &mut body, &[])?);
+                let mut catches = vec![];
+                while let Some(mut handler) = c.list("catch") {
+                    let tag = handler.index(&self.tags)?;
+                    let handler = self.instructions(scope, &mut handler, &[])?;
+                    catches.push((tag, Instructions(handler)));
+                }
+                let catch_all = match c.list("catch_all") {
+                    Some(mut handler) => {
+                        Some(Instructions(self.instructions(scope, &mut handler, &[])?))
+                    }
+                    None => None,
+                };
+                scope.labels.pop();
+                let delegate = match (&catches[..], &catch_all) {
+                    ([], None) => c.list("delegate"),
+                    _ => None,
+                };
+                match delegate {
+                    Some(mut delegate) => {
+                        let target = scope.label(&mut delegate)?;
+                        delegate.finish()?;
+                        ControlInstruction::Delegate(block_type, body, target)
+                    }
+                    None => ControlInstruction::Try {
+                        block: block_type,
+                        body,
+                        catches,
+                        catch_all,
+                    },
+                }
+            }
+            _ => {
+                let instruction = self.instruction(scope, keyword, offset, c)?;
+                while !c.is_empty() {
+                    match c.next_list() {
+                        Some(mut operand) => self.folded(scope, &mut operand, out)?,
+                        None => return c.error("expected a folded instruction"),
+                    }
+                }
+                out.push(instruction);
+                return Ok(());
+            }
+        };
+        c.finish()?;
+        out.push(Instruction::Control(instruction));
+        Ok(())
+    }
+
+    ///the `(catch ...)` handlers of `try_table`, whose labels are outside of the block
+    fn catches(&self, scope: &Scope<'a>, c: &mut Cursor<'_, 'a>) -> Result<Vec<Catch>> {
+        let mut catches = vec![];
+        while let Some(kind @ ("catch" | "catch_ref" | "catch_all" | "catch_all_ref")) =
+            c.peek_list()
+        {
+            let mut handler = c.list(kind).unwrap();
+            let catch = match kind {
+                "catch" => Catch::Catch(handler.index(&self.tags)?, scope.label(&mut handler)?),
+                "catch_ref" => {
+                    Catch::CatchRef(handler.index(&self.tags)?, scope.label(&mut handler)?)
+                }
+                "catch_all" => Catch::CatchAll(scope.label(&mut handler)?),
+                _ => Catch::CatchAllRef(scope.label(&mut handler)?),
+            };
+            handler.finish()?;
+            catches.push(catch);
+        }
+        Ok(catches)
+    }
+
+    ///an instruction other than the blocks, with its immediates
+    fn instruction(
+        &mut self,
+        scope: &mut Scope<'a>,
+        keyword: &str,
+        offset: usize,
+        c: &mut Cursor<'_, 'a>,
+    ) -> Result<Instruction> {
+        use Instruction::*;
+        let instruction = match keyword {
+            "unreachable" => Control(ControlInstruction::Unreachable),
+            "nop" => Control(ControlInstruction::Nop),
+            "br" => Control(ControlInstruction::Br(scope.label(c)?)),
+            "br_if" => Control(ControlInstruction::BrIf(scope.label(c)?)),
+            "br_table" => {
+                let mut table = vec![scope.label(c)?];
+                while c.peek_index() {
+                    table.push(scope.label(c)?);
+                }
+                let default = table.pop().unwrap();
+                Control(ControlInstruction::BrTable { table, default })
+            }
+            "return" => Control(ControlInstruction::Return),
+            "call" => Control(ControlInstruction::Call(c.index(&self.funcs)?)),
+            "call_indirect" => {
+                let table = self.optional_index(c, |context| &context.tables)?;
+                let (type_idx, _) = self.type_use(c)?;
+                Control(ControlInstruction::CallIndirect(type_idx, table))
+            }
+            "throw" => Control(ControlInstruction::Throw(c.index(&self.tags)?)),
+            "throw_ref" => Control(ControlInstruction::ThrowRef),
+            "rethrow" => Control(ControlInstruction::Rethrow(scope.label(c)?)),
+            "ref.null" => {
+                let ref_type = match c.peek_keyword() {
+                    Some("func") => RefType::FuncRef,
+                    Some("extern") => RefType::ExternRef,
+                    Some("exn") => RefType::ExnRef,
+                    _ => return c.error("expected func, extern or exn"),
+                };
+                c.keyword()?;
+                Reference(ReferenceInstruction::Null(ref_type))
+            }
+            "ref.is_null" => Reference(ReferenceInstruction::IsNull),
+            "ref.func" => Reference(ReferenceInstruction::Func(c.index(&self.funcs)?)),
+            "drop" => Parametric(ParametricInstruction::Drop),
+            "select" => {
+                let mut types = None;
+                while let Some(mut result) = c.list("result") {
+                    let types = types.get_or_insert_with(Vec::new);
+                    while !result.is_empty() {
+                        types.push(result.value_type()?);
+                    }
+                }
+                match types {
+                    Some(types) => Parametric(ParametricInstruction::SelectTyped(types)),
+                    None => Parametric(ParametricInstruction::Select),
+                }
+            }
+            "local.get" => Variable(VariableInstruction::LocalGet(c.index(&scope.locals)?)),
+            "local.set" => Variable(VariableInstruction::LocalSet(c.index(&scope.locals)?)),
+            "local.tee" => Variable(VariableInstruction::LocalTee(c.index(&scope.locals)?)),
+            "global.get" => Variable(VariableInstruction::GlobalGet(c.index(&self.globals)?)),
+            "global.set" => Variable(VariableInstruction::GlobalSet(c.index(&self.globals)?)),
+            "table.get" | "table.set" | "table.size" | "table.grow" | "table.fill" => {
+                let table = self.optional_index(c, |context| &context.tables)?;
+                let instruction = match keyword {
+                    "table.get" => TableInstruction::TableGet(table),
+                    "table.set" => TableInstruction::TableSet(table),
+                    "table.size" => TableInstruction::TableSize(table),
+                    "table.grow" => TableInstruction::TableGrow(table),
+                    _ => TableInstruction::TableFill(table),
+                };
+                Table(instruction)
+            }
+            "table.copy" => {
+                let destination = self.optional_index(c, |context| &context.tables)?;
+                let source = self.optional_index(c, |context| &context.tables)?;
+                Table(TableInstruction::TableCopy(destination, source))
+            }
+            "table.init" => {
+                let table = if c.peek_index_at(1) {
+                    c.index(&self.tables)?
+                } else {
+                    0
+                };
+                Table(TableInstruction::TableInit(c.index(&self.elems)?, table))
+            }
+            "elem.drop" => Table(TableInstruction::TableDrop(c.index(&self.elems)?)),
+            "memory.size" | "memory.grow" | "memory.fill" => {
+                let memory = self.optional_index(c, |context| &context.memories)?;
+                let instruction = match keyword {
+                    "memory.size" => MemoryInstruction::Size(memory),
+                    "memory.grow" => MemoryInstruction::Grow(memory),
+                    _ => MemoryInstruction::Fill(Prefix, memory),
+                };
+                Memory(instruction)
+            }
+            "memory.copy" => {
+                let destination = self.optional_index(c, |context| &context.memories)?;
+                let source = self.optional_index(c, |context| &context.memories)?;
+                Memory(MemoryInstruction::Copy(Prefix, destination, source))
+            }
+            "memory.init" => {
+                self.uses_data_count = true;
+                let memory = if c.peek_index_at(1) {
+                    c.index(&self.memories)?
+                } else {
+                    0
+                };
+                Memory(MemoryInstruction::Init(
+                    Prefix,
+                    c.index(&self.datas)?,
+                    memory,
+                ))
+            }
+            "data.drop" => {
+                self.uses_data_count = true;
+                Memory(MemoryInstruction::Drop(Prefix, c.index(&self.datas)?))
+            }
+            "atomic.fence" => Atomic(AtomicInstruction::AtomicFence(Suffix)),
+            "i32.const" => Numeric(NumericInstruction::I32Const(c.i32()?)),
+            "i64.const" => Numeric(NumericInstruction::I64Const(c.i64()?)),
+            "f32.const" => Numeric(NumericInstruction::F32Const(c.f32()?)),
+            "f64.const" => Numeric(NumericInstruction::F64Const(c.f64()?)),
+            _ => {
+                if let Some(instruction) = keywords::numeric(keyword) {
+                    Numeric(instruction)
+                } else if let Some(instruction) = keywords::saturating(keyword) {
+                    Saturating(instruction)
+                } else if let Some(template) = keywords::simd(keyword, Default::default()) {
+                    Simd(self.simd(c, keyword, &template)?)
+                } else if let Some(instruction) = keywords::relaxed_simd(keyword) {
+                    RelaxedSimd(instruction)
+                } else if let Some((constructor, natural)) = keywords::memory(keyword) {
+                    let memory = self.optional_index(c, |context| &context.memories)?;
+                    Memory(constructor(mem_arg(c, memory, natural)?))
+                } else if let Some((constructor, natural)) = keywords::atomic(keyword) {
+                    let memory = self.optional_index(c, |context| &context.memories)?;
+                    Atomic(constructor(mem_arg(c, memory, natural)?))
+                } else {
+                    let message = format!("unknown instruction {}", keyword);
+                    return Err(Error { offset, message });
+                }
+            }
+        };
+        Ok(instruction)
+    }
+
+    ///`keyword` with the immediates `template` takes, in the order the printer writes them
+    fn simd(
+        &self,
+        c: &mut Cursor<'_, 'a>,
+        keyword: &str,
+        template: &SimdInstruction,
+    ) -> Result<SimdInstruction> {
+        let mut immediates = SimdImmediates::default();
+        if let Some((_, width)) = template.memory_access() {
+            //a lone index before the lane is the lane
+            let lane_only = template.lane().is_some()
+                && c.peek_id().is_none()
+                && !c.peek_index_at(1)
+                && !matches!(c.peek_keyword_at(1), Some(keyword)
+                    if keyword.starts_with("offset=") || keyword.starts_with("align="));
+            let memory = if lane_only {
+                0
+            } else {
+                self.optional_index(c, |context| &context.memories)?
+            };
+            immediates.mem_arg = mem_arg(c, memory, (width / 8).trailing_zeros())?;
+        }
+        match template {
+            SimdInstruction::V128Const(_) => immediates.v128 = c.v128()?,
+            SimdInstruction::I8x16Shuffle(_) => {
+                for lane in &mut immediates.shuffle.0 {
+                    *lane = c.lane(32)?;
+                }
+            }
+            template => {
+                if let Some((_, lanes)) = template.lane() {
+                    immediates.lane = c.lane(lanes)?;
+                }
+            }
+        }
+        Ok(keywords::simd(keyword, immediates).expect("the template has the same keyword"))
+    }
+    ///an index that defaults to 0 when omitted
+    fn optional_index(
+        &self,
+        c: &mut Cursor<'_, 'a>,
+        space: impl FnOnce(&Self) -> &Space<'a>,
+    ) -> Result<u32> {
+        if c.peek_index() {
+            c.index(space(self))
+        } else {
+            Ok(0)
+        }
+    }
+}
+
+///`offset=` and `align=` of an access to `memory`, `natural` is the default alignment as a power
+///of two
+fn mem_arg(c: &mut Cursor, memory: MemIdx, natural: u32) -> Result<MemArg> {
+    let mut mem_arg = MemArg {
+        align: natural,
+        offset: 0,
+        memory,
+        explicit_memory: false,
+    };
+    if let Some(offset) = c
+        .peek_keyword()
+        .and_then(|keyword| keyword.strip_prefix("offset="))
+    {
+        mem_arg.offset = match numbers::u64(offset) {
+            Some(offset) => offset,
+            None => return c.error(format!("invalid offset {}", offset)),
+        };
+        c.keyword()?;
+    }
+    if let Some(align) = c
+        .peek_keyword()
+        .and_then(|keyword| keyword.strip_prefix("align="))
+    {
+        mem_arg.align = match numbers::u32(align) {
+            Some(align) if align.is_power_of_two() => align.trailing_zeros(),
+            _ => return c.error(format!("invalid alignment {}", align)),
+        };
+        c.keyword()?;
+    }
+    Ok(mem_arg)
+}
@@ -0,0 +1,613 @@
+//!instruction mnemonics
+use crate::instructions::{
+    atomic::AtomicInstruction,
+    memory::{MemArg, MemoryInstruction},
+    numeric::{NumericInstruction, SaturatingTruncationInstruction},
+    simd::{LaneIdx, MemLane, RelaxedSimdInstruction, ShuffleLanes, SimdInstruction, V128},
+};
+
+///maps mnemonics to instructions without immediates, and back
+macro_rules! keywords {
+    ($name:ident, $reverse:ident -> $ty:ident { $($keyword:literal => $variant:ident,)* }) => {
+        pub(crate) fn $name(keyword: &str) -> Option<$ty> {
+            let instruction = match keyword {
+                $($keyword => $ty::$variant,)*
+                _ => return None,
+            };
+            Some(instruction)
+        }
+        pub(crate) fn $reverse(instruction: &$ty) -> Option<&'static str> {
+            let keyword = match instruction {
+                $($ty::$variant => $keyword,)*
+                #[allow(unreachable_patterns)]
+                _ => return None,
+            };
+            Some(keyword)
+        }
+    };
+}
+
+keywords!(numeric, numeric_keyword -> NumericInstruction {
+        "i32.eqz" => I32Eqz,
+        "i32.eq" => I32Eq,
+        "i32.ne" => I32Ne,
+        "i32.lt_s" => I32LtS,
+        "i32.lt_u" => I32LtU,
+        "i32.gt_s" => I32GtS,
+        "i32.gt_u" => I32GtU,
+        "i32.le_s" => I32LeS,
+        "i32.le_u" => I32LeU,
+        "i32.ge_s" => I32GeS,
+        "i32.ge_u" => I32GeU,
+        "i64.eqz" => I64Eqz,
+        "i64.eq" => I64Eq,
+        "i64.ne" => I64Ne,
+        "i64.lt_s" => I64LtS,
+        "i64.lt_u" => I64LtU,
+        "i64.gt_s" => I64GtS,
+        "i64.gt_u" => I64GtU,
+        "i64.le_s" => I64LeS,
+        "i64.le_u" => I64LeU,
+        "i64.ge_s" => I64GeS,
+        "i64.ge_u" => I64GeU,
+        "f32.eq" => F32Eq,
+        "f32.ne" => F32Ne,
+        "f32.lt" => F32Lt,
+        "f32.gt" => F32Gt,
+        "f32.le" => F32Le,
+        "f32.ge" => F32Ge,
+        "f64.eq" => F64Eq,
+        "f64.ne" => F64Ne,
+        "f64.lt" => F64Lt,
+        "f64.gt" => F64Gt,
+        "f64.le" => F64Le,
+        "f64.ge" => F64Ge,
+        "i32.clz" => I32Clz,
+        "i32.ctz" => I32Ctz,
+        "i32.popcnt" => I32Popcnt,
+        "i32.add" => I32Add,
+        "i32.sub" => I32Sub,
+        "i32.mul" => I32Mul,
+        "i32.div_s" => I32DivS,
+        "i32.div_u" => I32DivU,
+        "i32.rem_s" => I32RemS,
+        "i32.rem_u" => I32RemU,
+        "i32.and" => I32And,
+        "i32.or" => I32Or,
+        "i32.xor" => I32Xor,
+        "i32.shl" => I32Shl,
+        "i32.shr_s" => I32ShrS,
+        "i32.shr_u" => I32ShrU,
+        "i32.rotl" => I32Rotl,
+        "i32.rotr" => I32Rotr,
+        "i64.clz" => I64Clz,
+        "i64.ctz" => I64Ctz,
+        "i64.popcnt" => I64Popcnt,
+        "i64.add" => I64Add,
+        "i64.sub" => I64Sub,
+        "i64.mul" => I64Mul,
+        "i64.div_s" => I64DivS,
+        "i64.div_u" => I64DivU,
+        "i64.rem_s" => I64RemS,
+        "i64.rem_u" => I64RemU,
+        "i64.and" => I64And,
+        "i64.or" => I64Or,
+        "i64.xor" => I64Xor,
+        "i64.shl" => I64Shl,
+        "i64.shr_s" => I64ShrS,
+        "i64.shr_u" => I64ShrU,
+        "i64.rotl" => I64Rotl,
+        "i64.rotr" => I64Rotr,
+        "f32.abs" => F32Abs,
+        "f32.neg" => F32Neg,
+        "f32.ceil" => F32Ceil,
+        "f32.floor" => F32Floor,
+        "f32.trunc" => F32Trunc,
+        "f32.nearest" => F32Nearest,
+        "f32.sqrt" => F32Sqrt,
+        "f32.add" => F32Add,
+        "f32.sub" => F32Sub,
+        "f32.mul" => F32Mul,
+        "f32.div" => F32Div,
+        "f32.min" => F32Min,
+        "f32.max" => F32Max,
+        "f32.copysign" => F32CopySign,
+        "f64.abs" => F64Abs,
+        "f64.neg" => F64Neg,
+        "f64.ceil" => F64Ceil,
+        "f64.floor" => F64Floor,
+        "f64.trunc" => F64Trunc,
+        "f64.nearest" => F64Nearest,
+        "f64.sqrt" => F64Sqrt,
+        "f64.add" => F64Add,
+        "f64.sub" => F64Sub,
+        "f64.mul" => F64Mul,
+        "f64.div" => F64Div,
+        "f64.min" => F64Min,
+        "f64.max" => F64Max,
+        "f64.copysign" => F64CopySign,
+        "i32.wrap_i64" => I32WrapI64,
+        "i32.trunc_f32_s" => I32TruncF32S,
+        "i32.trunc_f32_u" => I32TruncF32U,
+        "i32.trunc_f64_s" => I32TruncF64S,
+        "i32.trunc_f64_u" => I32TruncF64U,
+        "i64.extend_i32_s" => I64ExtendI32S,
+        "i64.extend_i32_u" => I64ExtendI32U,
+        "i64.trunc_f32_s" => I64TruncF32S,
+        "i64.trunc_f32_u" => I64TruncF32U,
+        "i64.trunc_f64_s" => I64TruncF64S,
+        "i64.trunc_f64_u" => I64TruncF64U,
+        "f32.convert_i32_s" => F32ConvertI32S,
+        "f32.convert_i32_u" => F32ConvertI32U,
+        "f32.convert_i64_s" => F32ConvertI64S,
+        "f32.convert_i64_u" => F32ConvertI64u,
+        "f32.demote_f64" => F32DemoteF64,
+        "f64.convert_i32_s" => F64ConvertI32S,
+        "f64.convert_i32_u" => F64ConvertI32U,
+        "f64.convert_i64_s" => F64ConvertI64S,
+        "f64.convert_i64_u" => F64ConvertI64u,
+        "f64.promote_f32" => F64PromoteF32,
+        "i32.reinterpret_f32" => I32ReinterpretF32,
+        "i64.reinterpret_f64" => I64ReinterpretF64,
+        "f32.reinterpret_i32" => F32ReinterpretI32,
+        "f64.reinterpret_i64" => F64ReinterpretI64,
+        "i32.extend8_s" => I32Extend8S,
+        "i32.extend16_s" => I32Extend16S,
+        "i64.extend8_s" => I64Extend8S,
+        "i64.extend16_s" => I64Extend16S,
+        "i64.extend32_s" => I64Extend32S,
+});
+
+keywords!(saturating, saturating_keyword -> SaturatingTruncationInstruction {
+        "i32.trunc_sat_f32_s" => I32TruncSatF32S,
+        "i32.trunc_sat_f32_u" => I32TruncSatF32U,
+        "i32.trunc_sat_f64_s" => I32TruncSatF64S,
+        "i32.trunc_sat_f64_u" => I32TruncSatF64U,
+        "i64.trunc_sat_f32_s" => I64TruncSatF32S,
+        "i64.trunc_sat_f32_u" => I64TruncSatF32U,
+        "i64.trunc_sat_f64_s" => I64TruncSatF64S,
+        "i64.trunc_sat_f64_u" => I64TruncSatF64U,
+});
+
+///the immediates a SIMD instruction may take, parsed before the instruction is built
+#[derive(Default)]
+pub(crate) struct SimdImmediates {
+    pub mem_arg: MemArg,
+    pub lane: LaneIdx,
+    pub v128: V128,
+    pub shuffle: ShuffleLanes,
+}
+///picks the immediate of a variant out of [SimdImmediates]
+trait FromImmediates {
+    fn from_immediates(immediates: SimdImmediates) -> Self;
+}
+impl FromImmediates for MemArg {
+    fn from_immediates(immediates: SimdImmediates) -> Self {
+        immediates.mem_arg
+    }
+}
+impl FromImmediates for LaneIdx {
+    fn from_immediates(immediates: SimdImmediates) -> Self {
+        immediates.lane
+    }
+}
+impl FromImmediates for V128 {
+    fn from_immediates(immediates: SimdImmediates) -> Self {
+        immediates.v128
+    }
+}
+impl FromImmediates for ShuffleLanes {
+    fn from_immediates(immediates: SimdImmediates) -> Self {
+        immediates.shuffle
+    }
+}
+impl FromImmediates for MemLane {
+    fn from_immediates(immediates: SimdImmediates) -> Self {
+        MemLane {
+            mem_arg: immediates.mem_arg,
+            lane: immediates.lane,
+        }
+    }
+}
+
+///maps mnemonics to instructions built from their immediates, and instructions back to their
+///mnemonic
+macro_rules! simd_mnemonics {
+    ($name:ident, $reverse:ident -> $ty:ident {
+        $($keyword:literal => $variant:ident $(($immediate:ty))?,)*
+    }) => {
+        pub(crate) fn $name(keyword: &str, immediates: SimdImmediates) -> Option<$ty> {
+            let instruction = match keyword {
+                $($keyword => $ty::$variant $((<$immediate>::from_immediates(immediates)))?,)*
+                _ => return None,
+            };
+            Some(instruction)
+        }
+        pub(crate) fn $reverse(instruction: &$ty) -> &'static str {
+            match instruction {
+                $($ty::$variant { .. } => $keyword,)*
+            }
+        }
+    };
+}
+
+simd_mnemonics!(simd, simd_keyword -> SimdInstruction {
+        "v128.load" => V128Load(MemArg),
+        "v128.load8x8_s" => V128Load8x8S(MemArg),
+        "v128.load8x8_u" => V128Load8x8U(MemArg),
+        "v128.load16x4_s" => V128Load16x4S(MemArg),
+        "v128.load16x4_u" => V128Load16x4U(MemArg),
+        "v128.load32x2_s" => V128Load32x2S(MemArg),
+        "v128.load32x2_u" => V128Load32x2U(MemArg),
+        "v128.load8_splat" => V128Load8Splat(MemArg),
+        "v128.load16_splat" => V128Load16Splat(MemArg),
+        "v128.load32_splat" => V128Load32Splat(MemArg),
+        "v128.load64_splat" => V128Load64Splat(MemArg),
+        "v128.store" => V128Store(MemArg),
+        "v128.const" => V128Const(V128),
+        "i8x16.shuffle" => I8x16Shuffle(ShuffleLanes),
+        "i8x16.swizzle" => I8x16Swizzle,
+        "i8x16.splat" => I8x16Splat,
+        "i16x8.splat" => I16x8Splat,
+        "i32x4.splat" => I32x4Splat,
+        "i64x2.splat" => I64x2Splat,
+        "f32x4.splat" => F32x4Splat,
+        "f64x2.splat" => F64x2Splat,
+        "i8x16.extract_lane_s" => I8x16ExtractLaneS(LaneIdx),
+        "i8x16.extract_lane_u" => I8x16ExtractLaneU(LaneIdx),
+        "i8x16.replace_lane" => I8x16ReplaceLane(LaneIdx),
+        "i16x8.extract_lane_s" => I16x8ExtractLaneS(LaneIdx),
+        "i16x8.extract_lane_u" => I16x8ExtractLaneU(LaneIdx),
+        "i16x8.replace_lane" => I16x8ReplaceLane(LaneIdx),
+        "i32x4.extract_lane" => I32x4ExtractLane(LaneIdx),
+        "i32x4.replace_lane" => I32x4ReplaceLane(LaneIdx),
+        "i64x2.extract_lane" => I64x2ExtractLane(LaneIdx),
+        "i64x2.replace_lane" => I64x2ReplaceLane(LaneIdx),
+        "f32x4.extract_lane" => F32x4ExtractLane(LaneIdx),
+        "f32x4.replace_lane" => F32x4ReplaceLane(LaneIdx),
+        "f64x2.extract_lane" => F64x2ExtractLane(LaneIdx),
+        "f64x2.replace_lane" => F64x2ReplaceLane(LaneIdx),
+        "i8x16.eq" => I8x16Eq,
+        "i8x16.ne" => I8x16Ne,
+        "i8x16.lt_s" => I8x16LtS,
+        "i8x16.lt_u" => I8x16LtU,
+        "i8x16.gt_s" => I8x16GtS,
+        "i8x16.gt_u" => I8x16GtU,
+        "i8x16.le_s" => I8x16LeS,
+        "i8x16.le_u" => I8x16LeU,
+        "i8x16.ge_s" => I8x16GeS,
+        "i8x16.ge_u" => I8x16GeU,
+        "i16x8.eq" => I16x8Eq,
+        "i16x8.ne" => I16x8Ne,
+        "i16x8.lt_s" => I16x8LtS,
+        "i16x8.lt_u" => I16x8LtU,
+        "i16x8.gt_s" => I16x8GtS,
+        "i16x8.gt_u" => I16x8GtU,
+        "i16x8.le_s" => I16x8LeS,
+        "i16x8.le_u" => I16x8LeU,
+        "i16x8.ge_s" => I16x8GeS,
+        "i16x8.ge_u" => I16x8GeU,
+        "i32x4.eq" => I32x4Eq,
+        "i32x4.ne" => I32x4Ne,
+        "i32x4.lt_s" => I32x4LtS,
+        "i32x4.lt_u" => I32x4LtU,
+        "i32x4.gt_s" => I32x4GtS,
+        "i32x4.gt_u" => I32x4GtU,
+        "i32x4.le_s" => I32x4LeS,
+        "i32x4.le_u" => I32x4LeU,
+        "i32x4.ge_s" => I32x4GeS,
+        "i32x4.ge_u" => I32x4GeU,
+        "f32x4.eq" => F32x4Eq,
+        "f32x4.ne" => F32x4Ne,
+        "f32x4.lt" => F32x4Lt,
+        "f32x4.gt" => F32x4Gt,
+        "f32x4.le" => F32x4Le,
+        "f32x4.ge" => F32x4Ge,
+        "f64x2.eq" => F64x2Eq,
+        "f64x2.ne" => F64x2Ne,
+        "f64x2.lt" => F64x2Lt,
+        "f64x2.gt" => F64x2Gt,
+        "f64x2.le" => F64x2Le,
+        "f64x2.ge" => F64x2Ge,
+        "v128.not" => V128Not,
+        "v128.and" => V128And,
+        "v128.andnot" => V128AndNot,
+        "v128.or" => V128Or,
+        "v128.xor" => V128Xor,
+        "v128.bitselect" => V128Bitselect,
+        "v128.any_true" => V128AnyTrue,
+        "v128.load8_lane" => V128Load8Lane(MemLane),
+        "v128.load16_lane" => V128Load16Lane(MemLane),
+        "v128.load32_lane" => V128Load32Lane(MemLane),
+        "v128.load64_lane" => V128Load64Lane(MemLane),
+        "v128.store8_lane" => V128Store8Lane(MemLane),
+        "v128.store16_lane" => V128Store16Lane(MemLane),
+        "v128.store32_lane" => V128Store32Lane(MemLane),
+        "v128.store64_lane" => V128Store64Lane(MemLane),
+        "v128.load32_zero" => V128Load32Zero(MemArg),
+        "v128.load64_zero" => V128Load64Zero(MemArg),
+        "f32x4.demote_f64x2_zero" => F32x4DemoteF64x2Zero,
+        "f64x2.promote_low_f32x4" => F64x2PromoteLowF32x4,
+        "i8x16.abs" => I8x16Abs,
+        "i8x16.neg" => I8x16Neg,
+        "i8x16.popcnt" => I8x16Popcnt,
+        "i8x16.all_true" => I8x16AllTrue,
+        "i8x16.bitmask" => I8x16Bitmask,
+        "i8x16.narrow_i16x8_s" => I8x16NarrowI16x8S,
+        "i8x16.narrow_i16x8_u" => I8x16NarrowI16x8U,
+        "f32x4.ceil" => F32x4Ceil,
+        "f32x4.floor" => F32x4Floor,
+        "f32x4.trunc" => F32x4Trunc,
+        "f32x4.nearest" => F32x4Nearest,
+        "i8x16.shl" => I8x16Shl,
+        "i8x16.shr_s" => I8x16ShrS,
+        "i8x16.shr_u" => I8x16ShrU,
+        "i8x16.add" => I8x16Add,
+        "i8x16.add_sat_s" => I8x16AddSatS,
+        "i8x16.add_sat_u" => I8x16AddSatU,
+        "i8x16.sub" => I8x16Sub,
+        "i8x16.sub_sat_s" => I8x16SubSatS,
+        "i8x16.sub_sat_u" => I8x16SubSatU,
+        "f64x2.ceil" => F64x2Ceil,
+        "f64x2.floor" => F64x2Floor,
+        "i8x16.min_s" => I8x16MinS,
+        "i8x16.min_u" => I8x16MinU,
+        "i8x16.max_s" => I8x16MaxS,
+        "i8x16.max_u" => I8x16MaxU,
+        "f64x2.trunc" => F64x2Trunc,
+        "i8x16.avgr_u" => I8x16AvgrU,
+        "i16x8.extadd_pairwise_i8x16_s" => I16x8ExtaddPairwiseI8x16S,
+        "i16x8.extadd_pairwise_i8x16_u" => I16x8ExtaddPairwiseI8x16U,
+        "i32x4.extadd_pairwise_i16x8_s" => I32x4ExtaddPairwiseI16x8S,
+        "i32x4.extadd_pairwise_i16x8_u" => I32x4ExtaddPairwiseI16x8U,
+        "i16x8.abs" => I16x8Abs,
+        "i16x8.neg" => I16x8Neg,
+        "i16x8.q15mulr_sat_s" => I16x8Q15mulrSatS,
+        "i16x8.all_true" => I16x8AllTrue,
+        "i16x8.bitmask" => I16x8Bitmask,
+        "i16x8.narrow_i32x4_s" => I16x8NarrowI32x4S,
+        "i16x8.narrow_i32x4_u" => I16x8NarrowI32x4U,
+        "i16x8.extend_low_i8x16_s" => I16x8ExtendLowI8x16S,
+        "i16x8.extend_high_i8x16_s" => I16x8ExtendHighI8x16S,
+        "i16x8.extend_low_i8x16_u" => I16x8ExtendLowI8x16U,
+        "i16x8.extend_high_i8x16_u" => I16x8ExtendHighI8x16U,
+        "i16x8.shl" => I16x8Shl,
+        "i16x8.shr_s" => I16x8ShrS,
+        "i16x8.shr_u" => I16x8ShrU,
+        "i16x8.add" => I16x8Add,
+        "i16x8.add_sat_s" => I16x8AddSatS,
+        "i16x8.add_sat_u" => I16x8AddSatU,
+        "i16x8.sub" => I16x8Sub,
+        "i16x8.sub_sat_s" => I16x8SubSatS,
+        "i16x8.sub_sat_u" => I16x8SubSatU,
+        "f64x2.nearest" => F64x2Nearest,
+        "i16x8.mul" => I16x8Mul,
+        "i16x8.min_s" => I16x8MinS,
+        "i16x8.min_u" => I16x8MinU,
+        "i16x8.max_s" => I16x8MaxS,
+        "i16x8.max_u" => I16x8MaxU,
+        "i16x8.avgr_u" => I16x8AvgrU,
+        "i16x8.extmul_low_i8x16_s" => I16x8ExtmulLowI8x16S,
+        "i16x8.extmul_high_i8x16_s" => I16x8ExtmulHighI8x16S,
+        "i16x8.extmul_low_i8x16_u" => I16x8ExtmulLowI8x16U,
+        "i16x8.extmul_high_i8x16_u" => I16x8ExtmulHighI8x16U,
+        "i32x4.abs" => I32x4Abs,
+        "i32x4.neg" => I32x4Neg,
+        "i32x4.all_true" => I32x4AllTrue,
+        "i32x4.bitmask" => I32x4Bitmask,
+        "i32x4.extend_low_i16x8_s" => I32x4ExtendLowI16x8S,
+        "i32x4.extend_high_i16x8_s" => I32x4ExtendHighI16x8S,
+        "i32x4.extend_low_i16x8_u" => I32x4ExtendLowI16x8U,
+        "i32x4.extend_high_i16x8_u" => I32x4ExtendHighI16x8U,
+        "i32x4.shl" => I32x4Shl,
+        "i32x4.shr_s" => I32x4ShrS,
+        "i32x4.shr_u" => I32x4ShrU,
+        "i32x4.add" => I32x4Add,
+        "i32x4.sub" => I32x4Sub,
+        "i32x4.mul" => I32x4Mul,
+        "i32x4.min_s" => I32x4MinS,
+        "i32x4.min_u" => I32x4MinU,
+        "i32x4.max_s" => I32x4MaxS,
+        "i32x4.max_u" => I32x4MaxU,
+        "i32x4.dot_i16x8_s" => I32x4DotI16x8S,
+        "i32x4.extmul_low_i16x8_s" => I32x4ExtmulLowI16x8S,
+        "i32x4.extmul_high_i16x8_s" => I32x4ExtmulHighI16x8S,
+        "i32x4.extmul_low_i16x8_u" => I32x4ExtmulLowI16x8U,
+        "i32x4.extmul_high_i16x8_u" => I32x4ExtmulHighI16x8U,
+        "i64x2.abs" => I64x2Abs,
+        "i64x2.neg" => I64x2Neg,
+        "i64x2.all_true" => I64x2AllTrue,
+        "i64x2.bitmask" => I64x2Bitmask,
+        "i64x2.extend_low_i32x4_s" => I64x2ExtendLowI32x4S,
+        "i64x2.extend_high_i32x4_s" => I64x2ExtendHighI32x4S,
+        "i64x2.extend_low_i32x4_u" => I64x2ExtendLowI32x4U,
+        "i64x2.extend_high_i32x4_u" => I64x2ExtendHighI32x4U,
+        "i64x2.shl" => I64x2Shl,
+        "i64x2.shr_s" => I64x2ShrS,
+        "i64x2.shr_u" => I64x2ShrU,
+        "i64x2.add" => I64x2Add,
+        "i64x2.sub" => I64x2Sub,
+        "i64x2.mul" => I64x2Mul,
+        "i64x2.eq" => I64x2Eq,
+        "i64x2.ne" => I64x2Ne,
+        "i64x2.lt_s" => I64x2LtS,
+        "i64x2.gt_s" => I64x2GtS,
+        "i64x2.le_s" => I64x2LeS,
+        "i64x2.ge_s" => I64x2GeS,
+        "i64x2.extmul_low_i32x4_s" => I64x2ExtmulLowI32x4S,
+        "i64x2.extmul_high_i32x4_s" => I64x2ExtmulHighI32x4S,
+        "i64x2.extmul_low_i32x4_u" => I64x2ExtmulLowI32x4U,
+        "i64x2.extmul_high_i32x4_u" => I64x2ExtmulHighI32x4U,
+        "f32x4.abs" => F32x4Abs,
+        "f32x4.neg" => F32x4Neg,
+        "f32x4.sqrt" => F32x4Sqrt,
+        "f32x4.add" => F32x4Add,
+        "f32x4.sub" => F32x4Sub,
+        "f32x4.mul" => F32x4Mul,
+        "f32x4.div" => F32x4Div,
+        "f32x4.min" => F32x4Min,
+        "f32x4.max" => F32x4Max,
+        "f32x4.pmin" => F32x4Pmin,
+        "f32x4.pmax" => F32x4Pmax,
+        "f64x2.abs" => F64x2Abs,
+        "f64x2.neg" => F64x2Neg,
+        "f64x2.sqrt" => F64x2Sqrt,
+        "f64x2.add" => F64x2Add,
+        "f64x2.sub" => F64x2Sub,
+        "f64x2.mul" => F64x2Mul,
+        "f64x2.div" => F64x2Div,
+        "f64x2.min" => F64x2Min,
+        "f64x2.max" => F64x2Max,
+        "f64x2.pmin" => F64x2Pmin,
+        "f64x2.pmax" => F64x2Pmax,
+        "i32x4.trunc_sat_f32x4_s" => I32x4TruncSatF32x4S,
+        "i32x4.trunc_sat_f32x4_u" => I32x4TruncSatF32x4U,
+        "f32x4.convert_i32x4_s" => F32x4ConvertI32x4S,
+        "f32x4.convert_i32x4_u" => F32x4ConvertI32x4U,
+        "i32x4.trunc_sat_f64x2_s_zero" => I32x4TruncSatF64x2SZero,
+        "i32x4.trunc_sat_f64x2_u_zero" => I32x4TruncSatF64x2UZero,
+        "f64x2.convert_low_i32x4_s" => F64x2ConvertLowI32x4S,
+        "f64x2.convert_low_i32x4_u" => F64x2ConvertLowI32x4U,
+});
+
+keywords!(relaxed_simd, relaxed_simd_keyword -> RelaxedSimdInstruction {
+        "i8x16.relaxed_swizzle" => I8x16RelaxedSwizzle,
+        "i32x4.relaxed_trunc_f32x4_s" => I32x4RelaxedTruncF32x4S,
+        "i32x4.relaxed_trunc_f32x4_u" => I32x4RelaxedTruncF32x4U,
+        "i32x4.relaxed_trunc_f64x2_s_zero" => I32x4RelaxedTruncF64x2SZero,
+        "i32x4.relaxed_trunc_f64x2_u_zero" => I32x4RelaxedTruncF64x2UZero,
+        "f32x4.relaxed_madd" => F32x4RelaxedMadd,
+        "f32x4.relaxed_nmadd" => F32x4RelaxedNmadd,
+        "f64x2.relaxed_madd" => F64x2RelaxedMadd,
+        "f64x2.relaxed_nmadd" => F64x2RelaxedNmadd,
+        "i8x16.relaxed_laneselect" => I8x16RelaxedLaneselect,
+        "i16x8.relaxed_laneselect" => I16x8RelaxedLaneselect,
+        "i32x4.relaxed_laneselect" => I32x4RelaxedLaneselect,
+        "i64x2.relaxed_laneselect" => I64x2RelaxedLaneselect,
+        "f32x4.relaxed_min" => F32x4RelaxedMin,
+        "f32x4.relaxed_max" => F32x4RelaxedMax,
+        "f64x2.relaxed_min" => F64x2RelaxedMin,
+        "f64x2.relaxed_max" => F64x2RelaxedMax,
+        "i16x8.relaxed_q15mulr_s" => I16x8RelaxedQ15mulrS,
+        "i16x8.relaxed_dot_i8x16_i7x16_s" => I16x8RelaxedDotI8x16I7x16S,
+        "i32x4.relaxed_dot_i8x16_i7x16_add_s" => I32x4RelaxedDotI8x16I7x16AddS,
+});
+
+type Constructor<T> = fn(MemArg) -> T;
+///maps memory accesses to their constructor and the log2 of their natural alignment, and back
+macro_rules! memory_keywords {
+    ($name:ident, $reverse:ident -> $ty:ident {
+        $($keyword:literal => ($variant:ident, $align:literal),)*
+    }) => {
+        pub(crate) fn $name(keyword: &str) -> Option<(Constructor<$ty>, u32)> {
+            let (constructor, align): (Constructor<$ty>, u32) = match keyword {
+                $($keyword => ($ty::$variant, $align),)*
+                _ => return None,
+            };
+            Some((constructor, align))
+        }
+        pub(crate) fn $reverse(instruction: &$ty) -> Option<(&'static str, &MemArg, u32)> {
+            let keyword = match instruction {
+                $($ty::$variant(mem_arg) => ($keyword, mem_arg, $align),)*
+                #[allow(unreachable_patterns)]
+                _ => return None,
+            };
+            Some(keyword)
+        }
+    };
+}
+
+memory_keywords!(memory, memory_keyword -> MemoryInstruction {
+    "i32.load" => (I32Load, 2),
+    "i64.load" => (I64Load, 3),
+    "f32.load" => (F32Load, 2),
+    "f64.load" => (F64Load, 3),
+    "i32.load8_s" => (I32Load8S, 0),
+    "i32.load8_u" => (I32Load8U, 0),
+    "i32.load16_s" => (I32Load16S, 1),
+    "i32.load16_u" => (I32Load16U, 1),
+    "i64.load8_s" => (I64Load8S, 0),
+    "i64.load8_u" => (I64Load8U, 0),
+    "i64.load16_s" => (I64Load16S, 1),
+    "i64.load16_u" => (I64Load16U, 1),
+    "i64.load32_s" => (I64Load32S, 2),
+    "i64.load32_u" => (I64Load32U, 2),
+    "i32.store" => (I32Store, 2),
+    "i64.store" => (I64Store, 3),
+    "f32.store" => (F32Store, 2),
+    "f64.store" => (F64Store, 3),
+    "i32.store8" => (I32Store8, 0),
+    "i32.store16" => (I32Store16, 1),
+    "i64.store8" => (I64Store8, 0),
+    "i64.store16" => (I64Store16, 1),
+    "i64.store32" => (I64Store32, 2),
+});
+
+memory_keywords!(atomic, atomic_keyword -> AtomicInstruction {
+        "memory.atomic.notify" => (MemoryAtomicNotify, 2),
+        "memory.atomic.wait32" => (MemoryAtomicWait32, 2),
+        "memory.atomic.wait64" => (MemoryAtomicWait64, 3),
+        "i32.atomic.load" => (I32AtomicLoad, 2),
+        "i64.atomic.load" => (I64AtomicLoad, 3),
+        "i32.atomic.load8_u" => (I32AtomicLoad8U, 0),
+        "i32.atomic.load16_u" => (I32AtomicLoad16U, 1),
+        "i64.atomic.load8_u" => (I64AtomicLoad8U, 0),
+        "i64.atomic.load16_u" => (I64AtomicLoad16U, 1),
+        "i64.atomic.load32_u" => (I64AtomicLoad32U, 2),
+        "i32.atomic.store" => (I32AtomicStore, 2),
+        "i64.atomic.store" => (I64AtomicStore, 3),
+        "i32.atomic.store8" => (I32AtomicStore8, 0),
+        "i32.atomic.store16" => (I32AtomicStore16, 1),
+        "i64.atomic.store8" => (I64AtomicStore8, 0),
+        "i64.atomic.store16" => (I64AtomicStore16, 1),
+        "i64.atomic.store32" => (I64AtomicStore32, 2),
+        "i32.atomic.rmw.add" => (I32AtomicRmwAdd, 2),
+        "i64.atomic.rmw.add" => (I64AtomicRmwAdd, 3),
+        "i32.atomic.rmw8.add_u" => (I32AtomicRmw8AddU, 0),
+        "i32.atomic.rmw16.add_u" => (I32AtomicRmw16AddU, 1),
+        "i64.atomic.rmw8.add_u" => (I64AtomicRmw8AddU, 0),
+        "i64.atomic.rmw16.add_u" => (I64AtomicRmw16AddU, 1),
+        "i64.atomic.rmw32.add_u" => (I64AtomicRmw32AddU, 2),
+        "i32.atomic.rmw.sub" => (I32AtomicRmwSub, 2),
+        "i64.atomic.rmw.sub" => (I64AtomicRmwSub, 3),
+        "i32.atomic.rmw8.sub_u" => (I32AtomicRmw8SubU, 0),
+        "i32.atomic.rmw16.sub_u" => (I32AtomicRmw16SubU, 1),
+        "i64.atomic.rmw8.sub_u" => (I64AtomicRmw8SubU, 0),
+        "i64.atomic.rmw16.sub_u" => (I64AtomicRmw16SubU, 1),
+        "i64.atomic.rmw32.sub_u" => (I64AtomicRmw32SubU, 2),
+        "i32.atomic.rmw.and" => (I32AtomicRmwAnd, 2),
+        "i64.atomic.rmw.and" => (I64AtomicRmwAnd, 3),
+        "i32.atomic.rmw8.and_u" => (I32AtomicRmw8AndU, 0),
+        "i32.atomic.rmw16.and_u" => (I32AtomicRmw16AndU, 1),
+        "i64.atomic.rmw8.and_u" => (I64AtomicRmw8AndU, 0),
+        "i64.atomic.rmw16.and_u" => (I64AtomicRmw16AndU, 1),
+        "i64.atomic.rmw32.and_u" => (I64AtomicRmw32AndU, 2),
+        "i32.atomic.rmw.or" => (I32AtomicRmwOr, 2),
+        "i64.atomic.rmw.or" => (I64AtomicRmwOr, 3),
+        "i32.atomic.rmw8.or_u" => (I32AtomicRmw8OrU, 0),
+        "i32.atomic.rmw16.or_u" => (I32AtomicRmw16OrU, 1),
+        "i64.atomic.rmw8.or_u" => (I64AtomicRmw8OrU, 0),
+        "i64.atomic.rmw16.or_u" => (I64AtomicRmw16OrU, 1),
+        "i64.atomic.rmw32.or_u" => (I64AtomicRmw32OrU, 2),
+        "i32.atomic.rmw.xor" => (I32AtomicRmwXor, 2),
+        "i64.atomic.rmw.xor" => (I64AtomicRmwXor, 3),
+        "i32.atomic.rmw8.xor_u" => (I32AtomicRmw8XorU, 0),
+        "i32.atomic.rmw16.xor_u" => (I32AtomicRmw16XorU, 1),
+        "i64.atomic.rmw8.xor_u" => (I64AtomicRmw8XorU, 0),
+        "i64.atomic.rmw16.xor_u" => (I64AtomicRmw16XorU, 1),
+        "i64.atomic.rmw32.xor_u" => (I64AtomicRmw32XorU, 2),
+        "i32.atomic.rmw.xchg" => (I32AtomicRmwXchg, 2),
+        "i64.atomic.rmw.xchg" => (I64AtomicRmwXchg, 3),
+        "i32.atomic.rmw8.xchg_u" => (I32AtomicRmw8XchgU, 0),
+        "i32.atomic.rmw16.xchg_u" => (I32AtomicRmw16XchgU, 1),
+        "i64.atomic.rmw8.xchg_u" => (I64AtomicRmw8XchgU, 0),
+        "i64.atomic.rmw16.xchg_u" => (I64AtomicRmw16XchgU, 1),
+        "i64.atomic.rmw32.xchg_u" => (I64AtomicRmw32XchgU, 2),
+        "i32.atomic.rmw.cmpxchg" => (I32AtomicRmwCmpxchg, 2),
+        "i64.atomic.rmw.cmpxchg" => (I64AtomicRmwCmpxchg, 3),
+        "i32.atomic.rmw8.cmpxchg_u" => (I32AtomicRmw8CmpxchgU, 0),
+        "i32.atomic.rmw16.cmpxchg_u" => (I32AtomicRmw16CmpxchgU, 1),
+        "i64.atomic.rmw8.cmpxchg_u" => (I64AtomicRmw8CmpxchgU, 0),
+        "i64.atomic.rmw16.cmpxchg_u" => (I64AtomicRmw16CmpxchgU, 1),
+        "i64.atomic.rmw32.cmpxchg_u" => (I64AtomicRmw32CmpxchgU, 2),
+});
@@ -0,0 +1,192 @@
+use super::{Error, Result};
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_while1, take_while_m_n},
+    character::complete::{char, multispace1, not_line_ending},
+    combinator::{map, map_opt, opt, value},
+    error::{context, ContextError, ErrorKind, ParseError, VerboseError, VerboseErrorKind},
+    multi::many0_count,
+    sequence::{delimited, pair, preceded},
+    IResult,
+};
+
+type Res<'a, T> = IResult<&'a str, T, VerboseError<&'a str>>;
+
+#[derive(Debug)]
+pub(crate) enum Atom<'a> {
+    ///keywords, numbers and any other unquoted token
+    Keyword(&'a str),
+    ///`$name`, without the `$`
+    Id(&'a str),
+    String(Vec<u8>),
+}
+///a token or a parenthesized list, with the byte offset it starts at
+#[derive(Debug)]
+pub(crate) enum Sexp<'a> {
+    Atom(Atom<'a>, usize),
+    List(Vec<Sexp<'a>>, usize),
+}
+impl<'a> Sexp<'a> {
+    pub fn offset(&self) -> usize {
+        match self {
+            Sexp::Atom(_, offset) | Sexp::List(_, offset) => *offset,
+        }
+    }
+}
+
+pub(crate) fn is_idchar(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-./:<=>?@\\^_`|~".contains(c)
+}
+
+fn failure<'a, T>(i: &'a str, message: &'static str) -> Res<'a, T> {
+    let error = VerboseError::from_error_kind(i, ErrorKind::Verify);
+    Err(nom::Err::Failure(VerboseError::add_context(
+        i, message, error,
+    )))
+}
+
+fn line_comment<'a>(i: &'a str) -> Res<'a, ()> {
+    value((), pair(tag(";;"), not_line_ending))(i)
+}
+///block comments nest
+fn block_comment<'a>(i: &'a str) -> Res<'a, ()> {
+    let start = i;
+    let (mut i, _) = tag("(;")(i)?;
+    loop {
+        if let Some(rest) = i.strip_prefix(";)") {
+            return Ok((rest, ()));
+        }
+        if i.starts_with("(;") {
+            i = block_comment(i)?.0;
+            continue;
+        }
+        let mut chars = i.chars();
+        if chars.next().is_none() {
+            return failure(start, "unterminated block comment");
+        }
+        i = chars.as_str();
+    }
+}
+fn space<'a>(i: &'a str) -> Res<'a, ()> {
+    value(
+        (),
+        many0_count(alt((value((), multispace1), line_comment, block_comment))),
+    )(i)
+}
+
+fn escaped_byte<'a>(i: &'a str) -> Res<'a, u8> {
+    let hex = map_opt(
+        take_while_m_n(2, 2, |c: char| c.is_ascii_hexdigit()),
+        |hex| u8::from_str_radix(hex, 16).ok(),
+    );
+    alt((
+        value(b'\t', char('t')),
+        value(b'\n', char('n')),
+        value(b'\r', char('r')),
+        value(b'"', char('"')),
+        value(b'\'', char('\'')),
+        value(b'\\', char('\\')),
+        hex,
+    ))(i)
+}
+fn escaped_char<'a>(i: &'a str) -> Res<'a, char> {
+    let hex = take_while1(|c: char| c.is_ascii_hexdigit());
+    map_opt(delimited(tag("u{"), hex, char('}')), |hex| {
+        u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+    })(i)
+}
+///the escape sequence after a `\\`
+fn escape<'a>(i: &'a str, out: &mut Vec<u8>) -> Res<'a, ()> {
+    if let Ok((i, byte)) = escaped_byte(i) {
+        out.push(byte);
+        return Ok((i, ()));
+    }
+    if let Ok((i, c)) = escaped_char(i) {
+        out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+        return Ok((i, ()));
+    }
+    failure(i, "invalid escape sequence")
+}
+fn string<'a>(i: &'a str) -> Res<'a, Vec<u8>> {
+    let start = i;
+    let (mut i, _) = char('"')(i)?;
+    let mut bytes = vec![];
+    loop {
+        let mut chars = i.chars();
+        match chars.next() {
+            None | Some('\n') => return failure(start, "unterminated string"),
+            Some('"') => return Ok((chars.as_str(), bytes)),
+            Some('\\') => i = escape(chars.as_str(), &mut bytes)?.0,
+            Some(c) => {
+                bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                i = chars.as_str();
+            }
+        }
+    }
+}
+fn atom<'a>(i: &'a str) -> Res<'a, Atom<'a>> {
+    let id = map(preceded(char('$'), take_while1(is_idchar)), Atom::Id);
+    let keyword = map(take_while1(is_idchar), Atom::Keyword);
+    let string = map(string, Atom::String);
+    context("unexpected character", alt((string, id, keyword)))(i)
+}
+
+///parses one s-expression, `source` is used to compute offsets
+fn sexp<'a>(source: &'a str, i: &'a str) -> Res<'a, Sexp<'a>> {
+    let offset = source.len() - i.len();
+    let (mut i, open) = opt(char('('))(i)?;
+    if open.is_none() {
+        let (i, atom) = atom(i)?;
+        return Ok((i, Sexp::Atom(atom, offset)));
+    }
+    let mut items = vec![];
+    loop {
+        i = space(i)?.0;
+        if let Some(rest) = i.strip_prefix(')') {
+            return Ok((rest, Sexp::List(items, offset)));
+        }
+        if i.is_empty() {
+            return failure(&source[offset..], "unclosed parenthesis");
+        }
+        let (rest, item) = sexp(source, i)?;
+        items.push(item);
+        i = rest;
+    }
+}
+
+fn sexps<'a>(source: &'a str) -> Res<'a, Vec<Sexp<'a>>> {
+    let mut sexps = vec![];
+    let mut i = space(source)?.0;
+    while !i.is_empty() {
+        if i.starts_with(')') {
+            return failure(i, "unexpected closing parenthesis");
+        }
+        let (rest, sexp) = sexp(source, i)?;
+        sexps.push(sexp);
+        i = space(rest)?.0;
+    }
+    Ok((i, sexps))
+}
+
+///splits the source into s-expressions
+pub(crate) fn parse<'a>(source: &'a str) -> Result<Vec<Sexp<'a>>> {
+    let (_, sexps) = sexps(source).map_err(|error| {
+        let error = match error {
+            nom::Err::Error(error) | nom::Err::Failure(error) => error,
+            nom::Err::Incomplete(_) => unreachable!("complete parsers only"),
+        };
+        let (rest, message) = error
+            .errors
+            .iter()
+            .find_map(|(rest, kind)| match kind {
+                VerboseErrorKind::Context(message) => Some((*rest, *message)),
+                _ => None,
+            })
+            .unwrap_or((error.errors[0].0, "unexpected character"));
+        Error {
+            offset: source.len() - rest.len(),
+            message: message.to_string(),
+        }
+    })?;
+    Ok(sexps)
+}
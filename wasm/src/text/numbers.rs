@@ -0,0 +1,259 @@
+//!numeric literals, `None` for malformed or out of range ones
+use std::convert::TryInto;
+
+fn split_sign(text: &str) -> (bool, &str) {
+    match text.as_bytes().first() {
+        Some(b'-') => (true, &text[1..]),
+        Some(b'+') => (false, &text[1..]),
+        _ => (false, text),
+    }
+}
+///removes the `_` separators, which may only appear between digits
+fn digits(text: &str, hex: bool) -> Option<String> {
+    let is_digit = |c: char| {
+        if hex {
+            c.is_ascii_hexdigit()
+        } else {
+            c.is_ascii_digit()
+        }
+    };
+    let mut previous_digit = false;
+    let mut digits = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == '_' {
+            if !previous_digit {
+                return None;
+            }
+            previous_digit = false;
+            continue;
+        }
+        previous_digit = is_digit(c);
+        digits.push(c);
+    }
+    if previous_digit {
+        Some(digits)
+    } else {
+        None
+    }
+}
+fn magnitude(text: &str) -> Option<u64> {
+    let (radix, text) = match text.strip_prefix("0x") {
+        Some(hex) => (16, hex),
+        None => (10, text),
+    };
+    let digits = digits(text, radix == 16)?;
+    if !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    u64::from_str_radix(&digits, radix).ok()
+}
+
+pub(crate) fn u64(text: &str) -> Option<u64> {
+    magnitude(text)
+}
+pub(crate) fn u32(text: &str) -> Option<u32> {
+    magnitude(text)?.try_into().ok()
+}
+///accepts both the signed and the unsigned range
+pub(crate) fn i32(text: &str) -> Option<i32> {
+    match split_sign(text) {
+        (true, text) => {
+            let magnitude = magnitude(text)?;
+            (magnitude <= 1 << 31).then(|| (magnitude as i64).wrapping_neg() as i32)
+        }
+        (false, text) => u32(text).map(|value| value as i32),
+    }
+}
+///accepts both the signed and the unsigned range
+pub(crate) fn i64(text: &str) -> Option<i64> {
+    match split_sign(text) {
+        (true, text) => {
+            let magnitude = magnitude(text)?;
+            (magnitude <= 1 << 63).then(|| (magnitude as i64).wrapping_neg())
+        }
+        (false, text) => magnitude(text).map(|value| value as i64),
+    }
+}
+///an integer narrower than 32 bits, in either range, as its two's complement bits
+pub(crate) fn narrow(text: &str, bits: u32) -> Option<u32> {
+    let value = i64(text)?;
+    let range = -(1 << (bits - 1))..1 << bits;
+    range
+        .contains(&value)
+        .then(|| value as u32 & ((1 << bits) - 1))
+}
+
+pub(crate) fn f32(text: &str) -> Option<f32> {
+    float(text, 23, 8).map(|bits| f32::from_bits(bits as u32))
+}
+pub(crate) fn f64(text: &str) -> Option<f64> {
+    float(text, 52, 11).map(f64::from_bits)
+}
+
+///bit pattern of a float with the given field widths
+fn float(text: &str, mantissa_bits: u32, exponent_bits: u32) -> Option<u64> {
+    let (negative, text) = split_sign(text);
+    let sign = (negative as u64) << (mantissa_bits + exponent_bits);
+    let infinity = ((1 << exponent_bits) - 1) << mantissa_bits;
+    let bits = match text {
+        "inf" => infinity,
+        "nan" => infinity | 1 << (mantissa_bits - 1),
+        _ => match (text.strip_prefix("nan:0x"), text.strip_prefix("0x")) {
+            (Some(payload), _) => {
+                let payload = u64::from_str_radix(&digits(payload, true)?, 16).ok()?;
+                if payload == 0 || payload >> mantissa_bits != 0 {
+                    return None;
+                }
+                infinity | payload
+            }
+            (None, Some(hex)) => hex_float(hex, mantissa_bits, exponent_bits)?,
+            (None, None) => decimal_float(text, mantissa_bits)?,
+        },
+    };
+    Some(sign | bits)
+}
+
+fn decimal_float(text: &str, mantissa_bits: u32) -> Option<u64> {
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+        Some(e) => (&text[..e], Some(&text[e + 1..])),
+        None => (text, None),
+    };
+    let (int, fraction) = match mantissa.split_once('.') {
+        Some((int, fraction)) => (int, Some(fraction)),
+        None => (mantissa, None),
+    };
+    let mut cleaned = digits(int, false)?;
+    if let Some(fraction) = fraction.filter(|fraction| !fraction.is_empty()) {
+        cleaned.push('.');
+        cleaned.push_str(&digits(fraction, false)?);
+    }
+    if let Some(exponent) = exponent {
+        let (negative, exponent) = split_sign(exponent);
+        cleaned.push('e');
+        if negative {
+            cleaned.push('-');
+        }
+        cleaned.push_str(&digits(exponent, false)?);
+    }
+    //rust's float parsing rounds correctly
+    let bits = if mantissa_bits == 23 {
+        let value: f32 = cleaned.parse().ok()?;
+        (!value.is_infinite()).then(|| value.to_bits() as u64)?
+    } else {
+        let value: f64 = cleaned.parse().ok()?;
+        (!value.is_infinite()).then(|| value.to_bits())?
+    };
+    Some(bits)
+}
+
+///`hex` is the literal after `0x`, rounded to nearest even
+fn hex_float(hex: &str, mantissa_bits: u32, exponent_bits: u32) -> Option<u64> {
+    let (mantissa, exponent) = match hex.find(['p', 'P']) {
+        Some(p) => (&hex[..p], Some(&hex[p + 1..])),
+        None => (hex, None),
+    };
+    let mut exponent: i64 = match exponent {
+        Some(exponent) => {
+            let (negative, exponent) = split_sign(exponent);
+            let exponent: i64 = digits(exponent, false)?.parse().ok()?;
+            let exponent = exponent.min(100_000);
+            if negative {
+                -exponent
+            } else {
+                exponent
+            }
+        }
+        None => 0,
+    };
+    let (int, fraction) = match mantissa.split_once('.') {
+        Some((int, fraction)) => (digits(int, true)?, fraction),
+        None => (digits(mantissa, true)?, ""),
+    };
+    let fraction = if fraction.is_empty() {
+        String::new()
+    } else {
+        digits(fraction, true)?
+    };
+    //value is significand * 2^exponent, sticky records dropped non zero digits
+    let mut significand: u64 = 0;
+    let mut sticky = false;
+    for (index, c) in int.chars().chain(fraction.chars()).enumerate() {
+        let digit = c.to_digit(16)? as u64;
+        let fractional = index >= int.len();
+        if significand >> 60 == 0 {
+            significand = significand << 4 | digit;
+            if fractional {
+                exponent -= 4;
+            }
+        } else {
+            sticky |= digit != 0;
+            if !fractional {
+                exponent += 4;
+            }
+        }
+    }
+    if significand == 0 {
+        return Some(0);
+    }
+    let leading = significand.leading_zeros();
+    significand <<= leading;
+    //unbiased exponent of the leading bit
+    let exponent = exponent - leading as i64 + 63;
+    let bias = (1 << (exponent_bits - 1)) - 1;
+    if exponent > bias {
+        return None;
+    }
+    let min_exponent = 1 - bias;
+    let kept_bits = if exponent >= min_exponent {
+        mantissa_bits as i64 + 1
+    } else {
+        mantissa_bits as i64 + 1 - (min_exponent - exponent)
+    };
+    let shift = 64 - kept_bits;
+    if shift > 64 {
+        return Some(0);
+    }
+    let shift = shift as u32;
+    let significand = significand as u128;
+    let mut kept = (significand >> shift) as u64;
+    let rest = significand & ((1 << shift) - 1);
+    let half = 1 << (shift - 1);
+    if rest > half || (rest == half && (sticky || kept & 1 == 1)) {
+        kept += 1;
+    }
+    if exponent < min_exponent {
+        //a subnormal that rounds up to 2^mantissa_bits has the exponent field 1
+        return Some(kept);
+    }
+    let (kept, exponent) = if kept >> (mantissa_bits + 1) != 0 {
+        (kept >> 1, exponent + 1)
+    } else {
+        (kept, exponent)
+    };
+    if exponent > bias {
+        return None;
+    }
+    let mantissa = kept & ((1 << mantissa_bits) - 1);
+    Some(((exponent + bias) as u64) << mantissa_bits | mantissa)
+}
+
+#[test]
+fn literals() {
+    assert_eq!(i32("-0x8000_0000"), Some(i32::MIN));
+    assert_eq!(i32("0xFFFF_FFFF"), Some(-1));
+    assert_eq!(i32("4294967296"), None);
+    assert_eq!(i32("1__0"), None);
+    assert_eq!(i64("-9223372036854775808"), Some(i64::MIN));
+    assert_eq!(u32("-1"), None);
+    assert_eq!(f32("0x1.8p1"), Some(3.0));
+    assert_eq!(f64("-0x1p-1074"), Some(-f64::from_bits(1)));
+    assert_eq!(f32("0x1.fffffep127"), Some(f32::MAX));
+    assert_eq!(f32("0x1.ffffffp127"), None);
+    assert_eq!(f32("0x1.000001p0"), Some(1.0));
+    assert_eq!(f32("0x1.000003p0"), Some(1.0000002));
+    assert_eq!(f64("1_000.5e-1"), Some(100.05));
+    assert_eq!(f32("1e39"), None);
+    assert_eq!(f64("-inf"), Some(f64::NEG_INFINITY));
+    assert_eq!(f32("nan:0x200000").map(f32::to_bits), Some(0x7FA0_0000));
+    assert!(f64("nan").unwrap().is_nan());
+}
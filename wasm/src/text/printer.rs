@@ -0,0 +1,900 @@
+use super::keywords;
+use crate::{
+    instructions::{
+        atomic::AtomicInstruction,
+        control::{BlockType, Catch, ControlInstruction},
+        memory::{MemArg, MemoryInstruction},
+        numeric::NumericInstruction,
+        parametric::ParametricInstruction,
+        reference::ReferenceInstruction,
+        simd::SimdInstruction,
+        table::TableInstruction,
+        variable::VariableInstruction,
+        Expression, Instruction,
+    },
+    modules::{
+        code::Code,
+        data::Data,
+        element::Elem,
+        export::ExportDescriptor,
+        import::ImportDescriptor,
+        indices::{FuncIdx, LabelIdx, MemIdx, TagIdx, TypeIdx},
+        names::{IndirectNameMap, NameMap, NameSection},
+        Module, Section,
+    },
+    types::{
+        FuncType, GlobalType, IndexType, Limit, MemType, Mutability, NumType, RefType, TableType,
+        ValueType,
+    },
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+const INDENT: &str = "  ";
+
+type Ids = HashMap<u32, String>;
+///the names of the `name` section as `$id`s
+#[derive(Default)]
+struct Names {
+    funcs: Ids,
+    locals: HashMap<FuncIdx, Ids>,
+    labels: HashMap<FuncIdx, Ids>,
+    types: Ids,
+    tables: Ids,
+    memories: Ids,
+    globals: Ids,
+    elems: Ids,
+    datas: Ids,
+    tags: Ids,
+}
+///keeps the first of the names that print the same
+fn ids(map: &NameMap) -> Ids {
+    let mut taken = HashSet::new();
+    let mut ids = Ids::new();
+    for assoc in &map.0 {
+        let id = identifier(assoc.name.as_bytes());
+        if !id.is_empty() && taken.insert(id.clone()) {
+            ids.insert(assoc.index, id);
+        }
+    }
+    ids
+}
+fn indirect_ids(map: &IndirectNameMap) -> HashMap<u32, Ids> {
+    map.0
+        .iter()
+        .map(|assoc| (assoc.index, ids(&assoc.names)))
+        .collect()
+}
+impl From<&NameSection<'_>> for Names {
+    fn from(names: &NameSection) -> Self {
+        Self {
+            funcs: ids(&names.funcs),
+            locals: indirect_ids(&names.locals),
+            labels: indirect_ids(&names.labels),
+            types: ids(&names.types),
+            tables: ids(&names.tables),
+            memories: ids(&names.memories),
+            globals: ids(&names.globals),
+            elems: ids(&names.elems),
+            datas: ids(&names.datas),
+            tags: ids(&names.tags),
+        }
+    }
+}
+///`$name` of an entry, or its index
+fn reference(ids: &Ids, index: u32) -> String {
+    match ids.get(&index) {
+        Some(name) => format!("${}", name),
+        None => index.to_string(),
+    }
+}
+///`$name (;index;)` of an entry being defined, or just the comment
+fn definition(ids: &Ids, index: u32) -> String {
+    match ids.get(&index) {
+        Some(name) => format!("${} (;{};)", name, index),
+        None => format!("(;{};)", index),
+    }
+}
+///a name made into an `$id`, replacing the characters ids can't contain
+fn identifier(name: &[u8]) -> String {
+    String::from_utf8_lossy(name)
+        .chars()
+        .map(|c| if super::lexer::is_idchar(c) { c } else { '_' })
+        .collect()
+}
+
+///a quoted string, escaping anything but printable ascii
+fn string(bytes: &[u8]) -> String {
+    let mut string = String::from("\"");
+    for byte in bytes {
+        match byte {
+            b'"' | b'\\' => write!(string, "\\{}", *byte as char).unwrap(),
+            b' '..=b'~' => string.push(*byte as char),
+            _ => write!(string, "\\{:02x}", byte).unwrap(),
+        }
+    }
+    string.push('"');
+    string
+}
+///the exact hexadecimal form of a float given its bit pattern
+fn float(bits: u64, mantissa_bits: u32, exponent_bits: u32) -> String {
+    let sign = if bits >> (mantissa_bits + exponent_bits) & 1 == 1 {
+        "-"
+    } else {
+        ""
+    };
+    let max_exponent = (1 << exponent_bits) - 1;
+    let exponent = (bits >> mantissa_bits) & max_exponent;
+    let mantissa = bits & ((1 << mantissa_bits) - 1);
+    let bias = (max_exponent >> 1) as i64;
+    let hex = |fraction: u64, exponent: i64| {
+        let digits = (mantissa_bits as usize).div_ceil(4);
+        let fraction = fraction << (digits * 4 - mantissa_bits as usize);
+        let fraction = format!("{:0width$x}", fraction, width = digits);
+        match fraction.trim_end_matches('0') {
+            "" => format!("0x1p{:+}", exponent),
+            fraction => format!("0x1.{}p{:+}", fraction, exponent),
+        }
+    };
+    let magnitude = match (exponent, mantissa) {
+        (0, 0) => "0x0p+0".to_string(),
+        (exponent, 0) if exponent == max_exponent => "inf".to_string(),
+        (exponent, mantissa) if exponent == max_exponent => {
+            if mantissa == 1 << (mantissa_bits - 1) {
+                "nan".to_string()
+            } else {
+                format!("nan:0x{:x}", mantissa)
+            }
+        }
+        (0, mantissa) => {
+            //subnormals are normalized, moving the leading one to the implicit bit
+            let shift = mantissa.leading_zeros() - (63 - mantissa_bits);
+            let fraction = (mantissa << shift) & ((1 << mantissa_bits) - 1);
+            hex(fraction, 1 - bias - shift as i64)
+        }
+        (exponent, mantissa) => hex(mantissa, exponent as i64 - bias),
+    };
+    format!("{}{}", sign, magnitude)
+}
+
+fn value_type(value_type: &ValueType) -> &'static str {
+    match value_type {
+        ValueType::NumType(NumType::I32) => "i32",
+        ValueType::NumType(NumType::I64) => "i64",
+        ValueType::NumType(NumType::F32) => "f32",
+        ValueType::NumType(NumType::F64) => "f64",
+        ValueType::V128 => "v128",
+        ValueType::RefType(ref_type) => self::ref_type(ref_type),
+    }
+}
+fn ref_type(ref_type: &RefType) -> &'static str {
+    match ref_type {
+        RefType::FuncRef => "funcref",
+        RefType::ExternRef => "externref",
+        RefType::ExnRef => "exnref",
+    }
+}
+fn limits(limit: &Limit) -> String {
+    match limit.max {
+        Some(max) => format!("{} {}", limit.min, max),
+        None => limit.min.to_string(),
+    }
+}
+fn mem_type(mem_type: &MemType) -> String {
+    let mut text = limits(&mem_type.lim);
+    if mem_type.lim.index_type == IndexType::I64 {
+        text.insert_str(0, "i64 ");
+    }
+    if mem_type.shared {
+        text.push_str(" shared");
+    }
+    text
+}
+fn table_type(table_type: &TableType) -> String {
+    format!("{} {}", limits(&table_type.lim), ref_type(&table_type.et))
+}
+fn global_type(global_type: &GlobalType) -> String {
+    match global_type.m {
+        Mutability::Const => value_type(&global_type.t).to_string(),
+        Mutability::Var => format!("(mut {})", value_type(&global_type.t)),
+    }
+}
+///`(param ...)` or `(result ...)`, empty without types
+fn types(keyword: &str, types: &[ValueType]) -> String {
+    if types.is_empty() {
+        return String::new();
+    }
+    let types: Vec<_> = types.iter().map(value_type).collect();
+    format!(" ({} {})", keyword, types.join(" "))
+}
+fn signature(func_type: &FuncType) -> String {
+    types("param", &func_type.rt1) + &types("result", &func_type.rt2)
+}
+
+struct Printer<'m> {
+    out: String,
+    names: Names,
+    types: Vec<&'m FuncType>,
+    ///type of every function, imported ones first
+    funcs: Vec<TypeIdx>,
+    ///tables, memories, globals and tags printed so far, imports included
+    tables: u32,
+    memories: u32,
+    globals: u32,
+    tags: u32,
+    ///labels of the enclosing blocks
+    labels: Vec<Option<String>>,
+    ///blocks printed so far in the current function, which number the label names
+    blocks: u32,
+}
+
+///renders `module` in the text format
+pub(crate) fn module(module: &Module) -> String {
+    let mut printer = Printer {
+        out: String::from("(module\n"),
+        names: Names::default(),
+        types: vec![],
+        funcs: vec![],
+        tables: 0,
+        memories: 0,
+        globals: 0,
+        tags: 0,
+        labels: vec![],
+        blocks: 0,
+    };
+    if let Some(names) = module.names() {
+        printer.names = Names::from(&names);
+    }
+    for section in &module.sections {
+        match section {
+            Section::TypeSection(types) => printer.types.extend(&types.0),
+            Section::ImportSection(imports) => {
+                let funcs = imports
+                    .0
+                    .iter()
+                    .filter_map(|import| match import.descriptor {
+                        ImportDescriptor::Func(type_idx) => Some(type_idx),
+                        _ => None,
+                    });
+                printer.funcs.extend(funcs);
+            }
+            Section::FunctionSection(functions) => printer.funcs.extend(&functions.0),
+            _ => {}
+        }
+    }
+    for section in &module.sections {
+        printer.section(section);
+    }
+    printer.out.push(')');
+    printer.out
+}
+
+impl<'m> Printer<'m> {
+    fn line(&mut self, depth: usize, line: &str) {
+        for _ in 0..depth {
+            self.out.push_str(INDENT);
+        }
+        self.out.push_str(line);
+        self.out.push('\n');
+    }
+    fn func(&self, index: FuncIdx) -> String {
+        reference(&self.names.funcs, index)
+    }
+    fn local(&self, func: FuncIdx, index: u32) -> String {
+        match self.names.locals.get(&func) {
+            Some(locals) => reference(locals, index),
+            None => index.to_string(),
+        }
+    }
+    ///`$name` of the label `depth` blocks out, or the depth
+    fn label(&self, depth: LabelIdx) -> String {
+        let label = (self.labels.len() as u32)
+            .checked_sub(depth + 1)
+            .and_then(|index| self.labels[index as usize].as_ref());
+        match label {
+            Some(name) => format!("${}", name),
+            None => depth.to_string(),
+        }
+    }
+    fn type_use(&self, index: TypeIdx) -> String {
+        format!("(type {})", reference(&self.names.types, index))
+    }
+    fn table(&self, index: u32) -> String {
+        reference(&self.names.tables, index)
+    }
+    fn next_table(&mut self) -> String {
+        self.tables += 1;
+        definition(&self.names.tables, self.tables - 1)
+    }
+    fn next_memory(&mut self) -> String {
+        self.memories += 1;
+        definition(&self.names.memories, self.memories - 1)
+    }
+    fn next_global(&mut self) -> String {
+        self.globals += 1;
+        definition(&self.names.globals, self.globals - 1)
+    }
+    fn tag(&self, index: TagIdx) -> String {
+        reference(&self.names.tags, index)
+    }
+    fn next_tag(&mut self) -> String {
+        self.tags += 1;
+        definition(&self.names.tags, self.tags - 1)
+    }
+
+    fn section(&mut self, section: &Section) {
+        match section {
+            Section::TypeSection(types) => {
+                for (index, func_type) in types.0.iter().enumerate() {
+                    let line = format!(
+                        "(type {} (func{}))",
+                        definition(&self.names.types, index as u32),
+                        signature(func_type)
+                    );
+                    self.line(1, &line);
+                }
+            }
+            Section::ImportSection(imports) => {
+                let mut funcs = 0;
+                for import in &imports.0 {
+                    let descriptor = match &import.descriptor {
+                        ImportDescriptor::Func(_) => {
+                            funcs += 1;
+                            self.func_header(funcs - 1, &mut 0)
+                        }
+                        ImportDescriptor::Table(table) => {
+                            format!("table {} {}", self.next_table(), table_type(table))
+                        }
+                        ImportDescriptor::Mem(memory) => {
+                            format!("memory {} {}", self.next_memory(), mem_type(memory))
+                        }
+                        ImportDescriptor::Global(global) => {
+                            format!("global {} {}", self.next_global(), global_type(global))
+                        }
+                        ImportDescriptor::Tag(tag) => {
+                            let id = self.next_tag();
+                            format!("tag {} {}", id, self.type_use(tag.type_index))
+                        }
+                    };
+                    let line = format!(
+                        "(import {} {} ({}))",
+                        string(import.module.as_bytes()),
+                        string(import.name.as_bytes()),
+                        descriptor
+                    );
+                    self.line(1, &line);
+                }
+            }
+            Section::TableSection(tables) => {
+                for table in &tables.0 {
+                    let id = self.next_table();
+                    let line = format!("(table {} {})", id, table_type(&table.table_type));
+                    self.line(1, &line);
+                }
+            }
+            Section::MemorySection(memories) => {
+                for memory in &memories.0 {
+                    let id = self.next_memory();
+                    let line = format!("(memory {} {})", id, mem_type(&memory.memory_type));
+                    self.line(1, &line);
+                }
+            }
+            Section::TagSection(tags) => {
+                for tag in &tags.0 {
+                    let id = self.next_tag();
+                    let line = format!("(tag {} {})", id, self.type_use(tag.tag_type.type_index));
+                    self.line(1, &line);
+                }
+            }
+            Section::GlobalSection(globals) => {
+                for global in &globals.0 {
+                    let line = format!(
+                        "(global {} {} {})",
+                        self.next_global(),
+                        global_type(&global.global_type),
+                        self.expression(&global.expression)
+                    );
+                    self.line(1, &line);
+                }
+            }
+            Section::ExportSection(exports) => {
+                for export in &exports.0 {
+                    let descriptor = match export.descriptor {
+                        ExportDescriptor::Func(index) => format!("func {}", self.func(index)),
+                        ExportDescriptor::Table(index) => format!("table {}", self.table(index)),
+                        ExportDescriptor::Mem(index) => {
+                            format!("memory {}", reference(&self.names.memories, index))
+                        }
+                        ExportDescriptor::Global(index) => {
+                            format!("global {}", reference(&self.names.globals, index))
+                        }
+                        ExportDescriptor::Tag(index) => format!("tag {}", self.tag(index)),
+                    };
+                    let line = format!(
+                        "(export {} ({}))",
+                        string(export.name.as_bytes()),
+                        descriptor
+                    );
+                    self.line(1, &line);
+                }
+            }
+            Section::StartSection(start) => {
+                if let Some(func) = start.0 {
+                    let line = format!("(start {})", self.func(func));
+                    self.line(1, &line);
+                }
+            }
+            Section::ElementSection(elems) => {
+                for (index, elem) in elems.0.iter().enumerate() {
+                    let id = definition(&self.names.elems, index as u32);
+                    let line = format!("(elem {}{})", id, self.elem(elem));
+                    self.line(1, &line);
+                }
+            }
+            Section::CodeSection(codes) => {
+                let imported = self.funcs.len() - codes.0.len();
+                for (index, code) in codes.0.iter().enumerate() {
+                    self.code((imported + index) as FuncIdx, code);
+                }
+            }
+            Section::LazyCodeSection(codes) => {
+                let imported = self.funcs.len() - codes.0.len();
+                for (index, code) in codes.0.iter().enumerate() {
+                    let func = (imported + index) as FuncIdx;
+                    match code.decode_code() {
+                        Ok(code) => self.code(func, &code),
+                        Err(error) => self.line(1, &format!(";; func {}: {}", func, error)),
+                    }
+                }
+            }
+            Section::DataSection(datas) => {
+                for (index, data) in datas.0.iter().enumerate() {
+                    let (memory, offset, bytes) = match data {
+                        Data::Active(offset, bytes) => (None, Some(offset), bytes),
+                        Data::Passive(bytes) => (None, None, bytes),
+                        Data::ActiveExplicit(memory, offset, bytes) => {
+                            (Some(memory), Some(offset), bytes)
+                        }
+                    };
+                    let id = definition(&self.names.datas, index as u32);
+                    let mut line = format!("(data {}", id);
+                    if let Some(memory) = memory {
+                        let memory = reference(&self.names.memories, *memory);
+                        write!(line, " (memory {})", memory).unwrap();
+                    }
+                    if let Some(offset) = offset {
+                        write!(line, " {}", self.offset(offset)).unwrap();
+                    }
+                    write!(line, " {})", string(bytes)).unwrap();
+                    self.line(1, &line);
+                }
+            }
+            //function types are printed with the code, the other sections are implied
+            _ => {}
+        }
+    }
+
+    ///a constant expression, folded if it is a single instruction
+    fn expression(&self, expression: &Expression) -> String {
+        match &expression.0[..] {
+            [instruction] => format!("({})", self.instruction(None, instruction)),
+            instructions => {
+                let instructions: Vec<_> = instructions
+                    .iter()
+                    .map(|instruction| self.instruction(None, instruction))
+                    .collect();
+                instructions.join(" ")
+            }
+        }
+    }
+    fn offset(&self, offset: &Expression) -> String {
+        match offset.0.len() {
+            1 => self.expression(offset),
+            _ => format!("(offset {})", self.expression(offset)),
+        }
+    }
+    fn elem(&self, elem: &Elem) -> String {
+        let funcs = |funcs: &[FuncIdx]| {
+            let funcs: String = funcs
+                .iter()
+                .map(|func| format!(" {}", self.func(*func)))
+                .collect();
+            format!(" func{}", funcs)
+        };
+        let items = |ref_type: &RefType, expressions: &[Expression]| {
+            let items: String = expressions
+                .iter()
+                .map(|expression| format!(" (item {})", self.expression(expression)))
+                .collect();
+            format!(" {}{}", self::ref_type(ref_type), items)
+        };
+        match elem {
+            Elem::ActiveIndex(offset, list) => format!(" {}{}", self.offset(offset), funcs(list)),
+            Elem::PassiveIndex(_, list) => funcs(list),
+            Elem::ActiveExplicitIndex(table, offset, _, list) => {
+                let table = self.table(*table);
+                format!(" (table {}) {}{}", table, self.offset(offset), funcs(list))
+            }
+            Elem::DeclarativeIndex(_, list) => format!(" declare{}", funcs(list)),
+            Elem::ActiveExpression(offset, list) => {
+                format!(" {}{}", self.offset(offset), items(&RefType::FuncRef, list))
+            }
+            Elem::PassiveExpression(ref_type, list) => items(ref_type, list),
+            Elem::ActiveExplicitExpression(table, offset, ref_type, list) => format!(
+                " (table {}) {}{}",
+                self.table(*table),
+                self.offset(offset),
+                items(ref_type, list)
+            ),
+            Elem::DeclarativeExpression(ref_type, list) => {
+                format!(" declare{}", items(ref_type, list))
+            }
+        }
+    }
+
+    ///`func` with its id and type use, params and results spelled out, as imports and
+    ///definitions start. `locals` counts the params, whose names come first
+    fn func_header(&self, index: FuncIdx, locals: &mut u32) -> String {
+        let type_idx = self.funcs[index as usize];
+        let id = definition(&self.names.funcs, index);
+        let mut header = format!("func {} {}", id, self.type_use(type_idx));
+        if let Some(func_type) = self.types.get(type_idx as usize).copied() {
+            self.declare(index, &mut header, "param", &func_type.rt1, locals);
+            header.push_str(&types("result", &func_type.rt2));
+        }
+        header
+    }
+    ///named params and locals are declared one by one, the others grouped
+    fn declare(
+        &self,
+        func: FuncIdx,
+        header: &mut String,
+        keyword: &str,
+        types: &[ValueType],
+        locals: &mut u32,
+    ) {
+        let mut group = vec![];
+        for value_type in types.iter().map(self::value_type) {
+            match self
+                .names
+                .locals
+                .get(&func)
+                .and_then(|names| names.get(locals))
+            {
+                Some(name) => {
+                    if !group.is_empty() {
+                        write!(header, " ({} {})", keyword, group.join(" ")).unwrap();
+                        group.clear();
+                    }
+                    write!(header, " ({} ${} {})", keyword, name, value_type).unwrap();
+                }
+                None => group.push(value_type),
+            }
+            *locals += 1;
+        }
+        if !group.is_empty() {
+            write!(header, " ({} {})", keyword, group.join(" ")).unwrap();
+        }
+    }
+    fn code(&mut self, index: FuncIdx, code: &Code) {
+        let mut locals = 0;
+        let mut header = format!("({}", self.func_header(index, &mut locals));
+        let declared: Vec<_> = code
+            .code
+            .locals
+            .iter()
+            .flat_map(|local| std::iter::repeat_n(local.value_type, local.count as usize))
+            .collect();
+        self.declare(index, &mut header, "local", &declared, &mut locals);
+        let body = &code.code.body.0;
+        if body.is_empty() {
+            header.push(')');
+            self.line(1, &header);
+            return;
+        }
+        self.line(1, &header);
+        self.blocks = 0;
+        self.instructions(index, body, 2);
+        //closes the function on its last line
+        self.out.pop();
+        self.out.push_str(")\n");
+    }
+    fn instructions(&mut self, func: FuncIdx, instructions: &[Instruction], depth: usize) {
+        for instruction in instructions {
+            let control = match instruction {
+                Instruction::Control(control) => control,
+                instruction => {
+                    let line = self.instruction(Some(func), instruction);
+                    self.line(depth, &line);
+                    continue;
+                }
+            };
+            let (keyword, block_type, body) = match control {
+                ControlInstruction::Block(block_type, body) => ("block", block_type, body),
+                ControlInstruction::Loop(block_type, body) => ("loop", block_type, body),
+                ControlInstruction::IfElse(block_type, body, _) => ("if", block_type, body),
+                ControlInstruction::TryTable(block_type, _, body) => {
+                    ("try_table", block_type, body)
+                }
+                ControlInstruction::Try {
+                    block: block_type,
+                    body,
+                    ..
+                }
+                | ControlInstruction::Delegate(block_type, body, _) => ("try", block_type, body),
+                _ => {
+                    let line = self.instruction(Some(func), instruction);
+                    self.line(depth, &line);
+                    continue;
+                }
+            };
+            let label = self
+                .names
+                .labels
+                .get(&func)
+                .and_then(|labels| labels.get(&self.blocks))
+                .cloned();
+            self.blocks += 1;
+            let mut line = keyword.to_string();
+            if let Some(label) = &label {
+                write!(line, " ${}", label).unwrap();
+            }
+            match block_type {
+                BlockType::Empty => {}
+                BlockType::ValType(result) => {
+                    write!(line, " (result {})", value_type(result)).unwrap()
+                }
+                BlockType::TypeIdx(index) => write!(line, " {}", self.type_use(*index)).unwrap(),
+            };
+            if let ControlInstruction::TryTable(_, catches, _) = control {
+                for catch in catches {
+                    write!(line, " {}", self.catch(catch)).unwrap();
+                }
+            }
+            self.line(depth, &line);
+            self.labels.push(label);
+            self.instructions(func, &body.0, depth + 1);
+            match control {
+                ControlInstruction::IfElse(_, _, Some(otherwise)) => {
+                    self.line(depth, "else");
+                    self.instructions(func, &otherwise.0, depth + 1);
+                }
+                ControlInstruction::Try {
+                    catches, catch_all, ..
+                } => {
+                    for (tag, handler) in catches {
+                        let line = format!("catch {}", self.tag(*tag));
+                        self.line(depth, &line);
+                        self.instructions(func, &handler.0, depth + 1);
+                    }
+                    if let Some(handler) = catch_all {
+                        self.line(depth, "catch_all");
+                        self.instructions(func, &handler.0, depth + 1);
+                    }
+                }
+                _ => {}
+            }
+            self.labels.pop();
+            match control {
+                //the label of delegate is outside of the block
+                ControlInstruction::Delegate(_, _, label) => {
+                    let line = format!("delegate {}", self.label(*label));
+                    self.line(depth, &line);
+                }
+                _ => self.line(depth, "end"),
+            }
+        }
+    }
+    ///a handler of `try_table`, its label is outside of the block
+    fn catch(&self, catch: &Catch) -> String {
+        match catch {
+            Catch::Catch(tag, label) => {
+                format!("(catch {} {})", self.tag(*tag), self.label(*label))
+            }
+            Catch::CatchRef(tag, label) => {
+                format!("(catch_ref {} {})", self.tag(*tag), self.label(*label))
+            }
+            Catch::CatchAll(label) => format!("(catch_all {})", self.label(*label)),
+            Catch::CatchAllRef(label) => format!("(catch_all_ref {})", self.label(*label)),
+        }
+    }
+    ///an instruction other than the blocks, `func` is unknown in constant expressions
+    fn instruction(&self, func: Option<FuncIdx>, instruction: &Instruction) -> String {
+        let local = |index: &u32| match func {
+            Some(func) => self.local(func, *index),
+            None => index.to_string(),
+        };
+        let global = |index: &u32| reference(&self.names.globals, *index);
+        let elem = |index: &u32| reference(&self.names.elems, *index);
+        let data = |index: &u32| reference(&self.names.datas, *index);
+        match instruction {
+            Instruction::Control(instruction) => match instruction {
+                ControlInstruction::Unreachable => "unreachable".to_string(),
+                ControlInstruction::Nop => "nop".to_string(),
+                ControlInstruction::Br(label) => format!("br {}", self.label(*label)),
+                ControlInstruction::BrIf(label) => format!("br_if {}", self.label(*label)),
+                ControlInstruction::BrTable { table, default } => {
+                    let labels: String = table
+                        .iter()
+                        .map(|label| format!(" {}", self.label(*label)))
+                        .collect();
+                    format!("br_table{} {}", labels, self.label(*default))
+                }
+                ControlInstruction::Return => "return".to_string(),
+                ControlInstruction::Call(func) => format!("call {}", self.func(*func)),
+                ControlInstruction::CallIndirect(type_idx, 0) => {
+                    format!("call_indirect {}", self.type_use(*type_idx))
+                }
+                ControlInstruction::CallIndirect(type_idx, table) => {
+                    let table = self.table(*table);
+                    format!("call_indirect {} {}", table, self.type_use(*type_idx))
+                }
+                ControlInstruction::Throw(tag) => format!("throw {}", self.tag(*tag)),
+                ControlInstruction::ThrowRef => "throw_ref".to_string(),
+                ControlInstruction::Rethrow(label) => format!("rethrow {}", self.label(*label)),
+                ControlInstruction::Block(..)
+                | ControlInstruction::Loop(..)
+                | ControlInstruction::IfElse(..)
+                | ControlInstruction::TryTable(..)
+                | ControlInstruction::Try { .. }
+                | ControlInstruction::Delegate(..) => unreachable!("printed as blocks"),
+            },
+            Instruction::Reference(instruction) => match instruction {
+                ReferenceInstruction::Null(RefType::FuncRef) => "ref.null func".to_string(),
+                ReferenceInstruction::Null(RefType::ExternRef) => "ref.null extern".to_string(),
+                ReferenceInstruction::Null(RefType::ExnRef) => "ref.null exn".to_string(),
+                ReferenceInstruction::IsNull => "ref.is_null".to_string(),
+                ReferenceInstruction::Func(func) => format!("ref.func {}", self.func(*func)),
+            },
+            Instruction::Parametric(instruction) => match instruction {
+                ParametricInstruction::Drop => "drop".to_string(),
+                ParametricInstruction::Select => "select".to_string(),
+                ParametricInstruction::SelectTyped(types) => {
+                    format!("select{}", self::types("result", types))
+                }
+            },
+            Instruction::Variable(instruction) => match instruction {
+                VariableInstruction::LocalGet(index) => format!("local.get {}", local(index)),
+                VariableInstruction::LocalSet(index) => format!("local.set {}", local(index)),
+                VariableInstruction::LocalTee(index) => format!("local.tee {}", local(index)),
+                VariableInstruction::GlobalGet(index) => format!("global.get {}", global(index)),
+                VariableInstruction::GlobalSet(index) => format!("global.set {}", global(index)),
+            },
+            Instruction::Table(instruction) => match instruction {
+                TableInstruction::TableGet(table) => format!("table.get {}", self.table(*table)),
+                TableInstruction::TableSet(table) => format!("table.set {}", self.table(*table)),
+                TableInstruction::TableInit(index, table) => {
+                    format!("table.init {} {}", self.table(*table), elem(index))
+                }
+                TableInstruction::TableDrop(index) => format!("elem.drop {}", elem(index)),
+                TableInstruction::TableCopy(destination, source) => {
+                    let destination = self.table(*destination);
+                    format!("table.copy {} {}", destination, self.table(*source))
+                }
+                TableInstruction::TableGrow(table) => format!("table.grow {}", self.table(*table)),
+                TableInstruction::TableSize(table) => format!("table.size {}", self.table(*table)),
+                TableInstruction::TableFill(table) => format!("table.fill {}", self.table(*table)),
+            },
+            Instruction::Memory(instruction) => match instruction {
+                MemoryInstruction::Size(memory) => {
+                    format!("memory.size{}", self.memory_use(*memory))
+                }
+                MemoryInstruction::Grow(memory) => {
+                    format!("memory.grow{}", self.memory_use(*memory))
+                }
+                MemoryInstruction::Init(_, index, memory) => {
+                    format!("memory.init{} {}", self.memory_use(*memory), data(index))
+                }
+                MemoryInstruction::Drop(_, index) => format!("data.drop {}", data(index)),
+                MemoryInstruction::Copy(_, 0, 0) => "memory.copy".to_string(),
+                MemoryInstruction::Copy(_, destination, source) => {
+                    let memory = |index: &u32| reference(&self.names.memories, *index);
+                    format!("memory.copy {} {}", memory(destination), memory(source))
+                }
+                MemoryInstruction::Fill(_, memory) => {
+                    format!("memory.fill{}", self.memory_use(*memory))
+                }
+                instruction => {
+                    let (keyword, mem_arg, natural) = keywords::memory_keyword(instruction)
+                        .expect("every other memory instruction is a load or store");
+                    self.memory_access(keyword, mem_arg, natural)
+                }
+            },
+            Instruction::Numeric(instruction) => match instruction {
+                NumericInstruction::I32Const(value) => format!("i32.const {}", value),
+                NumericInstruction::I64Const(value) => format!("i64.const {}", value),
+                NumericInstruction::F32Const(value) => {
+                    format!("f32.const {}", float(value.to_bits() as u64, 23, 8))
+                }
+                NumericInstruction::F64Const(value) => {
+                    format!("f64.const {}", float(value.to_bits(), 52, 11))
+                }
+                instruction => keywords::numeric_keyword(instruction)
+                    .expect("constants are the only numeric instructions with immediates")
+                    .to_string(),
+            },
+            Instruction::Saturating(instruction) => keywords::saturating_keyword(instruction)
+                .expect("every saturating instruction has a mnemonic")
+                .to_string(),
+            Instruction::Simd(instruction) => self.simd(instruction),
+            Instruction::RelaxedSimd(instruction) => keywords::relaxed_simd_keyword(instruction)
+                .expect("every relaxed SIMD instruction has a mnemonic")
+                .to_string(),
+            Instruction::Atomic(AtomicInstruction::AtomicFence(_)) => "atomic.fence".to_string(),
+            Instruction::Atomic(instruction) => {
+                let (keyword, mem_arg, natural) = keywords::atomic_keyword(instruction)
+                    .expect("every other atomic instruction accesses memory");
+                self.memory_access(keyword, mem_arg, natural)
+            }
+        }
+    }
+    ///the memory operand of an instruction, omitted for memory 0
+    fn memory_use(&self, index: MemIdx) -> String {
+        match index {
+            0 => String::new(),
+            index => format!(" {}", reference(&self.names.memories, index)),
+        }
+    }
+    ///a load or store with its memory, `offset=` and `align=`, omitted when they are the default
+    ///ones
+    fn memory_access(&self, keyword: &str, mem_arg: &MemArg, natural: u32) -> String {
+        let mut text = keyword.to_string() + &self.memory_use(mem_arg.memory);
+        if mem_arg.offset != 0 {
+            write!(text, " offset={}", mem_arg.offset).unwrap();
+        }
+        if mem_arg.align != natural {
+            write!(text, " align={}", 1u64 << mem_arg.align).unwrap();
+        }
+        text
+    }
+    fn simd(&self, instruction: &SimdInstruction) -> String {
+        let keyword = keywords::simd_keyword(instruction);
+        let mut text = match instruction.memory_access() {
+            Some((mem_arg, width)) => {
+                self.memory_access(keyword, mem_arg, (width / 8).trailing_zeros())
+            }
+            None => keyword.to_string(),
+        };
+        match instruction {
+            SimdInstruction::V128Const(v128) => {
+                text.push_str(" i32x4");
+                for lane in v128.0.chunks(4) {
+                    let lane = u32::from_le_bytes([lane[0], lane[1], lane[2], lane[3]]);
+                    write!(text, " {:#010x}", lane).unwrap();
+                }
+            }
+            SimdInstruction::I8x16Shuffle(lanes) => {
+                for lane in &lanes.0 {
+                    write!(text, " {}", lane).unwrap();
+                }
+            }
+            instruction => {
+                if let Some((lane, _)) = instruction.lane() {
+                    write!(text, " {}", lane).unwrap();
+                }
+            }
+        }
+        text
+    }
+}
+
+#[test]
+fn floats() {
+    let f32 = |value: f32| float(value.to_bits() as u64, 23, 8);
+    let f64 = |value: f64| float(value.to_bits(), 52, 11);
+    assert_eq!(f32(3.0), "0x1.8p+1");
+    assert_eq!(f32(-0.0), "-0x0p+0");
+    assert_eq!(f32(f32::MAX), "0x1.fffffep+127");
+    assert_eq!(f32(f32::from_bits(1)), "0x1p-149");
+    assert_eq!(f64(f64::from_bits(3)), "0x1.8p-1073");
+    assert_eq!(f64(0.1), "0x1.999999999999ap-4");
+    assert_eq!(f64(f64::NEG_INFINITY), "-inf");
+    assert_eq!(f32(f32::from_bits(0x7FC0_0000)), "nan");
+    assert_eq!(float(0xFFA0_0000, 23, 8), "-nan:0x200000");
+    for text in ["0x1.8p+1", "0x1p-149", "-nan:0x200000", "0x1.fffffep+127"] {
+        let bits = super::numbers::f32(text).unwrap().to_bits() as u64;
+        assert_eq!(float(bits, 23, 8), text);
+    }
+}
@@ -1,9 +1,15 @@
-use nom::{branch::alt, bytes::complete::tag, sequence::tuple, Parser};
+use crate::{modules::indices::TypeIdx, Suffix};
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    combinator::{map, verify},
+    Parser,
+};
 use std::fmt::Debug;
-use wasm_core::values::Parse;
-use wasm_derive::Parse;
+use wasm_core::values::{Encode, Parse};
+use wasm_derive::{Encode, Parse};
 
-#[derive(Parse, Debug)]
+#[derive(Parse, Encode, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum NumType {
     #[starting = 0x7C]
     F64,
@@ -12,37 +18,52 @@ pub enum NumType {
     I32,
 }
 
-#[derive(Parse, Debug)]
+#[derive(Parse, Encode, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RefType {
+    ///a caught exception, from the exception handling proposal
+    #[starting = 0x69]
+    ExnRef,
     #[starting = 0x6F]
     ExternRef,
     FuncRef,
 }
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ValueType {
     NumType(NumType),
+    ///the 128 bit vector of the SIMD proposal
+    V128,
     RefType(RefType),
 }
-impl Parse for ValueType {
-    fn parse<'a, E>(i: &'a [u8]) -> nom::IResult<&[u8], Self, E>
+impl<'a> Parse<'a> for ValueType {
+    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
     where
         E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
     {
-        let num_type = NumType::parse.map(|num_type| ValueType::NumType(num_type));
-        let ref_type = RefType::parse.map(|ref_type| ValueType::RefType(ref_type));
-        alt((num_type, ref_type))(i)
+        let num_type = NumType::parse.map(ValueType::NumType);
+        let v128 = tag([0x7B]).map(|_| ValueType::V128);
+        let ref_type = RefType::parse.map(ValueType::RefType);
+        alt((num_type, v128, ref_type))(i)
+    }
+}
+impl Encode for ValueType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            ValueType::NumType(num_type) => num_type.encode(out),
+            ValueType::V128 => out.push(0x7B),
+            ValueType::RefType(ref_type) => ref_type.encode(out),
+        }
     }
 }
 type ResultType = Vec<ValueType>;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FuncType {
     pub rt1: ResultType,
     pub rt2: ResultType,
 }
 
-impl Parse for FuncType {
-    fn parse<'a, E>(i: &'a [u8]) -> nom::IResult<&[u8], Self, E>
+impl<'a> Parse<'a> for FuncType {
+    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
     where
         E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
     {
@@ -52,43 +73,145 @@ impl Parse for FuncType {
         Ok((i, FuncType { rt1, rt2 }))
     }
 }
+impl Encode for FuncType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(0x60);
+        self.rt1.encode(out);
+        self.rt2.encode(out);
+    }
+}
+///the type of the addresses of a memory, `I64` from the memory64 proposal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexType {
+    I32,
+    I64,
+}
+impl IndexType {
+    ///the type of the addresses
+    pub fn value_type(self) -> ValueType {
+        match self {
+            IndexType::I32 => ValueType::NumType(NumType::I32),
+            IndexType::I64 => ValueType::NumType(NumType::I64),
+        }
+    }
+}
 #[derive(Debug)]
 pub struct Limit {
-    pub min: u32,
-    pub max: Option<u32>,
+    pub min: u64,
+    pub max: Option<u64>,
+    pub index_type: IndexType,
 }
 
-impl Parse for Limit {
-    fn parse<'a, E>(i: &'a [u8]) -> nom::IResult<&[u8], Self, E>
+impl<'a> Parse<'a> for Limit {
+    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
     where
         E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
     {
-        let without_max = tuple((tag([0x00]), u32::parse)).map(|(_, min)| Limit { min, max: None });
-        let with_max = tuple((tag([0x01]), u32::parse, u32::parse)).map(|(_, min, max)| Limit {
+        let (i, flags) = verify(u8::parse, |flags| *flags <= 0x01)(i)?;
+        Limit::after_flags(flags, i)
+    }
+}
+impl Encode for Limit {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.encode_with(0x00, out);
+    }
+}
+impl Limit {
+    ///the limits following their flags byte, whose lowest bit tells if there is a maximum and
+    ///bit 2 if they are 64 bit
+    fn after_flags<'a, E>(flags: u8, i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
+    where
+        E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
+    {
+        let index_type = match flags & 0x04 {
+            0 => IndexType::I32,
+            _ => IndexType::I64,
+        };
+        let (i, min) = bound(index_type, i)?;
+        let (i, max) = match flags & 0x01 {
+            0 => (i, None),
+            _ => map(|i| bound(index_type, i), Some)(i)?,
+        };
+        let limit = Limit {
             min,
-            max: max.into(),
-        });
-        alt((without_max, with_max))(i)
+            max,
+            index_type,
+        };
+        Ok((i, limit))
+    }
+    ///encodes the limits after `flags`, setting the bits of the maximum and of the index type
+    fn encode_with(&self, mut flags: u8, out: &mut Vec<u8>) {
+        if self.index_type == IndexType::I64 {
+            flags |= 0x04;
+        }
+        match self.max {
+            None => {
+                out.push(flags);
+                self.min.encode(out);
+            }
+            Some(max) => {
+                out.push(flags | 0x01);
+                self.min.encode(out);
+                max.encode(out);
+            }
+        }
+    }
+}
+///a bound of limits, a `u32` unless they are 64 bit
+fn bound<'a, E>(index_type: IndexType, i: &'a [u8]) -> nom::IResult<&'a [u8], u64, E>
+where
+    E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
+{
+    match index_type {
+        IndexType::I32 => map(u32::parse, u64::from)(i),
+        IndexType::I64 => u64::parse(i),
     }
 }
-#[derive(Parse, Debug)]
+#[derive(Debug)]
 pub struct MemType {
     pub lim: Limit,
+    ///can be accessed by several threads, from the threads proposal
+    pub shared: bool,
 }
-#[derive(Parse, Debug)]
+impl<'a> Parse<'a> for MemType {
+    fn parse<E>(i: &'a [u8]) -> nom::IResult<&'a [u8], Self, E>
+    where
+        E: nom::error::ParseError<&'a [u8]> + nom::error::ContextError<&'a [u8]> + std::fmt::Debug,
+    {
+        let (i, flags) = verify(u8::parse, |flags| *flags <= 0x07)(i)?;
+        let (i, lim) = Limit::after_flags(flags, i)?;
+        let shared = flags & 0x02 != 0;
+        Ok((i, MemType { lim, shared }))
+    }
+}
+impl Encode for MemType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let flags = if self.shared { 0x02 } else { 0x00 };
+        self.lim.encode_with(flags, out);
+    }
+}
+#[derive(Parse, Encode, Debug)]
 pub struct TableType {
     pub et: RefType,
     pub lim: Limit,
 }
 
-#[derive(Parse, Debug)]
+#[derive(Parse, Encode, Debug)]
 pub struct GlobalType {
     pub t: ValueType,
     pub m: Mutability,
 }
 
-#[derive(Parse, Debug)]
+#[derive(Parse, Encode, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Mutability {
     Const,
     Var,
 }
+
+///the type of an exception tag, whose function type gives the values thrown with it
+#[derive(Parse, Encode, Debug)]
+pub struct TagType {
+    ///the attribute of the tag, always exception
+    pub attribute: Suffix<0x00>,
+    pub type_index: TypeIdx,
+}
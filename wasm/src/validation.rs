@@ -0,0 +1,689 @@
+use crate::{
+    instructions::{
+        numeric::NumericInstruction, reference::ReferenceInstruction, simd::SimdInstruction,
+        variable::VariableInstruction, Expression, Instruction,
+    },
+    modules::{
+        data::Data,
+        element::Elem,
+        export::{Export, ExportDescriptor},
+        global::Global,
+        import::{Import, ImportDescriptor},
+        indices::{FuncIdx, GlobalIdx, TagIdx, TypeIdx},
+        Module, Section,
+    },
+    types::{
+        FuncType, GlobalType, IndexType, Limit, MemType, Mutability, NumType, RefType, TableType,
+        TagType, ValueType,
+    },
+};
+use std::{collections::HashSet, fmt};
+pub use typing::{InstructionPath, Step};
+use wasm_core::values::Name;
+
+pub mod typing;
+
+#[derive(Debug)]
+pub struct ValidationError {
+    pub section: &'static str,
+    pub index: Option<u32>,
+    ///name of the entry in the `name` section, for function bodies
+    pub name: Option<Name<'static>>,
+    ///location of the offending instruction, for errors inside function bodies
+    pub path: Option<InstructionPath>,
+    pub message: String,
+}
+impl ValidationError {
+    fn new(section: &'static str, index: Option<u32>, message: impl Into<String>) -> Self {
+        Self {
+            section,
+            index,
+            name: None,
+            path: None,
+            message: message.into(),
+        }
+    }
+}
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} section", self.section)?;
+        //function bodies are numbered in the function index space, imports first
+        match (self.index, &self.path) {
+            (Some(index), Some(_)) => write!(f, ", function {}", index)?,
+            (Some(index), None) => write!(f, ", entry {}", index)?,
+            (None, _) => {}
+        }
+        if let Some(name) = &self.name {
+            write!(f, " (${})", name)?;
+        }
+        if let Some(path) = &self.path {
+            write!(f, ", instruction {}", path)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+impl std::error::Error for ValidationError {}
+
+///index spaces of a module, imports first, as defined by the spec's validation context
+#[derive(Debug, Default)]
+pub struct Context<'a> {
+    pub types: Vec<&'a FuncType>,
+    pub funcs: Vec<TypeIdx>,
+    pub tables: Vec<&'a TableType>,
+    pub mems: Vec<&'a MemType>,
+    pub globals: Vec<&'a GlobalType>,
+    ///the type of every exception tag
+    pub tags: Vec<TypeIdx>,
+    pub elems: Vec<RefType>,
+    pub datas: u32,
+    pub data_count: Option<u32>,
+    ///functions that may be referenced by `ref.func` inside function bodies
+    pub refs: HashSet<FuncIdx>,
+    pub imported_funcs: u32,
+    pub imported_globals: u32,
+}
+impl<'a> Context<'a> {
+    pub fn func_type(&self, func: FuncIdx) -> Option<&'a FuncType> {
+        let type_idx = *self.funcs.get(func as usize)?;
+        self.types.get(type_idx as usize).copied()
+    }
+    pub fn tag_type(&self, tag: TagIdx) -> Option<&'a FuncType> {
+        let type_idx = *self.tags.get(tag as usize)?;
+        self.types.get(type_idx as usize).copied()
+    }
+}
+
+///a module that passed [validate], along with its validation context
+#[derive(Debug)]
+pub struct ValidModule<'a> {
+    pub module: &'a Module<'a>,
+    pub context: Context<'a>,
+}
+
+const MAX_PAGES: u64 = 1 << 16;
+const MAX_PAGES_64: u64 = 1 << 48;
+const MAX_TABLE: u64 = u32::MAX as u64;
+
+pub fn validate<'a>(module: &'a Module<'a>) -> Result<ValidModule<'a>, ValidationError> {
+    let mut context = Context::default();
+    let mut functions = vec![];
+    let mut globals = vec![];
+    let mut tags = vec![];
+    let mut imports = vec![];
+    let mut exports = vec![];
+    let mut elems = vec![];
+    let mut datas = vec![];
+    let mut codes = vec![];
+    let mut start = None;
+    for section in &module.sections {
+        match section {
+            Section::TypeSection(sec) => context.types.extend(sec.0.iter()),
+            Section::ImportSection(sec) => imports.extend(sec.0.iter()),
+            Section::FunctionSection(sec) => functions.extend(sec.0.iter().copied()),
+            Section::TableSection(sec) => context
+                .tables
+                .extend(sec.0.iter().map(|table| &table.table_type)),
+            Section::MemorySection(sec) => context
+                .mems
+                .extend(sec.0.iter().map(|memory| &memory.memory_type)),
+            Section::TagSection(sec) => tags.extend(sec.0.iter().map(|tag| &tag.tag_type)),
+            Section::GlobalSection(sec) => globals.extend(sec.0.iter()),
+            Section::ExportSection(sec) => exports.extend(sec.0.iter()),
+            Section::StartSection(sec) => start = start.or(sec.0),
+            Section::ElementSection(sec) => elems.extend(sec.0.iter()),
+            Section::CodeSection(sec) => codes.extend(sec.0.iter()),
+            Section::LazyCodeSection(_) => {
+                let message = "function bodies not decoded, see Module::decode_code";
+                return Err(ValidationError::new("code", None, message));
+            }
+            Section::DataSection(sec) => datas.extend(sec.0.iter()),
+            Section::DataCountSection(sec) => context.data_count = sec.0,
+            Section::CustomSection(_) => {}
+        }
+    }
+
+    let mut imported_tables = vec![];
+    let mut imported_mems = vec![];
+    for (index, import) in imports.iter().enumerate() {
+        validate_import(&context, import, index as u32)?;
+        match &import.descriptor {
+            ImportDescriptor::Func(type_idx) => context.funcs.push(*type_idx),
+            ImportDescriptor::Table(table_type) => imported_tables.push(table_type),
+            ImportDescriptor::Mem(mem_type) => imported_mems.push(mem_type),
+            ImportDescriptor::Global(global_type) => context.globals.push(global_type),
+            ImportDescriptor::Tag(tag_type) => context.tags.push(tag_type.type_index),
+        }
+    }
+    context.imported_funcs = context.funcs.len() as u32;
+    context.imported_globals = context.globals.len() as u32;
+    context.tables.splice(0..0, imported_tables);
+    context.mems.splice(0..0, imported_mems);
+
+    for (index, type_idx) in functions.iter().enumerate() {
+        if *type_idx as usize >= context.types.len() {
+            let message = format!("type index {} out of bounds", type_idx);
+            return Err(ValidationError::new(
+                "function",
+                Some(index as u32),
+                message,
+            ));
+        }
+        context.funcs.push(*type_idx);
+    }
+    if functions.len() != codes.len() {
+        let message = format!(
+            "{} function declarations but {} bodies in the code section",
+            functions.len(),
+            codes.len()
+        );
+        return Err(ValidationError::new("code", None, message));
+    }
+
+    for (index, table) in context.tables.iter().enumerate() {
+        validate_limit(&table.lim, MAX_TABLE)
+            .map_err(|message| ValidationError::new("table", Some(index as u32), message))?;
+    }
+    for (index, memory) in context.mems.iter().enumerate() {
+        validate_mem_type(memory)
+            .map_err(|message| ValidationError::new("memory", Some(index as u32), message))?;
+    }
+    for (index, tag_type) in tags.iter().enumerate() {
+        let index = (context.tags.len() + index) as u32;
+        validate_tag_type(&context, tag_type)
+            .map_err(|message| ValidationError::new("tag", Some(index), message))?;
+    }
+    context
+        .tags
+        .extend(tags.iter().map(|tag_type| tag_type.type_index));
+
+    //declared references are collected before any expression is checked
+    collect_refs(&mut context, &globals, &elems, &exports);
+
+    for (index, global) in globals.iter().enumerate() {
+        let index = context.imported_globals + index as u32;
+        validate_const_expr(&context, &global.expression, global.global_type.t)
+            .map_err(|message| ValidationError::new("global", Some(index), message))?;
+        context.globals.push(&global.global_type);
+    }
+
+    for (index, elem) in elems.iter().enumerate() {
+        let ref_type = validate_elem(&context, elem)
+            .map_err(|message| ValidationError::new("element", Some(index as u32), message))?;
+        context.elems.push(ref_type);
+    }
+
+    for (index, data) in datas.iter().enumerate() {
+        validate_data(&context, data)
+            .map_err(|message| ValidationError::new("data", Some(index as u32), message))?;
+    }
+    context.datas = datas.len() as u32;
+    match context.data_count {
+        Some(count) if count != context.datas => {
+            let message = format!(
+                "declares {} data segments but the data section has {}",
+                count, context.datas
+            );
+            return Err(ValidationError::new("data count", None, message));
+        }
+        _ => {}
+    }
+
+    if let Some(start) = start {
+        match context.func_type(start) {
+            None => {
+                let message = format!("function index {} out of bounds", start);
+                return Err(ValidationError::new("start", Some(start), message));
+            }
+            Some(func_type) if !func_type.rt1.is_empty() || !func_type.rt2.is_empty() => {
+                let message = "start function must have type [] -> []";
+                return Err(ValidationError::new("start", Some(start), message));
+            }
+            Some(_) => {}
+        }
+    }
+
+    let mut names = HashSet::new();
+    for (index, export) in exports.iter().enumerate() {
+        validate_export(&context, export)
+            .map_err(|message| ValidationError::new("export", Some(index as u32), message))?;
+        if !names.insert(export.name.as_bytes()) {
+            let message = format!(
+                "duplicate export name {:?}",
+                String::from_utf8_lossy(export.name.as_bytes())
+            );
+            return Err(ValidationError::new("export", Some(index as u32), message));
+        }
+    }
+
+    let names = module.names();
+    for (index, code) in codes.iter().enumerate() {
+        let func = context.imported_funcs + index as u32;
+        typing::validate_function(&context, func, &code.code).map_err(|error| ValidationError {
+            section: "code",
+            index: Some(func),
+            name: names
+                .as_ref()
+                .and_then(|names| names.funcs.get(func).cloned())
+                .map(Name::into_owned),
+            path: Some(error.path),
+            message: error.message,
+        })?;
+    }
+
+    Ok(ValidModule { module, context })
+}
+
+fn validate_import(context: &Context, import: &Import, index: u32) -> Result<(), ValidationError> {
+    let message = match &import.descriptor {
+        ImportDescriptor::Func(type_idx) if *type_idx as usize >= context.types.len() => {
+            format!("type index {} out of bounds", type_idx)
+        }
+        ImportDescriptor::Table(table_type) => match validate_limit(&table_type.lim, MAX_TABLE) {
+            Err(message) => message,
+            Ok(()) => return Ok(()),
+        },
+        ImportDescriptor::Mem(mem_type) => match validate_mem_type(mem_type) {
+            Err(message) => message,
+            Ok(()) => return Ok(()),
+        },
+        ImportDescriptor::Tag(tag_type) => match validate_tag_type(context, tag_type) {
+            Err(message) => message,
+            Ok(()) => return Ok(()),
+        },
+        _ => return Ok(()),
+    };
+    Err(ValidationError::new("import", Some(index), message))
+}
+
+fn validate_limit(limit: &Limit, range: u64) -> Result<(), String> {
+    if limit.min > range {
+        return Err(format!("minimum {} exceeds {}", limit.min, range));
+    }
+    match limit.max {
+        Some(max) if max > range => Err(format!("maximum {} exceeds {}", max, range)),
+        Some(max) if max < limit.min => Err(format!(
+            "maximum {} is smaller than minimum {}",
+            max, limit.min
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn validate_mem_type(mem_type: &MemType) -> Result<(), String> {
+    let range = match mem_type.lim.index_type {
+        IndexType::I32 => MAX_PAGES,
+        IndexType::I64 => MAX_PAGES_64,
+    };
+    validate_limit(&mem_type.lim, range)?;
+    if mem_type.shared && mem_type.lim.max.is_none() {
+        return Err("shared memory must have a maximum".to_string());
+    }
+    Ok(())
+}
+
+///exceptions carry the params of the tag's type, which has no results
+fn validate_tag_type(context: &Context, tag_type: &TagType) -> Result<(), String> {
+    match context.types.get(tag_type.type_index as usize) {
+        None => Err(format!("type index {} out of bounds", tag_type.type_index)),
+        Some(func_type) if !func_type.rt2.is_empty() => {
+            Err("the type of a tag must have no results".to_string())
+        }
+        Some(_) => Ok(()),
+    }
+}
+
+fn collect_refs(context: &mut Context, globals: &[&Global], elems: &[&Elem], exports: &[&Export]) {
+    fn add_expr(refs: &mut HashSet<FuncIdx>, expr: &Expression) {
+        for instruction in &expr.0 {
+            if let Instruction::Reference(ReferenceInstruction::Func(func)) = instruction {
+                refs.insert(*func);
+            }
+        }
+    }
+    let refs = &mut context.refs;
+    for global in globals {
+        add_expr(refs, &global.expression);
+    }
+    for elem in elems {
+        match elem {
+            Elem::ActiveIndex(_, funcs)
+            | Elem::PassiveIndex(_, funcs)
+            | Elem::ActiveExplicitIndex(_, _, _, funcs)
+            | Elem::DeclarativeIndex(_, funcs) => refs.extend(funcs.iter().copied()),
+            Elem::ActiveExpression(_, exprs)
+            | Elem::PassiveExpression(_, exprs)
+            | Elem::ActiveExplicitExpression(_, _, _, exprs)
+            | Elem::DeclarativeExpression(_, exprs) => {
+                for expr in exprs {
+                    add_expr(refs, expr);
+                }
+            }
+        }
+    }
+    for export in exports {
+        if let ExportDescriptor::Func(func) = export.descriptor {
+            refs.insert(func);
+        }
+    }
+}
+
+///checks that `expr` is constant and produces a single value of type `expected`
+pub fn validate_const_expr(
+    context: &Context,
+    expr: &Expression,
+    expected: ValueType,
+) -> Result<(), String> {
+    let instruction = match &expr.0[..] {
+        [instruction] => instruction,
+        [] => return Err("constant expression is empty".to_string()),
+        _ => return Err("constant expression must be a single instruction".to_string()),
+    };
+    let found = match instruction {
+        Instruction::Numeric(NumericInstruction::I32Const(_)) => ValueType::NumType(NumType::I32),
+        Instruction::Numeric(NumericInstruction::I64Const(_)) => ValueType::NumType(NumType::I64),
+        Instruction::Numeric(NumericInstruction::F32Const(_)) => ValueType::NumType(NumType::F32),
+        Instruction::Numeric(NumericInstruction::F64Const(_)) => ValueType::NumType(NumType::F64),
+        Instruction::Simd(SimdInstruction::V128Const(_)) => ValueType::V128,
+        Instruction::Reference(ReferenceInstruction::Null(ref_type)) => {
+            ValueType::RefType(*ref_type)
+        }
+        Instruction::Reference(ReferenceInstruction::Func(func)) => {
+            if *func as usize >= context.funcs.len() {
+                return Err(format!("function index {} out of bounds", func));
+            }
+            ValueType::RefType(RefType::FuncRef)
+        }
+        Instruction::Variable(VariableInstruction::GlobalGet(global)) => {
+            const_global(context, *global)?
+        }
+        _ => return Err(format!("{:?} is not a constant instruction", instruction)),
+    };
+    if found != expected {
+        return Err(format!("expected {:?}, found {:?}", expected, found));
+    }
+    Ok(())
+}
+
+fn const_global(context: &Context, global: GlobalIdx) -> Result<ValueType, String> {
+    if global >= context.imported_globals {
+        return Err(format!(
+            "constant expressions may only read imported globals, found global {}",
+            global
+        ));
+    }
+    let global_type = context.globals[global as usize];
+    if global_type.m != Mutability::Const {
+        return Err(format!("global {} is mutable", global));
+    }
+    Ok(global_type.t)
+}
+
+fn validate_elem(context: &Context, elem: &Elem) -> Result<RefType, String> {
+    let funcref = ValueType::RefType(RefType::FuncRef);
+    let (table, offset, ref_type) = match elem {
+        Elem::ActiveIndex(offset, _) => (Some(0), Some(offset), RefType::FuncRef),
+        Elem::ActiveExplicitIndex(table, offset, _, _) => {
+            (Some(*table), Some(offset), RefType::FuncRef)
+        }
+        Elem::PassiveIndex(..) | Elem::DeclarativeIndex(..) => (None, None, RefType::FuncRef),
+        Elem::ActiveExpression(offset, _) => (Some(0), Some(offset), RefType::FuncRef),
+        Elem::ActiveExplicitExpression(table, offset, ref_type, _) => {
+            (Some(*table), Some(offset), *ref_type)
+        }
+        Elem::PassiveExpression(ref_type, _) | Elem::DeclarativeExpression(ref_type, _) => {
+            (None, None, *ref_type)
+        }
+    };
+    if let Some(table) = table {
+        let table_type = context
+            .tables
+            .get(table as usize)
+            .ok_or_else(|| format!("table index {} out of bounds", table))?;
+        if table_type.et != ref_type {
+            return Err(format!(
+                "segment of {:?} does not fit table {} of {:?}",
+                ref_type, table, table_type.et
+            ));
+        }
+    }
+    if let Some(offset) = offset {
+        validate_const_expr(context, offset, ValueType::NumType(NumType::I32))?;
+    }
+    match elem {
+        Elem::ActiveIndex(_, funcs)
+        | Elem::PassiveIndex(_, funcs)
+        | Elem::ActiveExplicitIndex(_, _, _, funcs)
+        | Elem::DeclarativeIndex(_, funcs) => {
+            if let Some(func) = funcs.iter().find(|f| **f as usize >= context.funcs.len()) {
+                return Err(format!("function index {} out of bounds", func));
+            }
+        }
+        Elem::ActiveExpression(_, exprs) => {
+            for expr in exprs {
+                validate_const_expr(context, expr, funcref)?;
+            }
+        }
+        Elem::PassiveExpression(_, exprs)
+        | Elem::ActiveExplicitExpression(_, _, _, exprs)
+        | Elem::DeclarativeExpression(_, exprs) => {
+            for expr in exprs {
+                validate_const_expr(context, expr, ValueType::RefType(ref_type))?;
+            }
+        }
+    }
+    Ok(ref_type)
+}
+
+fn validate_data(context: &Context, data: &Data) -> Result<(), String> {
+    let (memory, offset) = match data {
+        Data::Active(offset, _) => (0, offset),
+        Data::ActiveExplicit(memory, offset, _) => (*memory, offset),
+        Data::Passive(_) => return Ok(()),
+    };
+    let address = match context.mems.get(memory as usize) {
+        Some(mem_type) => mem_type.lim.index_type.value_type(),
+        None => return Err(format!("memory index {} out of bounds", memory)),
+    };
+    validate_const_expr(context, offset, address)
+}
+
+fn validate_export(context: &Context, export: &Export) -> Result<(), String> {
+    let (kind, index, len) = match export.descriptor {
+        ExportDescriptor::Func(idx) => ("function", idx, context.funcs.len()),
+        ExportDescriptor::Table(idx) => ("table", idx, context.tables.len()),
+        ExportDescriptor::Mem(idx) => ("memory", idx, context.mems.len()),
+        ExportDescriptor::Global(idx) => ("global", idx, context.globals.len()),
+        ExportDescriptor::Tag(idx) => ("tag", idx, context.tags.len()),
+    };
+    if index as usize >= len {
+        return Err(format!("{} index {} out of bounds", kind, index));
+    }
+    Ok(())
+}
+
+#[test]
+fn validate_fixtures() {
+    use nom::error::VerboseError;
+    use wasm_core::values::Parse;
+    let loop_file = &include_bytes!("loop.wasm")[..];
+    for file in [loop_file, &include_bytes!("if.wasm")[..]] {
+        let (_, module) = Module::parse::<VerboseError<_>>(file).unwrap();
+        validate(&module).unwrap();
+    }
+}
+#[test]
+fn missing_bodies() {
+    use nom::error::VerboseError;
+    use wasm_core::values::Parse;
+    let file = include_bytes!("if.wasm");
+    let (_, mut module) = Module::parse::<VerboseError<_>>(file).unwrap();
+    module
+        .sections
+        .retain(|section| !matches!(section, Section::CodeSection(_)));
+    let error = validate(&module).unwrap_err();
+    assert_eq!(error.section, "code");
+    assert_eq!(
+        error.to_string(),
+        "code section: 52 function declarations but 0 bodies in the code section"
+    );
+}
+#[test]
+fn named_function() {
+    use crate::modules::names::{NameAssoc, NameMap, NameSection};
+    let mut module = Module::from_wat("(func) (func (result i32))").unwrap();
+    let name = b"main".to_vec().into();
+    let names = NameSection {
+        funcs: NameMap(vec![NameAssoc { index: 1, name }]),
+        ..NameSection::default()
+    };
+    module
+        .sections
+        .push(Section::CustomSection((&names).into()));
+    let error = validate(&module).unwrap_err();
+    assert_eq!(error.name.as_ref().unwrap().as_bytes(), b"main");
+    assert!(error
+        .to_string()
+        .starts_with("code section, function 1 ($main), instruction"));
+}
+#[test]
+fn module_rules() {
+    use crate::{modules::DataCountSection, text};
+    let error = |module: &Module| validate(module).unwrap_err().to_string();
+    let parse = |source: &str| text::parse(source).unwrap();
+
+    let call = parse("(module (func call 1))");
+    assert!(error(&call).ends_with("function index 1 out of bounds"));
+    let imported = parse(r#"(module (import "env" "f" (func)) (func i32.const 0 call 0))"#);
+    assert!(error(&imported).starts_with("code section, function 1, instruction"));
+
+    let params = parse("(module (func (param i32)) (start 0))");
+    assert_eq!(
+        error(&params),
+        "start section, entry 0: start function must have type [] -> []"
+    );
+    let results = parse("(module (func (result i32) i32.const 0) (start 0))");
+    assert_eq!(
+        error(&results),
+        "start section, entry 0: start function must have type [] -> []"
+    );
+
+    let exports = parse(r#"(module (func) (export "f" (func 0)) (export "f" (func 0)))"#);
+    assert_eq!(
+        error(&exports),
+        r#"export section, entry 1: duplicate export name "f""#
+    );
+
+    let mut data_count = parse(r#"(module (memory 1) (data (i32.const 0) ""))"#);
+    data_count
+        .sections
+        .push(Section::DataCountSection(DataCountSection(Some(2))));
+    assert_eq!(
+        error(&data_count),
+        "data count section: declares 2 data segments but the data section has 1"
+    );
+
+    let memory = parse("(module (memory 2 1))");
+    assert_eq!(
+        error(&memory),
+        "memory section, entry 0: maximum 1 is smaller than minimum 2"
+    );
+    let table = parse("(module (table 2 1 funcref))");
+    assert_eq!(
+        error(&table),
+        "table section, entry 0: maximum 1 is smaller than minimum 2"
+    );
+}
+#[test]
+fn memory64() {
+    use crate::text;
+    use nom::error::VerboseError;
+    use wasm_core::values::{Encode, Parse};
+    let source = r#"
+        (module
+          (memory i64 1 70000)
+          (func (param i64) (result i64)
+            local.get 0
+            i64.load offset=0x1_0000_0000
+            memory.size
+            i64.add)
+          (data (i64.const 8) "wasm64"))"#;
+    let module = text::parse(source).unwrap();
+    validate(&module).unwrap();
+    assert!(text::print(&module).contains("(memory (;0;) i64 1 70000)"));
+    let bytes = module.to_bytes();
+    //one memory with flags 0x05, then the limits as LEB
+    assert!(bytes
+        .windows(6)
+        .any(|window| window == [0x01, 0x05, 0x01, 0xF0, 0xA2, 0x04]));
+    let (_, parsed) = Module::parse::<VerboseError<_>>(&bytes).unwrap();
+    assert_eq!(parsed.to_bytes(), bytes);
+
+    let error = |source: &str| validate(&text::parse(source).unwrap()).unwrap_err();
+    let address = error("(module (memory i64 1) (func (result i32) i32.const 0 i32.load))");
+    assert!(address
+        .to_string()
+        .ends_with("expected NumType(I64), found NumType(I32)"));
+    let offset =
+        error("(module (memory 1) (func (result i32) i32.const 0 i32.load offset=0x1_0000_0000))");
+    assert!(offset
+        .to_string()
+        .ends_with("offset 4294967296 out of bounds for a 32 bit memory"));
+    let data = error(r#"(module (memory i64 1) (data (i32.const 0) ""))"#);
+    assert_eq!(data.section, "data");
+}
+
+#[test]
+fn exception_handling() {
+    use crate::text;
+    use nom::error::VerboseError;
+    use wasm_core::values::{Encode, Parse};
+    let source = r#"
+        (module
+          (type (func (param i32)))
+          (import "env" "error" (tag (type 0)))
+          (memory 1)
+          (tag $empty)
+          (global i32 (i32.const 0))
+          (export "empty" (tag $empty))
+          (func (result i32)
+            (block $caught (result i32)
+              (try_table (catch 0 $caught)
+                i32.const 7
+                throw 0)
+              unreachable)
+            drop
+            (block $any (result exnref)
+              (try_table (catch_all_ref $any)
+                throw $empty)
+              unreachable)
+            throw_ref))"#;
+    let module = text::parse(source).unwrap();
+    validate(&module).unwrap();
+    let printed = text::print(&module);
+    assert!(printed.contains(r#"(import "env" "error" (tag (;0;) (type 0)))"#));
+    assert!(printed.contains("(export \"empty\" (tag 1))"));
+    assert!(printed.contains("try_table (catch 0 0)"));
+    let bytes = module.to_bytes();
+    assert_eq!(text::parse(&printed).unwrap().to_bytes(), bytes);
+    //the tag section comes between the memory and global sections
+    let ids: Vec<_> = module.sections.iter().map(Section::id).collect();
+    assert_eq!(ids, [1, 2, 3, 5, 13, 6, 7, 10]);
+    let (_, parsed) = Module::parse::<VerboseError<_>>(&bytes).unwrap();
+    assert_eq!(parsed.to_bytes(), bytes);
+
+    let error = |source: &str| validate(&text::parse(source).unwrap()).unwrap_err();
+    let label = error(
+        "(module (tag (param i32)) (func (block (try_table (catch 0 0) i32.const 0 throw 0))))",
+    );
+    assert!(label
+        .to_string()
+        .ends_with("label 0 takes [], the handler passes [NumType(I32)]"));
+    let rethrow = error("(module (func rethrow 0xFFFF_FFFF))");
+    assert!(rethrow
+        .to_string()
+        .ends_with("label 4294967295 out of bounds"));
+    let results = error("(module (type (func (result i32))) (tag (type 0)))");
+    assert!(results
+        .to_string()
+        .contains("the type of a tag must have no results"));
+}
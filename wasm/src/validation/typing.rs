@@ -0,0 +1,1265 @@
+use super::Context;
+use crate::{
+    instructions::{
+        atomic::AtomicInstruction,
+        control::{BlockType, Catch, ControlInstruction},
+        memory::{MemArg, MemoryInstruction},
+        numeric::{NumericInstruction, SaturatingTruncationInstruction},
+        parametric::ParametricInstruction,
+        reference::ReferenceInstruction,
+        simd::{RelaxedSimdInstruction, SimdInstruction},
+        table::TableInstruction,
+        variable::VariableInstruction,
+        Instruction,
+    },
+    modules::{
+        code::Func,
+        indices::{MemIdx, TagIdx},
+    },
+    types::{Mutability, NumType, RefType, ValueType},
+};
+use std::fmt;
+
+const I32: ValueType = ValueType::NumType(NumType::I32);
+const I64: ValueType = ValueType::NumType(NumType::I64);
+const F32: ValueType = ValueType::NumType(NumType::F32);
+const F64: ValueType = ValueType::NumType(NumType::F64);
+const V128: ValueType = ValueType::V128;
+const EXNREF: ValueType = ValueType::RefType(RefType::ExnRef);
+
+///one step into a function body: the index of an instruction in its sequence, the else
+///branch of the `if` named by the previous step, or a handler of its legacy `try`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    Index(usize),
+    Else,
+    ///the handler of the `catch` at this index
+    Catch(usize),
+    CatchAll,
+}
+///location of an instruction inside a nested function body, e.g. `3.else.0`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InstructionPath(pub Vec<Step>);
+impl fmt::Display for InstructionPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (n, step) in self.0.iter().enumerate() {
+            if n > 0 {
+                f.write_str(".")?;
+            }
+            match step {
+                Step::Index(index) => write!(f, "{}", index)?,
+                Step::Else => f.write_str("else")?,
+                Step::Catch(index) => write!(f, "catch{}", index)?,
+                Step::CatchAll => f.write_str("catch_all")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct TypeError {
+    pub path: InstructionPath,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameKind {
+    Function,
+    Block,
+    Loop,
+    If,
+    Else,
+    ///the body of a legacy `try`
+    Try,
+    ///a legacy `catch` or `catch_all` handler, the target of `rethrow`
+    Catch,
+}
+struct Frame {
+    kind: FrameKind,
+    start_types: Vec<ValueType>,
+    end_types: Vec<ValueType>,
+    height: usize,
+    unreachable: bool,
+}
+impl Frame {
+    fn label_types(&self) -> &[ValueType] {
+        match self.kind {
+            FrameKind::Loop => &self.start_types,
+            _ => &self.end_types,
+        }
+    }
+}
+
+///runs the spec's instruction typing algorithm over the body of function `func`
+pub fn validate_function(context: &Context, func: u32, code: &Func) -> Result<(), TypeError> {
+    let func_type = context.func_type(func).ok_or_else(|| TypeError {
+        path: InstructionPath::default(),
+        message: format!("function {} has no type", func),
+    })?;
+    //the declared counts are kept as runs, they may add up to billions
+    let params = func_type.rt1.iter().map(|param| (1, *param));
+    let declared = code
+        .locals
+        .iter()
+        .map(|local| (local.count, local.value_type));
+    let mut locals = vec![];
+    let mut total = 0u64;
+    for (count, value_type) in params.chain(declared) {
+        total += count as u64;
+        if total > u32::MAX as u64 {
+            return Err(TypeError {
+                path: InstructionPath::default(),
+                message: "too many locals".to_string(),
+            });
+        }
+        if count > 0 {
+            locals.push((total, value_type));
+        }
+    }
+    let mut checker = Checker {
+        context,
+        locals,
+        vals: vec![],
+        ctrls: vec![],
+        path: vec![],
+    };
+    checker.push_ctrl(FrameKind::Function, vec![], func_type.rt2.clone());
+    checker.check_sequence(&code.body.0)?;
+    checker.pop_ctrl()?;
+    Ok(())
+}
+
+struct Checker<'a, 'c> {
+    context: &'c Context<'a>,
+    ///runs of locals, params first, as the index past their end and their type
+    locals: Vec<(u64, ValueType)>,
+    ///`None` stands for the unknown type of values popped from an unreachable stack
+    vals: Vec<Option<ValueType>>,
+    ctrls: Vec<Frame>,
+    path: Vec<Step>,
+}
+
+impl<'a, 'c> Checker<'a, 'c> {
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, TypeError> {
+        Err(TypeError {
+            path: InstructionPath(self.path.clone()),
+            message: message.into(),
+        })
+    }
+
+    fn push_val(&mut self, val: ValueType) {
+        self.vals.push(Some(val));
+    }
+    fn pop_val(&mut self) -> Result<Option<ValueType>, TypeError> {
+        let frame = self.ctrls.last().expect("no control frame");
+        if self.vals.len() == frame.height {
+            if frame.unreachable {
+                return Ok(None);
+            }
+            return self.error("value stack underflow");
+        }
+        Ok(self.vals.pop().flatten())
+    }
+    fn pop_expect(&mut self, expected: ValueType) -> Result<Option<ValueType>, TypeError> {
+        let actual = self.pop_val()?;
+        match actual {
+            Some(actual) if actual != expected => {
+                self.error(format!("expected {:?}, found {:?}", expected, actual))
+            }
+            _ => Ok(actual),
+        }
+    }
+    fn push_vals(&mut self, vals: &[ValueType]) {
+        for val in vals {
+            self.push_val(*val);
+        }
+    }
+    fn pop_vals(&mut self, vals: &[ValueType]) -> Result<(), TypeError> {
+        for val in vals.iter().rev() {
+            self.pop_expect(*val)?;
+        }
+        Ok(())
+    }
+    fn pop_ref(&mut self) -> Result<(), TypeError> {
+        match self.pop_val()? {
+            Some(ValueType::RefType(_)) | None => Ok(()),
+            Some(found) => self.error(format!("expected a reference, found {:?}", found)),
+        }
+    }
+
+    fn push_ctrl(
+        &mut self,
+        kind: FrameKind,
+        start_types: Vec<ValueType>,
+        end_types: Vec<ValueType>,
+    ) {
+        let height = self.vals.len();
+        self.push_vals(&start_types);
+        self.ctrls.push(Frame {
+            kind,
+            start_types,
+            end_types,
+            height,
+            unreachable: false,
+        });
+    }
+    fn pop_ctrl(&mut self) -> Result<Frame, TypeError> {
+        let frame = self.ctrls.last().expect("no control frame");
+        let end_types = frame.end_types.clone();
+        let height = frame.height;
+        self.pop_vals(&end_types)?;
+        if self.vals.len() != height {
+            let extra = self.vals.len() - height;
+            return self.error(format!(
+                "{} values left on the stack at end of block",
+                extra
+            ));
+        }
+        Ok(self.ctrls.pop().unwrap())
+    }
+    fn unreachable(&mut self) {
+        let frame = self.ctrls.last_mut().expect("no control frame");
+        self.vals.truncate(frame.height);
+        frame.unreachable = true;
+    }
+    fn label_types(&self, label: u32) -> Result<Vec<ValueType>, TypeError> {
+        let depth = label as usize;
+        if depth >= self.ctrls.len() {
+            return self.error(format!("label {} out of bounds", label));
+        }
+        let frame = &self.ctrls[self.ctrls.len() - 1 - depth];
+        Ok(frame.label_types().to_vec())
+    }
+
+    fn block_type(&self, block: &BlockType) -> Result<(Vec<ValueType>, Vec<ValueType>), TypeError> {
+        match block {
+            BlockType::Empty => Ok((vec![], vec![])),
+            BlockType::ValType(val_type) => Ok((vec![], vec![*val_type])),
+            BlockType::TypeIdx(type_idx) => match self.context.types.get(*type_idx as usize) {
+                Some(func_type) => Ok((func_type.rt1.clone(), func_type.rt2.clone())),
+                None => self.error(format!("type index {} out of bounds", type_idx)),
+            },
+        }
+    }
+
+    fn tag_params(&self, tag: TagIdx) -> Result<Vec<ValueType>, TypeError> {
+        match self.context.tag_type(tag) {
+            Some(func_type) => Ok(func_type.rt1.clone()),
+            None => self.error(format!("tag index {} out of bounds", tag)),
+        }
+    }
+    ///checks that the label of a `try_table` handler takes the values it catches
+    fn check_catch(&self, catch: &Catch) -> Result<(), TypeError> {
+        let (caught, label) = match catch {
+            Catch::Catch(tag, label) => (self.tag_params(*tag)?, label),
+            Catch::CatchRef(tag, label) => {
+                let mut caught = self.tag_params(*tag)?;
+                caught.push(EXNREF);
+                (caught, label)
+            }
+            Catch::CatchAll(label) => (vec![], label),
+            Catch::CatchAllRef(label) => (vec![EXNREF], label),
+        };
+        let types = self.label_types(*label)?;
+        if types != caught {
+            return self.error(format!(
+                "label {} takes {:?}, the handler passes {:?}",
+                label, types, caught
+            ));
+        }
+        Ok(())
+    }
+
+    fn check_sequence(&mut self, instructions: &[Instruction]) -> Result<(), TypeError> {
+        for (index, instruction) in instructions.iter().enumerate() {
+            self.path.push(Step::Index(index));
+            self.check(instruction)?;
+            self.path.pop();
+        }
+        Ok(())
+    }
+
+    fn check(&mut self, instruction: &Instruction) -> Result<(), TypeError> {
+        match instruction {
+            Instruction::Control(instruction) => self.check_control(instruction),
+            Instruction::Reference(instruction) => self.check_reference(instruction),
+            Instruction::Parametric(instruction) => self.check_parametric(instruction),
+            Instruction::Variable(instruction) => self.check_variable(instruction),
+            Instruction::Table(instruction) => self.check_table(instruction),
+            Instruction::Memory(instruction) => self.check_memory(instruction),
+            Instruction::Numeric(instruction) => {
+                let (params, result) = numeric_type(instruction);
+                self.pop_vals(params)?;
+                self.push_val(result);
+                Ok(())
+            }
+            Instruction::Saturating(instruction) => {
+                let (param, result) = saturating_type(instruction);
+                self.pop_expect(param)?;
+                self.push_val(result);
+                Ok(())
+            }
+            Instruction::Simd(instruction) => self.check_simd(instruction),
+            Instruction::Atomic(instruction) => self.check_atomic(instruction),
+            Instruction::RelaxedSimd(instruction) => {
+                self.pop_vals(relaxed_simd_type(instruction))?;
+                self.push_val(V128);
+                Ok(())
+            }
+        }
+    }
+
+    fn check_control(&mut self, instruction: &ControlInstruction) -> Result<(), TypeError> {
+        match instruction {
+            ControlInstruction::Unreachable => self.unreachable(),
+            ControlInstruction::Nop => {}
+            ControlInstruction::Block(block, body) | ControlInstruction::Loop(block, body) => {
+                let kind = match instruction {
+                    ControlInstruction::Loop(..) => FrameKind::Loop,
+                    _ => FrameKind::Block,
+                };
+                let (params, results) = self.block_type(block)?;
+                self.pop_vals(&params)?;
+                self.push_ctrl(kind, params, results);
+                self.check_sequence(&body.0)?;
+                let frame = self.pop_ctrl()?;
+                self.push_vals(&frame.end_types);
+            }
+            ControlInstruction::IfElse(block, if_branch, else_branch) => {
+                let (params, results) = self.block_type(block)?;
+                self.pop_expect(I32)?;
+                self.pop_vals(&params)?;
+                self.push_ctrl(FrameKind::If, params.clone(), results.clone());
+                self.check_sequence(&if_branch.0)?;
+                self.pop_ctrl()?;
+                self.path.push(Step::Else);
+                self.push_ctrl(FrameKind::Else, params, results);
+                //without an `else`, the branch is empty
+                self.check_sequence(else_branch.as_ref().map_or(&[], |branch| &branch.0))?;
+                let frame = self.pop_ctrl()?;
+                self.path.pop();
+                self.push_vals(&frame.end_types);
+            }
+            ControlInstruction::Br(label) => {
+                let types = self.label_types(*label)?;
+                self.pop_vals(&types)?;
+                self.unreachable();
+            }
+            ControlInstruction::BrIf(label) => {
+                self.pop_expect(I32)?;
+                let types = self.label_types(*label)?;
+                self.pop_vals(&types)?;
+                self.push_vals(&types);
+            }
+            ControlInstruction::BrTable { table, default } => {
+                self.pop_expect(I32)?;
+                let default_types = self.label_types(*default)?;
+                let arity = default_types.len();
+                for label in table {
+                    let types = self.label_types(*label)?;
+                    if types.len() != arity {
+                        return self.error(format!(
+                            "label {} has arity {}, default label has arity {}",
+                            label,
+                            types.len(),
+                            arity
+                        ));
+                    }
+                    //each target is checked against the same operands without consuming them
+                    let mut popped = vec![];
+                    for val in types.iter().rev() {
+                        popped.push(self.pop_expect(*val)?);
+                    }
+                    self.vals.extend(popped.into_iter().rev());
+                }
+                self.pop_vals(&default_types)?;
+                self.unreachable();
+            }
+            ControlInstruction::Return => {
+                let types = self.ctrls[0].end_types.clone();
+                self.pop_vals(&types)?;
+                self.unreachable();
+            }
+            ControlInstruction::Call(func) => match self.context.func_type(*func) {
+                Some(func_type) => {
+                    self.pop_vals(&func_type.rt1)?;
+                    self.push_vals(&func_type.rt2);
+                }
+                None => return self.error(format!("function index {} out of bounds", func)),
+            },
+            ControlInstruction::CallIndirect(type_idx, table) => {
+                match self.context.tables.get(*table as usize) {
+                    Some(table_type) if table_type.et == RefType::FuncRef => {}
+                    Some(_) => {
+                        return self.error(format!("table {} is not a funcref table", table))
+                    }
+                    None => return self.error(format!("table index {} out of bounds", table)),
+                }
+                let func_type = match self.context.types.get(*type_idx as usize) {
+                    Some(func_type) => *func_type,
+                    None => return self.error(format!("type index {} out of bounds", type_idx)),
+                };
+                self.pop_expect(I32)?;
+                self.pop_vals(&func_type.rt1)?;
+                self.push_vals(&func_type.rt2);
+            }
+            ControlInstruction::Throw(tag) => {
+                let params = self.tag_params(*tag)?;
+                self.pop_vals(&params)?;
+                self.unreachable();
+            }
+            ControlInstruction::ThrowRef => {
+                self.pop_expect(EXNREF)?;
+                self.unreachable();
+            }
+            ControlInstruction::TryTable(block, catches, body) => {
+                let (params, results) = self.block_type(block)?;
+                //the handlers branch from outside of the block
+                for catch in catches {
+                    self.check_catch(catch)?;
+                }
+                self.pop_vals(&params)?;
+                self.push_ctrl(FrameKind::Block, params, results);
+                self.check_sequence(&body.0)?;
+                let frame = self.pop_ctrl()?;
+                self.push_vals(&frame.end_types);
+            }
+            ControlInstruction::Try {
+                block,
+                body,
+                catches,
+                catch_all,
+            } => {
+                let (params, results) = self.block_type(block)?;
+                self.pop_vals(&params)?;
+                self.push_ctrl(FrameKind::Try, params, results.clone());
+                self.check_sequence(&body.0)?;
+                self.pop_ctrl()?;
+                for (index, (tag, handler)) in catches.iter().enumerate() {
+                    self.path.push(Step::Catch(index));
+                    let caught = self.tag_params(*tag)?;
+                    self.push_ctrl(FrameKind::Catch, caught, results.clone());
+                    self.check_sequence(&handler.0)?;
+                    self.pop_ctrl()?;
+                    self.path.pop();
+                }
+                if let Some(handler) = catch_all {
+                    self.path.push(Step::CatchAll);
+                    self.push_ctrl(FrameKind::Catch, vec![], results.clone());
+                    self.check_sequence(&handler.0)?;
+                    self.pop_ctrl()?;
+                    self.path.pop();
+                }
+                self.push_vals(&results);
+            }
+            ControlInstruction::Delegate(block, body, label) => {
+                let (params, results) = self.block_type(block)?;
+                self.pop_vals(&params)?;
+                self.push_ctrl(FrameKind::Try, params, results);
+                self.check_sequence(&body.0)?;
+                let frame = self.pop_ctrl()?;
+                self.label_types(*label)?;
+                self.push_vals(&frame.end_types);
+            }
+            ControlInstruction::Rethrow(label) => {
+                let depth = *label as usize;
+                let frame = (depth < self.ctrls.len())
+                    .then(|| self.ctrls[self.ctrls.len() - 1 - depth].kind);
+                match frame {
+                    Some(FrameKind::Catch) => {}
+                    Some(_) => {
+                        return self.error(format!("label {} is not a catch handler", label))
+                    }
+                    None => return self.error(format!("label {} out of bounds", label)),
+                }
+                self.unreachable();
+            }
+        }
+        Ok(())
+    }
+
+    fn check_reference(&mut self, instruction: &ReferenceInstruction) -> Result<(), TypeError> {
+        match instruction {
+            ReferenceInstruction::Null(ref_type) => self.push_val(ValueType::RefType(*ref_type)),
+            ReferenceInstruction::IsNull => {
+                self.pop_ref()?;
+                self.push_val(I32);
+            }
+            ReferenceInstruction::Func(func) => {
+                if *func as usize >= self.context.funcs.len() {
+                    return self.error(format!("function index {} out of bounds", func));
+                }
+                if !self.context.refs.contains(func) {
+                    return self.error(format!("function {} is not declared as a reference", func));
+                }
+                self.push_val(ValueType::RefType(RefType::FuncRef));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_parametric(&mut self, instruction: &ParametricInstruction) -> Result<(), TypeError> {
+        match instruction {
+            ParametricInstruction::Drop => {
+                self.pop_val()?;
+            }
+            ParametricInstruction::Select => {
+                self.pop_expect(I32)?;
+                let t1 = self.pop_val()?;
+                let t2 = self.pop_val()?;
+                let is_ref = |t: Option<ValueType>| matches!(t, Some(ValueType::RefType(_)));
+                if is_ref(t1) || is_ref(t2) {
+                    return self.error("untyped select requires numeric operands");
+                }
+                match (t1, t2) {
+                    (Some(t1), Some(t2)) if t1 != t2 => {
+                        return self.error(format!("select operands {:?} and {:?} differ", t1, t2))
+                    }
+                    (Some(t), _) | (_, Some(t)) => self.push_val(t),
+                    (None, None) => self.vals.push(None),
+                }
+            }
+            ParametricInstruction::SelectTyped(types) => {
+                let t = match &types[..] {
+                    [t] => *t,
+                    _ => return self.error("typed select must have exactly one type"),
+                };
+                self.pop_expect(I32)?;
+                self.pop_expect(t)?;
+                self.pop_expect(t)?;
+                self.push_val(t);
+            }
+        }
+        Ok(())
+    }
+
+    fn local(&self, local: u32) -> Result<ValueType, TypeError> {
+        let run = self.locals.partition_point(|(end, _)| *end <= local as u64);
+        match self.locals.get(run) {
+            Some((_, val_type)) => Ok(*val_type),
+            None => self.error(format!("local index {} out of bounds", local)),
+        }
+    }
+
+    fn check_variable(&mut self, instruction: &VariableInstruction) -> Result<(), TypeError> {
+        match instruction {
+            VariableInstruction::LocalGet(local) => {
+                let t = self.local(*local)?;
+                self.push_val(t);
+            }
+            VariableInstruction::LocalSet(local) => {
+                let t = self.local(*local)?;
+                self.pop_expect(t)?;
+            }
+            VariableInstruction::LocalTee(local) => {
+                let t = self.local(*local)?;
+                self.pop_expect(t)?;
+                self.push_val(t);
+            }
+            VariableInstruction::GlobalGet(global) => {
+                match self.context.globals.get(*global as usize) {
+                    Some(global_type) => self.push_val(global_type.t),
+                    None => return self.error(format!("global index {} out of bounds", global)),
+                }
+            }
+            VariableInstruction::GlobalSet(global) => {
+                match self.context.globals.get(*global as usize) {
+                    Some(global_type) if global_type.m == Mutability::Var => {
+                        self.pop_expect(global_type.t)?;
+                    }
+                    Some(_) => return self.error(format!("global {} is immutable", global)),
+                    None => return self.error(format!("global index {} out of bounds", global)),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn table(&self, table: u32) -> Result<ValueType, TypeError> {
+        match self.context.tables.get(table as usize) {
+            Some(table_type) => Ok(ValueType::RefType(table_type.et)),
+            None => self.error(format!("table index {} out of bounds", table)),
+        }
+    }
+    fn elem(&self, elem: u32) -> Result<ValueType, TypeError> {
+        match self.context.elems.get(elem as usize) {
+            Some(ref_type) => Ok(ValueType::RefType(*ref_type)),
+            None => self.error(format!("element segment index {} out of bounds", elem)),
+        }
+    }
+
+    fn check_table(&mut self, instruction: &TableInstruction) -> Result<(), TypeError> {
+        match instruction {
+            TableInstruction::TableGet(table) => {
+                let t = self.table(*table)?;
+                self.pop_expect(I32)?;
+                self.push_val(t);
+            }
+            TableInstruction::TableSet(table) => {
+                let t = self.table(*table)?;
+                self.pop_expect(t)?;
+                self.pop_expect(I32)?;
+            }
+            TableInstruction::TableInit(elem, table) => {
+                let (t1, t2) = (self.table(*table)?, self.elem(*elem)?);
+                if t1 != t2 {
+                    return self.error(format!("cannot initialize {:?} table with {:?}", t1, t2));
+                }
+                self.pop_vals(&[I32, I32, I32])?;
+            }
+            TableInstruction::TableDrop(elem) => {
+                self.elem(*elem)?;
+            }
+            TableInstruction::TableCopy(destination, source) => {
+                let (t1, t2) = (self.table(*destination)?, self.table(*source)?);
+                if t1 != t2 {
+                    return self.error(format!("cannot copy {:?} table into {:?} table", t2, t1));
+                }
+                self.pop_vals(&[I32, I32, I32])?;
+            }
+            TableInstruction::TableGrow(table) => {
+                let t = self.table(*table)?;
+                self.pop_vals(&[t, I32])?;
+                self.push_val(I32);
+            }
+            TableInstruction::TableSize(table) => {
+                self.table(*table)?;
+                self.push_val(I32);
+            }
+            TableInstruction::TableFill(table) => {
+                let t = self.table(*table)?;
+                self.pop_vals(&[I32, t, I32])?;
+            }
+        }
+        Ok(())
+    }
+
+    fn memory(&self, memory: MemIdx) -> Result<(), TypeError> {
+        if memory as usize >= self.context.mems.len() {
+            return self.error(format!("memory index {} out of bounds", memory));
+        }
+        Ok(())
+    }
+    fn data(&self, data: u32) -> Result<(), TypeError> {
+        match self.context.data_count {
+            Some(count) if data < count => Ok(()),
+            Some(_) => self.error(format!("data segment index {} out of bounds", data)),
+            None => self.error("data segment instructions require a data count section"),
+        }
+    }
+
+    fn check_memory(&mut self, instruction: &MemoryInstruction) -> Result<(), TypeError> {
+        use MemoryInstruction::*;
+        match instruction {
+            Size(memory) => {
+                let address = self.address(*memory)?;
+                self.push_val(address);
+            }
+            Grow(memory) => {
+                let address = self.address(*memory)?;
+                self.pop_expect(address)?;
+                self.push_val(address);
+            }
+            Init(_, data, memory) => {
+                let address = self.address(*memory)?;
+                self.data(*data)?;
+                self.pop_vals(&[address, I32, I32])?;
+            }
+            Drop(_, data) => self.data(*data)?,
+            Copy(_, destination, source) => {
+                let destination = self.address(*destination)?;
+                let source = self.address(*source)?;
+                //the length fits in both memories
+                let length = if destination == I64 && source == I64 {
+                    I64
+                } else {
+                    I32
+                };
+                self.pop_vals(&[destination, source, length])?;
+            }
+            Fill(_, memory) => {
+                let address = self.address(*memory)?;
+                self.pop_vals(&[address, I32, address])?;
+            }
+            I32Load(mem_arg) => self.load(mem_arg, 32, I32)?,
+            I64Load(mem_arg) => self.load(mem_arg, 64, I64)?,
+            F32Load(mem_arg) => self.load(mem_arg, 32, F32)?,
+            F64Load(mem_arg) => self.load(mem_arg, 64, F64)?,
+            I32Load8S(mem_arg) | I32Load8U(mem_arg) => self.load(mem_arg, 8, I32)?,
+            I32Load16S(mem_arg) | I32Load16U(mem_arg) => self.load(mem_arg, 16, I32)?,
+            I64Load8S(mem_arg) | I64Load8U(mem_arg) => self.load(mem_arg, 8, I64)?,
+            I64Load16S(mem_arg) | I64Load16U(mem_arg) => self.load(mem_arg, 16, I64)?,
+            I64Load32S(mem_arg) | I64Load32U(mem_arg) => self.load(mem_arg, 32, I64)?,
+            I32Store(mem_arg) => self.store(mem_arg, 32, I32)?,
+            I64Store(mem_arg) => self.store(mem_arg, 64, I64)?,
+            F32Store(mem_arg) => self.store(mem_arg, 32, F32)?,
+            F64Store(mem_arg) => self.store(mem_arg, 64, F64)?,
+            I32Store8(mem_arg) => self.store(mem_arg, 8, I32)?,
+            I32Store16(mem_arg) => self.store(mem_arg, 16, I32)?,
+            I64Store8(mem_arg) => self.store(mem_arg, 8, I64)?,
+            I64Store16(mem_arg) => self.store(mem_arg, 16, I64)?,
+            I64Store32(mem_arg) => self.store(mem_arg, 32, I64)?,
+        }
+        Ok(())
+    }
+    ///the type of the addresses of `memory`
+    fn address(&self, memory: MemIdx) -> Result<ValueType, TypeError> {
+        self.memory(memory)?;
+        Ok(self.context.mems[memory as usize]
+            .lim
+            .index_type
+            .value_type())
+    }
+    ///checks the immediate of an access of `width` bits, returning the type of the address
+    fn mem_arg(&self, mem_arg: &MemArg, width: u32) -> Result<ValueType, TypeError> {
+        let address = self.address(mem_arg.memory)?;
+        if mem_arg.align >= 32 || 1_u64 << mem_arg.align > (width / 8) as u64 {
+            return self.error(format!(
+                "alignment 2^{} exceeds natural alignment of {} bytes",
+                mem_arg.align,
+                width / 8
+            ));
+        }
+        if address == I32 && mem_arg.offset > u32::MAX as u64 {
+            return self.error(format!(
+                "offset {} out of bounds for a 32 bit memory",
+                mem_arg.offset
+            ));
+        }
+        Ok(address)
+    }
+    fn load(&mut self, mem_arg: &MemArg, width: u32, t: ValueType) -> Result<(), TypeError> {
+        let address = self.mem_arg(mem_arg, width)?;
+        self.pop_expect(address)?;
+        self.push_val(t);
+        Ok(())
+    }
+    fn store(&mut self, mem_arg: &MemArg, width: u32, t: ValueType) -> Result<(), TypeError> {
+        let address = self.mem_arg(mem_arg, width)?;
+        self.pop_vals(&[address, t])
+    }
+
+    fn check_atomic(&mut self, instruction: &AtomicInstruction) -> Result<(), TypeError> {
+        let (params, result) = atomic_type(instruction);
+        let mut params = params.to_vec();
+        if let Some((mem_arg, width)) = instruction.memory_access() {
+            //the address is the first operand
+            params[0] = self.mem_arg(mem_arg, width)?;
+            if 1_u64 << mem_arg.align != (width / 8) as u64 {
+                return self.error(format!(
+                    "atomic alignment 2^{} is not the natural alignment of {} bytes",
+                    mem_arg.align,
+                    width / 8
+                ));
+            }
+        }
+        self.pop_vals(&params)?;
+        if let Some(result) = result {
+            self.push_val(result);
+        }
+        Ok(())
+    }
+
+    fn check_simd(&mut self, instruction: &SimdInstruction) -> Result<(), TypeError> {
+        let (params, result) = simd_type(instruction);
+        let mut params = params.to_vec();
+        if let Some((mem_arg, width)) = instruction.memory_access() {
+            params[0] = self.mem_arg(mem_arg, width)?;
+        }
+        if let Some((lane, lanes)) = instruction.lane() {
+            if lane >= lanes {
+                return self.error(format!("lane index {} out of bounds", lane));
+            }
+        }
+        if let SimdInstruction::I8x16Shuffle(lanes) = instruction {
+            if let Some(lane) = lanes.0.iter().find(|lane| **lane >= 32) {
+                return self.error(format!("shuffle lane index {} out of bounds", lane));
+            }
+        }
+        self.pop_vals(&params)?;
+        if let Some(result) = result {
+            self.push_val(result);
+        }
+        Ok(())
+    }
+}
+
+///operand types and result type of a numeric instruction
+fn numeric_type(instruction: &NumericInstruction) -> (&'static [ValueType], ValueType) {
+    use NumericInstruction::*;
+    match instruction {
+        I32Const(_) => (&[], I32),
+        I64Const(_) => (&[], I64),
+        F32Const(_) => (&[], F32),
+        F64Const(_) => (&[], F64),
+        I32Eqz => (&[I32], I32),
+        I32Eq | I32Ne | I32LtS | I32LtU | I32GtS | I32GtU | I32LeS | I32LeU | I32GeS | I32GeU => {
+            (&[I32, I32], I32)
+        }
+        I64Eqz => (&[I64], I32),
+        I64Eq | I64Ne | I64LtS | I64LtU | I64GtS | I64GtU | I64LeS | I64LeU | I64GeS | I64GeU => {
+            (&[I64, I64], I32)
+        }
+        F32Eq | F32Ne | F32Lt | F32Gt | F32Le | F32Ge => (&[F32, F32], I32),
+        F64Eq | F64Ne | F64Lt | F64Gt | F64Le | F64Ge => (&[F64, F64], I32),
+        I32Clz | I32Ctz | I32Popcnt => (&[I32], I32),
+        I32Add | I32Sub | I32Mul | I32DivS | I32DivU | I32RemS | I32RemU | I32And | I32Or
+        | I32Xor | I32Shl | I32ShrS | I32ShrU | I32Rotl | I32Rotr => (&[I32, I32], I32),
+        I64Clz | I64Ctz | I64Popcnt => (&[I64], I64),
+        I64Add | I64Sub | I64Mul | I64DivS | I64DivU | I64RemS | I64RemU | I64And | I64Or
+        | I64Xor | I64Shl | I64ShrS | I64ShrU | I64Rotl | I64Rotr => (&[I64, I64], I64),
+        F32Abs | F32Neg | F32Ceil | F32Floor | F32Trunc | F32Nearest | F32Sqrt => (&[F32], F32),
+        F32Add | F32Sub | F32Mul | F32Div | F32Min | F32Max | F32CopySign => (&[F32, F32], F32),
+        F64Abs | F64Neg | F64Ceil | F64Floor | F64Trunc | F64Nearest | F64Sqrt => (&[F64], F64),
+        F64Add | F64Sub | F64Mul | F64Div | F64Min | F64Max | F64CopySign => (&[F64, F64], F64),
+        I32WrapI64 => (&[I64], I32),
+        I32TruncF32S | I32TruncF32U => (&[F32], I32),
+        I32TruncF64S | I32TruncF64U => (&[F64], I32),
+        I64ExtendI32S | I64ExtendI32U => (&[I32], I64),
+        I64TruncF32S | I64TruncF32U => (&[F32], I64),
+        I64TruncF64S | I64TruncF64U => (&[F64], I64),
+        F32ConvertI32S | F32ConvertI32U => (&[I32], F32),
+        F32ConvertI64S | F32ConvertI64u => (&[I64], F32),
+        F32DemoteF64 => (&[F64], F32),
+        F64ConvertI32S | F64ConvertI32U => (&[I32], F64),
+        F64ConvertI64S | F64ConvertI64u => (&[I64], F64),
+        F64PromoteF32 => (&[F32], F64),
+        I32ReinterpretF32 => (&[F32], I32),
+        I64ReinterpretF64 => (&[F64], I64),
+        F32ReinterpretI32 => (&[I32], F32),
+        F64ReinterpretI64 => (&[I64], F64),
+        I32Extend8S | I32Extend16S => (&[I32], I32),
+        I64Extend8S | I64Extend16S | I64Extend32S => (&[I64], I64),
+    }
+}
+
+fn saturating_type(instruction: &SaturatingTruncationInstruction) -> (ValueType, ValueType) {
+    use SaturatingTruncationInstruction::*;
+    match instruction {
+        I32TruncSatF32S | I32TruncSatF32U => (F32, I32),
+        I32TruncSatF64S | I32TruncSatF64U => (F64, I32),
+        I64TruncSatF32S | I64TruncSatF32U => (F32, I64),
+        I64TruncSatF64S | I64TruncSatF64U => (F64, I64),
+    }
+}
+
+///operand types and result type, if any, of an atomic instruction
+fn atomic_type(instruction: &AtomicInstruction) -> (&'static [ValueType], Option<ValueType>) {
+    use AtomicInstruction::*;
+    match instruction {
+        MemoryAtomicNotify(_)
+        | I32AtomicRmwAdd(_)
+        | I32AtomicRmw8AddU(_)
+        | I32AtomicRmw16AddU(_)
+        | I32AtomicRmwSub(_)
+        | I32AtomicRmw8SubU(_)
+        | I32AtomicRmw16SubU(_)
+        | I32AtomicRmwAnd(_)
+        | I32AtomicRmw8AndU(_)
+        | I32AtomicRmw16AndU(_)
+        | I32AtomicRmwOr(_)
+        | I32AtomicRmw8OrU(_)
+        | I32AtomicRmw16OrU(_)
+        | I32AtomicRmwXor(_)
+        | I32AtomicRmw8XorU(_)
+        | I32AtomicRmw16XorU(_)
+        | I32AtomicRmwXchg(_)
+        | I32AtomicRmw8XchgU(_)
+        | I32AtomicRmw16XchgU(_) => (&[I32, I32], Some(I32)),
+        MemoryAtomicWait32(_) => (&[I32, I32, I64], Some(I32)),
+        MemoryAtomicWait64(_) => (&[I32, I64, I64], Some(I32)),
+        I32AtomicLoad(_) | I32AtomicLoad8U(_) | I32AtomicLoad16U(_) => (&[I32], Some(I32)),
+        I64AtomicLoad(_) | I64AtomicLoad8U(_) | I64AtomicLoad16U(_) | I64AtomicLoad32U(_) => {
+            (&[I32], Some(I64))
+        }
+        I32AtomicStore(_) | I32AtomicStore8(_) | I32AtomicStore16(_) => (&[I32, I32], None),
+        I64AtomicStore(_) | I64AtomicStore8(_) | I64AtomicStore16(_) | I64AtomicStore32(_) => {
+            (&[I32, I64], None)
+        }
+        I64AtomicRmwAdd(_)
+        | I64AtomicRmw8AddU(_)
+        | I64AtomicRmw16AddU(_)
+        | I64AtomicRmw32AddU(_)
+        | I64AtomicRmwSub(_)
+        | I64AtomicRmw8SubU(_)
+        | I64AtomicRmw16SubU(_)
+        | I64AtomicRmw32SubU(_)
+        | I64AtomicRmwAnd(_)
+        | I64AtomicRmw8AndU(_)
+        | I64AtomicRmw16AndU(_)
+        | I64AtomicRmw32AndU(_)
+        | I64AtomicRmwOr(_)
+        | I64AtomicRmw8OrU(_)
+        | I64AtomicRmw16OrU(_)
+        | I64AtomicRmw32OrU(_)
+        | I64AtomicRmwXor(_)
+        | I64AtomicRmw8XorU(_)
+        | I64AtomicRmw16XorU(_)
+        | I64AtomicRmw32XorU(_)
+        | I64AtomicRmwXchg(_)
+        | I64AtomicRmw8XchgU(_)
+        | I64AtomicRmw16XchgU(_)
+        | I64AtomicRmw32XchgU(_) => (&[I32, I64], Some(I64)),
+        I32AtomicRmwCmpxchg(_) | I32AtomicRmw8CmpxchgU(_) | I32AtomicRmw16CmpxchgU(_) => {
+            (&[I32, I32, I32], Some(I32))
+        }
+        I64AtomicRmwCmpxchg(_)
+        | I64AtomicRmw8CmpxchgU(_)
+        | I64AtomicRmw16CmpxchgU(_)
+        | I64AtomicRmw32CmpxchgU(_) => (&[I32, I64, I64], Some(I64)),
+        AtomicFence(_) => (&[], None),
+    }
+}
+
+///operand types of a relaxed SIMD instruction, which all return a `v128`
+fn relaxed_simd_type(instruction: &RelaxedSimdInstruction) -> &'static [ValueType] {
+    use RelaxedSimdInstruction::*;
+    match instruction {
+        I32x4RelaxedTruncF32x4S
+        | I32x4RelaxedTruncF32x4U
+        | I32x4RelaxedTruncF64x2SZero
+        | I32x4RelaxedTruncF64x2UZero => &[V128],
+        I8x16RelaxedSwizzle
+        | F32x4RelaxedMin
+        | F32x4RelaxedMax
+        | F64x2RelaxedMin
+        | F64x2RelaxedMax
+        | I16x8RelaxedQ15mulrS
+        | I16x8RelaxedDotI8x16I7x16S => &[V128, V128],
+        F32x4RelaxedMadd
+        | F32x4RelaxedNmadd
+        | F64x2RelaxedMadd
+        | F64x2RelaxedNmadd
+        | I8x16RelaxedLaneselect
+        | I16x8RelaxedLaneselect
+        | I32x4RelaxedLaneselect
+        | I64x2RelaxedLaneselect
+        | I32x4RelaxedDotI8x16I7x16AddS => &[V128, V128, V128],
+    }
+}
+
+///operand types and result type, if any, of a SIMD instruction
+fn simd_type(instruction: &SimdInstruction) -> (&'static [ValueType], Option<ValueType>) {
+    use SimdInstruction::*;
+    match instruction {
+        V128Load(_) | V128Load8x8S(_) | V128Load8x8U(_) | V128Load16x4S(_) | V128Load16x4U(_)
+        | V128Load32x2S(_) | V128Load32x2U(_) | V128Load8Splat(_) | V128Load16Splat(_)
+        | V128Load32Splat(_) | V128Load64Splat(_) | I8x16Splat | I16x8Splat | I32x4Splat
+        | V128Load32Zero(_) | V128Load64Zero(_) => (&[I32], Some(V128)),
+        V128Store(_) | V128Store8Lane(_) | V128Store16Lane(_) | V128Store32Lane(_)
+        | V128Store64Lane(_) => (&[I32, V128], None),
+        V128Const(_) => (&[], Some(V128)),
+        I8x16Shuffle(_)
+        | I8x16Swizzle
+        | I8x16Eq
+        | I8x16Ne
+        | I8x16LtS
+        | I8x16LtU
+        | I8x16GtS
+        | I8x16GtU
+        | I8x16LeS
+        | I8x16LeU
+        | I8x16GeS
+        | I8x16GeU
+        | I16x8Eq
+        | I16x8Ne
+        | I16x8LtS
+        | I16x8LtU
+        | I16x8GtS
+        | I16x8GtU
+        | I16x8LeS
+        | I16x8LeU
+        | I16x8GeS
+        | I16x8GeU
+        | I32x4Eq
+        | I32x4Ne
+        | I32x4LtS
+        | I32x4LtU
+        | I32x4GtS
+        | I32x4GtU
+        | I32x4LeS
+        | I32x4LeU
+        | I32x4GeS
+        | I32x4GeU
+        | F32x4Eq
+        | F32x4Ne
+        | F32x4Lt
+        | F32x4Gt
+        | F32x4Le
+        | F32x4Ge
+        | F64x2Eq
+        | F64x2Ne
+        | F64x2Lt
+        | F64x2Gt
+        | F64x2Le
+        | F64x2Ge
+        | V128And
+        | V128AndNot
+        | V128Or
+        | V128Xor
+        | I8x16NarrowI16x8S
+        | I8x16NarrowI16x8U
+        | I8x16Add
+        | I8x16AddSatS
+        | I8x16AddSatU
+        | I8x16Sub
+        | I8x16SubSatS
+        | I8x16SubSatU
+        | I8x16MinS
+        | I8x16MinU
+        | I8x16MaxS
+        | I8x16MaxU
+        | I8x16AvgrU
+        | I16x8Q15mulrSatS
+        | I16x8NarrowI32x4S
+        | I16x8NarrowI32x4U
+        | I16x8Add
+        | I16x8AddSatS
+        | I16x8AddSatU
+        | I16x8Sub
+        | I16x8SubSatS
+        | I16x8SubSatU
+        | I16x8Mul
+        | I16x8MinS
+        | I16x8MinU
+        | I16x8MaxS
+        | I16x8MaxU
+        | I16x8AvgrU
+        | I16x8ExtmulLowI8x16S
+        | I16x8ExtmulHighI8x16S
+        | I16x8ExtmulLowI8x16U
+        | I16x8ExtmulHighI8x16U
+        | I32x4Add
+        | I32x4Sub
+        | I32x4Mul
+        | I32x4MinS
+        | I32x4MinU
+        | I32x4MaxS
+        | I32x4MaxU
+        | I32x4DotI16x8S
+        | I32x4ExtmulLowI16x8S
+        | I32x4ExtmulHighI16x8S
+        | I32x4ExtmulLowI16x8U
+        | I32x4ExtmulHighI16x8U
+        | I64x2Add
+        | I64x2Sub
+        | I64x2Mul
+        | I64x2Eq
+        | I64x2Ne
+        | I64x2LtS
+        | I64x2GtS
+        | I64x2LeS
+        | I64x2GeS
+        | I64x2ExtmulLowI32x4S
+        | I64x2ExtmulHighI32x4S
+        | I64x2ExtmulLowI32x4U
+        | I64x2ExtmulHighI32x4U
+        | F32x4Add
+        | F32x4Sub
+        | F32x4Mul
+        | F32x4Div
+        | F32x4Min
+        | F32x4Max
+        | F32x4Pmin
+        | F32x4Pmax
+        | F64x2Add
+        | F64x2Sub
+        | F64x2Mul
+        | F64x2Div
+        | F64x2Min
+        | F64x2Max
+        | F64x2Pmin
+        | F64x2Pmax => (&[V128, V128], Some(V128)),
+        I64x2Splat => (&[I64], Some(V128)),
+        F32x4Splat => (&[F32], Some(V128)),
+        F64x2Splat => (&[F64], Some(V128)),
+        I8x16ExtractLaneS(_) | I8x16ExtractLaneU(_) | I16x8ExtractLaneS(_)
+        | I16x8ExtractLaneU(_) | I32x4ExtractLane(_) | V128AnyTrue | I8x16AllTrue
+        | I8x16Bitmask | I16x8AllTrue | I16x8Bitmask | I32x4AllTrue | I32x4Bitmask
+        | I64x2AllTrue | I64x2Bitmask => (&[V128], Some(I32)),
+        I8x16ReplaceLane(_) | I16x8ReplaceLane(_) | I32x4ReplaceLane(_) | I8x16Shl | I8x16ShrS
+        | I8x16ShrU | I16x8Shl | I16x8ShrS | I16x8ShrU | I32x4Shl | I32x4ShrS | I32x4ShrU
+        | I64x2Shl | I64x2ShrS | I64x2ShrU => (&[V128, I32], Some(V128)),
+        I64x2ExtractLane(_) => (&[V128], Some(I64)),
+        I64x2ReplaceLane(_) => (&[V128, I64], Some(V128)),
+        F32x4ExtractLane(_) => (&[V128], Some(F32)),
+        F32x4ReplaceLane(_) => (&[V128, F32], Some(V128)),
+        F64x2ExtractLane(_) => (&[V128], Some(F64)),
+        F64x2ReplaceLane(_) => (&[V128, F64], Some(V128)),
+        V128Not
+        | F32x4DemoteF64x2Zero
+        | F64x2PromoteLowF32x4
+        | I8x16Abs
+        | I8x16Neg
+        | I8x16Popcnt
+        | F32x4Ceil
+        | F32x4Floor
+        | F32x4Trunc
+        | F32x4Nearest
+        | F64x2Ceil
+        | F64x2Floor
+        | F64x2Trunc
+        | I16x8ExtaddPairwiseI8x16S
+        | I16x8ExtaddPairwiseI8x16U
+        | I32x4ExtaddPairwiseI16x8S
+        | I32x4ExtaddPairwiseI16x8U
+        | I16x8Abs
+        | I16x8Neg
+        | I16x8ExtendLowI8x16S
+        | I16x8ExtendHighI8x16S
+        | I16x8ExtendLowI8x16U
+        | I16x8ExtendHighI8x16U
+        | F64x2Nearest
+        | I32x4Abs
+        | I32x4Neg
+        | I32x4ExtendLowI16x8S
+        | I32x4ExtendHighI16x8S
+        | I32x4ExtendLowI16x8U
+        | I32x4ExtendHighI16x8U
+        | I64x2Abs
+        | I64x2Neg
+        | I64x2ExtendLowI32x4S
+        | I64x2ExtendHighI32x4S
+        | I64x2ExtendLowI32x4U
+        | I64x2ExtendHighI32x4U
+        | F32x4Abs
+        | F32x4Neg
+        | F32x4Sqrt
+        | F64x2Abs
+        | F64x2Neg
+        | F64x2Sqrt
+        | I32x4TruncSatF32x4S
+        | I32x4TruncSatF32x4U
+        | F32x4ConvertI32x4S
+        | F32x4ConvertI32x4U
+        | I32x4TruncSatF64x2SZero
+        | I32x4TruncSatF64x2UZero
+        | F64x2ConvertLowI32x4S
+        | F64x2ConvertLowI32x4U => (&[V128], Some(V128)),
+        V128Bitselect => (&[V128, V128, V128], Some(V128)),
+        V128Load8Lane(_) | V128Load16Lane(_) | V128Load32Lane(_) | V128Load64Lane(_) => {
+            (&[I32, V128], Some(V128))
+        }
+    }
+}
+
+#[test]
+fn mismatch_in_else_branch() {
+    use crate::modules::Module;
+    use nom::error::VerboseError;
+    use wasm_core::values::Parse;
+    let file = [
+        0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, //header
+        0x01, 0x05, 0x01, 0x60, 0x00, 0x01, 0x7F, //type section: [] -> [i32]
+        0x03, 0x02, 0x01, 0x00, //function section
+        0x0A, 0x11, 0x01, 0x0F, 0x00, //code section, one body without locals
+        0x41, 0x00, 0x04, 0x7F, 0x41, 0x01,
+        0x05, //i32.const 0 if (result i32) i32.const 1 else
+        0x43, 0x00, 0x00, 0x00, 0x00, 0x0B, 0x0B, //f32.const 0 end end
+    ];
+    let (_, module) = Module::parse::<VerboseError<_>>(&file).unwrap();
+    let error = super::validate(&module).unwrap_err();
+    assert_eq!(
+        error.path,
+        Some(InstructionPath(vec![Step::Index(1), Step::Else]))
+    );
+    assert_eq!(
+        error.to_string(),
+        "code section, function 0, instruction 1.else: expected NumType(I32), found NumType(F32)"
+    );
+}
+#[test]
+fn typing_rules() {
+    use crate::{fixtures::module_bytes, modules::Module, text};
+    let valid = |source: &str| super::validate(&text::parse(source).unwrap()).is_ok();
+    let error = |source: &str| {
+        let module = text::parse(source).unwrap();
+        super::validate(&module).unwrap_err().to_string()
+    };
+
+    //every target of br_table takes the same values
+    let br_table = r#"
+        (module
+          (func (param i32) (result i32)
+            (block (result i32)
+              (block (result i32)
+                i32.const 1
+                local.get 0
+                br_table 0 1 0))))"#;
+    assert!(valid(br_table));
+    let arity = r#"
+        (module
+          (func (param i32) (result i32)
+            (block (result i32)
+              (block
+                i32.const 1
+                local.get 0
+                br_table 0 1))))"#;
+    assert!(error(arity).ends_with("label 0 has arity 0, default label has arity 1"));
+
+    //after unreachable any values can be popped, but pushed ones keep their type
+    let polymorphic = "(module (func (result i32) unreachable i32.add))";
+    assert!(valid(polymorphic));
+    let pushed = "(module (func (result i32) unreachable f32.const 0 i32.add))";
+    assert!(error(pushed).ends_with("expected NumType(I32), found NumType(F32)"));
+
+    //block types from the type section take params off the stack
+    let params = r#"
+        (module
+          (type (func (param i32 i32) (result i32)))
+          (func (result i32)
+            i32.const 1
+            i32.const 2
+            (block (type 0) i32.add)))"#;
+    assert!(valid(params));
+    let missing = r#"
+        (module
+          (type (func (param i32 i32) (result i32)))
+          (func (result i32)
+            i32.const 1
+            (block (type 0) i32.add)))"#;
+    assert!(error(missing).ends_with("instruction 1: value stack underflow"));
+
+    //calls pop the params and push the results of the callee
+    let call = r#"
+        (module
+          (func $add (param i32 i64) (result i64) local.get 1)
+          (func (result i64) i32.const 1 i64.const 2 call $add))"#;
+    assert!(valid(call));
+    let swapped = r#"
+        (module
+          (func $add (param i32 i64) (result i64) local.get 1)
+          (func (result i64) i64.const 2 i32.const 1 call $add))"#;
+    assert!(error(swapped).ends_with("instruction 2: expected NumType(I64), found NumType(I32)"));
+
+    //runs of billions of locals are typed without being expanded
+    let types: &[u8] = &[
+        0x01, 0x05, 0x01, 0x60, 0x00, 0x01, 0x7F, 0x03, 0x02, 0x01, 0x00,
+    ];
+    let body = |locals: &[u8], index: &[u8]| {
+        let body = [locals, &[0x20], index, &[0x0B]].concat();
+        [
+            &[0x0A, body.len() as u8 + 2, 0x01, body.len() as u8][..],
+            &body,
+        ]
+        .concat()
+    };
+    let max = [0xFF, 0xFF, 0xFF, 0xFF, 0x0F];
+    //4294967294 i32 locals, reading the last one
+    let many = body(
+        &[0x01, 0xFE, 0xFF, 0xFF, 0xFF, 0x0F, 0x7F],
+        &[0xFD, 0xFF, 0xFF, 0xFF, 0x0F],
+    );
+    let bytes = module_bytes(&[types, &many]);
+    let module = Module::from_bytes(&bytes).unwrap();
+    super::validate(&module).unwrap();
+    let too_many = body(
+        &[&[0x02][..], &max, &[0x7F], &max, &[0x7F]].concat(),
+        &[0x00],
+    );
+    let bytes = module_bytes(&[types, &too_many]);
+    let module = Module::from_bytes(&bytes).unwrap();
+    let error = super::validate(&module).unwrap_err();
+    assert!(error.to_string().ends_with("too many locals"));
+}